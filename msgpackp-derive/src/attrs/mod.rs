@@ -0,0 +1,196 @@
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr, Member, Result, Type,
+    spanned::Spanned,
+};
+
+/**
+ * @name Container
+ * @description
+ *
+ * A struct or enum with its #[msgpackp(...)] attributes parsed.
+ */
+pub struct Container<'a> {
+    pub input: &'a DeriveInput,
+    pub map: bool,
+    pub body: Body,
+}
+
+pub enum Body {
+    Struct(Shape),
+    Enum(Vec<Variant>),
+}
+
+pub struct Variant {
+    pub ident: Ident,
+    pub name: String,
+    pub shape: Shape,
+}
+
+/**
+ * @name Shape
+ * @description
+ *
+ * How the fields of a struct or variant are laid out.
+ */
+pub enum Shape {
+    Unit,
+
+    /*
+     * One unnamed field: encoded as the field itself
+     */
+    Newtype(Box<Field>),
+
+    /*
+     * Named or unnamed fields: an array, or a map of named fields
+     */
+    Fields { named: bool, fields: Vec<Field> },
+}
+
+pub struct Field {
+    pub member: Member,
+    pub ty: Type,
+    pub name: String,
+    pub skip: bool,
+    pub default: bool,
+}
+
+impl Shape {
+    /**
+     * @name fields
+     * @description
+     *
+     * Every field, skipped ones included.
+     */
+    pub fn fields(&self) -> &[Field] {
+        match self {
+            Shape::Unit => &[],
+            Shape::Newtype(field) => core::slice::from_ref(&**field),
+            Shape::Fields { fields, .. } => fields,
+        }
+    }
+
+    /**
+     * @name written
+     * @description
+     *
+     * Fields that go on the wire, in order.
+     */
+    pub fn written(&self) -> impl Iterator<Item = &Field> {
+        self.fields().iter().filter(|field| !field.skip)
+    }
+}
+
+impl<'a> Container<'a> {
+    pub fn from_ast(input: &'a DeriveInput) -> Result<Self> {
+        let mut map = false;
+
+        for attr in msgpackp_attrs(&input.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("map") {
+                    map = true;
+
+                    Ok(())
+                } else {
+                    Err(meta.error("Unknown container attribute, expected `map`"))
+                }
+            })?;
+        }
+
+        let body = match &input.data {
+            Data::Struct(data) => Body::Struct(shape(&data.fields)?),
+            Data::Enum(data) => Body::Enum(
+                data.variants
+                    .iter()
+                    .map(|variant| {
+                        let mut name = variant.ident.to_string();
+
+                        for attr in msgpackp_attrs(&variant.attrs) {
+                            attr.parse_nested_meta(|meta| {
+                                if meta.path.is_ident("rename") {
+                                    name = meta.value()?.parse::<LitStr>()?.value();
+
+                                    Ok(())
+                                } else {
+                                    Err(meta.error("Unknown variant attribute, expected `rename`"))
+                                }
+                            })?;
+                        }
+
+                        Ok(Variant {
+                            ident: variant.ident.clone(),
+                            name,
+                            shape: shape(&variant.fields)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            Data::Union(_) => {
+                return Err(Error::new(
+                    input.span(),
+                    "#[derive(MsgpackP)] doesn't support unions",
+                ));
+            }
+        };
+
+        Ok(Container { input, map, body })
+    }
+}
+
+pub fn msgpackp_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("msgpackp"))
+}
+
+pub fn shape(fields: &Fields) -> Result<Shape> {
+    let named = matches!(fields, Fields::Named(_));
+
+    let mut parsed = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let (member, mut name) = match &field.ident {
+                Some(ident) => (Member::Named(ident.clone()), ident.to_string()),
+                None => (Member::Unnamed(index.into()), index.to_string()),
+            };
+
+            let mut skip = false;
+            let mut default = false;
+
+            for attr in msgpackp_attrs(&field.attrs) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        name = meta.value()?.parse::<LitStr>()?.value();
+                    } else if meta.path.is_ident("skip") {
+                        skip = true;
+                    } else if meta.path.is_ident("default") {
+                        default = true;
+                    } else {
+                        return Err(meta.error(
+                            "Unknown field attribute, expected `rename`, `skip` or `default`",
+                        ));
+                    }
+
+                    Ok(())
+                })?;
+            }
+
+            Ok(Field {
+                member,
+                ty: field.ty.clone(),
+                name,
+                skip,
+                default,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(match fields {
+        Fields::Unit => Shape::Unit,
+        Fields::Unnamed(_) if parsed.len() == 1 && !parsed[0].skip => {
+            Shape::Newtype(Box::new(parsed.remove(0)))
+        }
+        _ => Shape::Fields {
+            named,
+            fields: parsed,
+        },
+    })
+}
@@ -0,0 +1,78 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, parse_macro_input};
+
+mod attrs;
+mod packet;
+mod read_from;
+mod write_to;
+
+/**
+ * @name MsgpackP
+ * @description
+ *
+ * Derives `WriteTo` and `ReadFrom` for a struct or enum, without going
+ * through `Value`.
+ *
+ * - Structs are arrays of their fields, `#[msgpackp(map)]` writes maps
+ *   keyed by field name instead. Tuple structs with one field are
+ *   written as that field, unit structs as nil.
+ * - Enums are externally tagged: unit variants are their name, others
+ *   are a single-entry map `{ name: content }`.
+ *
+ * Field attributes: `rename = "..."` (map key), `skip` (not written,
+ * read as `Default`) and `default` (may be missing: absent from a map,
+ * or a trailing array element). Variants take `rename` too.
+ *
+ * `ReadFrom` is implemented for the type's first lifetime, so `&'a str`
+ * fields borrow the buffer.
+ */
+#[proc_macro_derive(MsgpackP, attributes(msgpackp))]
+pub fn derive_msgpackp(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let container = match attrs::Container::from_ast(&input) {
+        Ok(container) => container,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let write_to = write_to::expand(&container);
+    let read_from = read_from::expand(&container);
+
+    quote! {
+        #write_to
+        #read_from
+    }
+    .into()
+}
+
+/**
+ * @name Packet
+ * @description
+ *
+ * Derives `WriteTo` and `ReadFrom` for an enum of packets, each one
+ * being the `[code, [args...]]` envelope:
+ *
+ * ```rust,ignore
+ * #[derive(Packet)]
+ * enum Incoming<'a> {
+ *     #[packet("ch")]
+ *     Chat(&'a str),
+ *
+ *     #[packet("M")]
+ *     Move(f64),
+ * }
+ * ```
+ *
+ * Fields are the positional arguments, decoding checks their count and
+ * types. Codes no variant handles fail with `Error::UnknownPacket`,
+ * which carries the whole packet.
+ */
+#[proc_macro_derive(Packet, attributes(packet, msgpackp))]
+pub fn derive_packet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    packet::expand(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Ident, LitStr, Result, spanned::Spanned};
+
+use crate::{
+    attrs::{Shape, shape},
+    read_from, write_to,
+};
+
+/*
+ * A variant with its packet code
+ */
+struct Packet {
+    ident: Ident,
+    code: LitStr,
+    shape: Shape,
+}
+
+/**
+ * @name expand
+ * @description
+ *
+ * Generates WriteTo and ReadFrom for an enum of packets, each one
+ * encoded as the `[code, [args...]]` envelope.
+ */
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "#[derive(Packet)] only supports enums",
+        ));
+    };
+
+    let mut codes = HashSet::new();
+
+    let packets = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let attr = variant
+                .attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("packet"))
+                .ok_or_else(|| {
+                    Error::new(
+                        variant.span(),
+                        "Every variant needs its code: #[packet(\"code\")]",
+                    )
+                })?;
+
+            let code = attr.parse_args::<LitStr>()?;
+
+            if !codes.insert(code.value()) {
+                return Err(Error::new(code.span(), "Duplicate packet code"));
+            }
+
+            Ok(Packet {
+                ident: variant.ident.clone(),
+                code,
+                shape: shape(&variant.fields)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let write_to = write_to::impl_block(input, write(&packets));
+    let read_from = read_from::impl_block(input, |_| read(&packets));
+
+    Ok(quote! {
+        #write_to
+        #read_from
+    })
+}
+
+fn write(packets: &[Packet]) -> TokenStream {
+    let arms = packets.iter().map(|packet| {
+        let ident = &packet.ident;
+        let code = &packet.code;
+
+        let (pattern, values) = write_to::bind(&packet.shape);
+        let len = values.len();
+
+        quote! {
+            Self::#ident #pattern => {
+                ::msgpackp::msgpack::array::write_array_header(2, __writer)?;
+                ::msgpackp::msgpack::WriteTo::write_to(#code, __writer)?;
+                ::msgpackp::msgpack::array::write_array_header(#len, __writer)?;
+                #(
+                    ::msgpackp::msgpack::WriteTo::write_to(#values, __writer)?;
+                )*
+            }
+        }
+    });
+
+    quote! {
+        match self {
+            #(#arms)*
+        }
+    }
+}
+
+fn read(packets: &[Packet]) -> TokenStream {
+    let codes = packets.iter().map(|packet| &packet.code);
+    let indices = 0..packets.len();
+
+    let arms = packets.iter().enumerate().map(|(index, packet)| {
+        let ident = &packet.ident;
+        let code = &packet.code;
+        let fields = packet.shape.fields();
+
+        let bindings = (0..fields.len())
+            .map(|index| format_ident!("__f{}", index))
+            .collect::<Vec<_>>();
+
+        let len = packet.shape.written().count();
+
+        /*
+         * Arguments are positional and type-checked by their ReadFrom
+         */
+        let reads = fields.iter().zip(&bindings).map(|(field, binding)| {
+            let ty = &field.ty;
+
+            if field.skip {
+                quote!(let #binding: #ty = ::core::default::Default::default();)
+            } else {
+                quote!(let #binding: #ty = ::msgpackp::msgpack::ReadFrom::read_from(__reader)?;)
+            }
+        });
+
+        let value = match &packet.shape {
+            Shape::Unit => quote!(Self::#ident),
+            Shape::Fields { named: true, .. } => {
+                let members = fields.iter().map(|field| &field.member);
+
+                quote!(Self::#ident { #(#members: #bindings),* })
+            }
+            _ => quote!(Self::#ident(#(#bindings),*)),
+        };
+
+        quote! {
+            #index => {
+                let __marker = ::msgpackp::derive::read_marker(__reader)?;
+                let __len = ::msgpackp::msgpack::array::read_array_len(__marker, __reader)?;
+
+                if __len != #len {
+                    return ::core::result::Result::Err(
+                        ::msgpackp::derive::packet_arity(#code, #len, __len),
+                    );
+                }
+
+                #(#reads)*
+
+                ::core::result::Result::Ok(#value)
+            }
+        }
+    });
+
+    quote! {
+        let __marker = ::msgpackp::derive::read_marker(__reader)?;
+
+        if ::msgpackp::msgpack::array::read_array_len(__marker, __reader)? != 2 {
+            return ::core::result::Result::Err(
+                ::msgpackp::error::Error::Message("A packet is a [code, [args]] array"),
+            );
+        }
+
+        let __index: usize = match ::msgpackp::derive::read_code(__reader)? {
+            #(::msgpackp::value::Value::Str(#codes) => #indices,)*
+            __code => {
+                let __code = ::msgpackp::derive::packet_code(__code);
+
+                return ::core::result::Result::Err(
+                    ::msgpackp::derive::unknown_packet(__code, __reader),
+                );
+            }
+        };
+
+        match __index {
+            #(#arms)*
+            _ => ::core::unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, GenericParam, Lifetime, LifetimeParam, LitByteStr, parse_quote};
+
+use crate::attrs::{Body, Container, Field, Shape};
+
+/**
+ * @name expand
+ * @description
+ *
+ * Generates the ReadFrom impl.
+ */
+pub fn expand(container: &Container) -> TokenStream {
+    impl_block(container.input, |lifetime| match &container.body {
+        Body::Struct(shape) => {
+            let value = read_shape(shape, container.map, lifetime, &quote!(Self));
+
+            quote!(::core::result::Result::Ok(#value))
+        }
+        Body::Enum(variants) => {
+            let names = variants
+                .iter()
+                .map(|variant| LitByteStr::new(variant.name.as_bytes(), variant.ident.span()));
+            let indices = 0..variants.len();
+
+            let arms = variants.iter().enumerate().map(|(index, variant)| {
+                    let variant_ident = &variant.ident;
+
+                    match &variant.shape {
+                        Shape::Unit => quote! {
+                            #index => {
+                                if __content {
+                                    ::msgpackp::derive::skip(__reader)?;
+                                }
+
+                                ::core::result::Result::Ok(Self::#variant_ident)
+                            }
+                        },
+                        shape => {
+                            let value = read_shape(
+                                shape,
+                                container.map,
+                                lifetime,
+                                &quote!(Self::#variant_ident),
+                            );
+
+                            quote! {
+                                #index => {
+                                    if !__content {
+                                        return ::core::result::Result::Err(
+                                            ::msgpackp::error::Error::Message("Expected the variant's content"),
+                                        );
+                                    }
+
+                                    ::core::result::Result::Ok(#value)
+                                }
+                            }
+                        }
+                    }
+                });
+
+            quote! {
+                let __marker = ::msgpackp::derive::read_marker(__reader)?;
+
+                let (__index, __content) = {
+                    let (__name, __content) =
+                        ::msgpackp::derive::read_variant(__marker, __reader)?;
+
+                    let __index: usize = match __name {
+                        #(#names => #indices,)*
+                        _ => {
+                            return ::core::result::Result::Err(
+                                ::msgpackp::derive::unknown_variant(__name),
+                            );
+                        }
+                    };
+
+                    (__index, __content)
+                };
+
+                match __index {
+                    #(#arms)*
+                    _ => ::core::unreachable!(),
+                }
+            }
+        }
+    })
+}
+
+/**
+ * @name impl_block
+ * @description
+ *
+ * Wraps a body that reads `Self` from `__reader`, marker included,
+ * into the ReadFrom impl.
+ *
+ * The impl is for the first lifetime of the type, which is the reader's
+ * buffer lifetime, so `&'a str` fields borrow the buffer. Types without
+ * one get a fresh lifetime.
+ */
+pub fn impl_block(input: &DeriveInput, body: impl FnOnce(&Lifetime) -> TokenStream) -> TokenStream {
+    let ident = &input.ident;
+
+    let mut generics = input.generics.clone();
+
+    let lifetime: Lifetime = match generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime: Lifetime = parse_quote!('__de);
+
+            generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+            );
+
+            lifetime
+        }
+    };
+
+    let lifetimes = generics
+        .lifetimes()
+        .map(|param| param.lifetime.clone())
+        .filter(|other| *other != lifetime)
+        .collect::<Vec<_>>();
+
+    let type_params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+
+    let where_clause = generics.make_where_clause();
+
+    for other in lifetimes {
+        where_clause
+            .predicates
+            .push(parse_quote!(#lifetime: #other));
+    }
+
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::msgpackp::msgpack::ReadFrom<#lifetime> + #lifetime));
+    }
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let body = body(&lifetime);
+
+    quote! {
+        impl #impl_generics ::msgpackp::msgpack::ReadFrom<#lifetime> for #ident #ty_generics #where_clause {
+            fn read_from(
+                __reader: &mut ::msgpackp::reader::Reader<#lifetime>,
+            ) -> ::msgpackp::error::Result<Self> {
+                #body
+            }
+        }
+    }
+}
+
+/*
+ * Block that reads the fields of a struct or variant and evaluates
+ * to `path { ... }`
+ */
+fn read_shape(shape: &Shape, map: bool, lifetime: &Lifetime, path: &TokenStream) -> TokenStream {
+    match shape {
+        Shape::Unit => quote! {{
+            let __marker = ::msgpackp::derive::read_marker(__reader)?;
+
+            if __marker != 0xc0 {
+                return ::core::result::Result::Err(
+                    ::msgpackp::error::Error::UnexpectedMarker(__marker),
+                );
+            }
+
+            #path
+        }},
+        Shape::Newtype(field) => {
+            let ty = &field.ty;
+
+            quote! {
+                #path(<#ty as ::msgpackp::msgpack::ReadFrom<#lifetime>>::read_from(__reader)?)
+            }
+        }
+        Shape::Fields { named, fields } => {
+            let bindings = (0..fields.len())
+                .map(|index| format_ident!("__f{}", index))
+                .collect::<Vec<_>>();
+
+            let reads = if map && *named {
+                read_map(fields, &bindings)
+            } else {
+                read_array(fields, &bindings)
+            };
+
+            let value = if *named {
+                let members = fields.iter().map(|field| &field.member);
+
+                quote!(#path { #(#members: #bindings),* })
+            } else {
+                quote!(#path(#(#bindings),*))
+            };
+
+            quote! {{
+                let __marker = ::msgpackp::derive::read_marker(__reader)?;
+
+                #reads
+
+                #value
+            }}
+        }
+    }
+}
+
+/*
+ * Fields in order, trailing `default` fields may be missing and
+ * extra elements are skipped
+ */
+fn read_array(fields: &[Field], bindings: &[proc_macro2::Ident]) -> TokenStream {
+    let mut position = 0usize;
+
+    let reads = fields.iter().zip(bindings).map(|(field, binding)| {
+        let ty = &field.ty;
+
+        if field.skip {
+            return quote! {
+                let #binding: #ty = ::core::default::Default::default();
+            };
+        }
+
+        let index = position;
+        let missing = missing(field);
+
+        position += 1;
+
+        quote! {
+            let #binding: #ty = if #index < __len {
+                ::msgpackp::msgpack::ReadFrom::read_from(__reader)?
+            } else {
+                #missing
+            };
+        }
+    });
+
+    let reads = reads.collect::<Vec<_>>();
+    let written = position;
+
+    quote! {
+        let __len = ::msgpackp::msgpack::array::read_array_len(__marker, __reader)?;
+
+        #(#reads)*
+
+        for _ in #written..__len {
+            ::msgpackp::derive::skip(__reader)?;
+        }
+    }
+}
+
+/*
+ * Entries in any order, unknown and non-string keys are skipped
+ */
+fn read_map(fields: &[Field], bindings: &[proc_macro2::Ident]) -> TokenStream {
+    let written = fields
+        .iter()
+        .zip(bindings)
+        .enumerate()
+        .filter(|(_, (field, _))| !field.skip)
+        .collect::<Vec<_>>();
+
+    let slots = written.iter().map(|(_, (field, binding))| {
+        let ty = &field.ty;
+
+        quote!(let mut #binding: ::core::option::Option<#ty> = ::core::option::Option::None;)
+    });
+
+    let keys = written.iter().map(|(_, (field, _))| {
+        LitByteStr::new(field.name.as_bytes(), proc_macro2::Span::call_site())
+    });
+    let indices = written.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+    let assigned = written.iter().map(|(_, (_, binding))| binding);
+
+    let finishes = fields.iter().zip(bindings).map(|(field, binding)| {
+        let ty = &field.ty;
+
+        if field.skip {
+            return quote! {
+                let #binding: #ty = ::core::default::Default::default();
+            };
+        }
+
+        let missing = missing(field);
+
+        quote! {
+            let #binding: #ty = match #binding {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => #missing,
+            };
+        }
+    });
+
+    quote! {
+        let __len = ::msgpackp::msgpack::map::read_map_len(__marker, __reader)?;
+
+        #(#slots)*
+
+        for _ in 0..__len {
+            let __index: usize = match ::msgpackp::derive::read_key(__reader)? {
+                #(::core::option::Option::Some(#keys) => #indices,)*
+                ::core::option::Option::Some(_) => usize::MAX,
+                ::core::option::Option::None => continue,
+            };
+
+            match __index {
+                #(#indices => #assigned = ::core::option::Option::Some(::msgpackp::msgpack::ReadFrom::read_from(__reader)?),)*
+                _ => ::msgpackp::derive::skip(__reader)?,
+            }
+        }
+
+        #(#finishes)*
+    }
+}
+
+fn missing(field: &Field) -> TokenStream {
+    if field.default {
+        return quote!(::core::default::Default::default());
+    }
+
+    let name = &field.name;
+
+    quote! {
+        return ::core::result::Result::Err(::msgpackp::derive::missing_field(#name))
+    }
+}
@@ -0,0 +1,180 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, Member, parse_quote};
+
+use crate::attrs::{Body, Container, Shape};
+
+/**
+ * @name expand
+ * @description
+ *
+ * Generates the WriteTo impl.
+ */
+pub fn expand(container: &Container) -> TokenStream {
+    let body = match &container.body {
+        Body::Struct(shape) => {
+            let values = shape
+                .written()
+                .map(|field| {
+                    let member = &field.member;
+
+                    quote!(&self.#member)
+                })
+                .collect::<Vec<_>>();
+
+            write_shape(shape, container.map, &values)
+        }
+        Body::Enum(variants) => {
+            let arms = variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                let name = &variant.name;
+
+                let (pattern, values) = bind(&variant.shape);
+
+                /*
+                 * Externally tagged: unit variants are their name,
+                 * anything else is { name: content }
+                 */
+                let content = match &variant.shape {
+                    Shape::Unit => quote! {
+                        ::msgpackp::msgpack::WriteTo::write_to(#name, __writer)?;
+                    },
+                    shape => {
+                        let content = write_shape(shape, container.map, &values);
+
+                        quote! {
+                            ::msgpackp::msgpack::map::write_map_header(1, __writer)?;
+                            ::msgpackp::msgpack::WriteTo::write_to(#name, __writer)?;
+                            #content
+                        }
+                    }
+                };
+
+                quote! {
+                    Self::#variant_ident #pattern => {
+                        #content
+                    }
+                }
+            });
+
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    };
+
+    impl_block(container.input, body)
+}
+
+/**
+ * @name impl_block
+ * @description
+ *
+ * Wraps a body that writes `self` into `__writer` into the WriteTo impl.
+ */
+pub fn impl_block(input: &DeriveInput, body: TokenStream) -> TokenStream {
+    let ident = &input.ident;
+
+    let mut generics = input.generics.clone();
+
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::msgpackp::msgpack::WriteTo));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::msgpackp::msgpack::WriteTo for #ident #ty_generics #where_clause {
+            fn write_to<__W: ::msgpackp::io::Write>(
+                &self,
+                __writer: &mut __W,
+            ) -> ::msgpackp::error::Result<()> {
+                #body
+
+                ::core::result::Result::Ok(())
+            }
+        }
+    }
+}
+
+/*
+ * Writes the fields of a struct or variant, `values` are references
+ * to the written ones
+ */
+fn write_shape(shape: &Shape, map: bool, values: &[TokenStream]) -> TokenStream {
+    match shape {
+        Shape::Unit => quote! {
+            ::msgpackp::io::Write::write_all(__writer, &[0xc0])?;
+        },
+        Shape::Newtype(_) => {
+            let value = &values[0];
+
+            quote! {
+                ::msgpackp::msgpack::WriteTo::write_to(#value, __writer)?;
+            }
+        }
+        Shape::Fields { named, .. } => {
+            let len = values.len();
+
+            if map && *named {
+                let keys = shape.written().map(|field| &field.name);
+
+                quote! {
+                    ::msgpackp::msgpack::map::write_map_header(#len, __writer)?;
+                    #(
+                        ::msgpackp::msgpack::WriteTo::write_to(#keys, __writer)?;
+                        ::msgpackp::msgpack::WriteTo::write_to(#values, __writer)?;
+                    )*
+                }
+            } else {
+                quote! {
+                    ::msgpackp::msgpack::array::write_array_header(#len, __writer)?;
+                    #(
+                        ::msgpackp::msgpack::WriteTo::write_to(#values, __writer)?;
+                    )*
+                }
+            }
+        }
+    }
+}
+
+/*
+ * Pattern that binds the written fields of a variant, and the bindings
+ */
+pub fn bind(shape: &Shape) -> (TokenStream, Vec<TokenStream>) {
+    match shape {
+        Shape::Unit => (quote!(), Vec::new()),
+        Shape::Newtype(_) => (quote!((__f0)), vec![quote!(__f0)]),
+        Shape::Fields { named, fields } => {
+            let mut patterns = Vec::new();
+            let mut values = Vec::new();
+
+            for (index, field) in fields.iter().enumerate() {
+                let binding = format_ident!("__f{}", index);
+
+                match (&field.member, field.skip) {
+                    (Member::Named(ident), false) => patterns.push(quote!(#ident: #binding)),
+                    (Member::Named(_), true) => continue,
+                    (Member::Unnamed(_), false) => patterns.push(quote!(#binding)),
+                    (Member::Unnamed(_), true) => {
+                        patterns.push(quote!(_));
+
+                        continue;
+                    }
+                }
+
+                values.push(quote!(#binding));
+            }
+
+            if *named {
+                (quote!({ #(#patterns,)* .. }), values)
+            } else {
+                (quote!((#(#patterns),*)), values)
+            }
+        }
+    }
+}
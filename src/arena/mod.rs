@@ -0,0 +1,340 @@
+use ::bumpalo::{Bump, collections::Vec as BumpVec};
+use ordered_float::OrderedFloat;
+
+use crate::{
+    constants::Families,
+    error::{Error, Result},
+    io::Write,
+    msgpack::{
+        WriteTo,
+        array::{Array, read_array_len, write_array_header},
+        bin::write_bin_header,
+        ext::{Extension, read_ext_header, write_ext_header},
+        map::{read_map_len, write_map_header},
+    },
+    reader::Reader,
+    value::Value,
+};
+
+/**
+ * @name ArenaValue
+ * @description
+ *
+ * A Value whose arrays and maps live in a bumpalo arena.
+ *
+ * Strings, binaries and ext payloads borrow the input like in Value,
+ * containers borrow the arena, so a whole tree is freed at once by
+ * resetting the arena instead of dropping it node by node.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArenaValue<'a> {
+    Nil,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(OrderedFloat<f32>),
+    F64(OrderedFloat<f64>),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Str(&'a str),
+    Bin(&'a [u8]),
+    Array(&'a [ArenaValue<'a>]),
+    Map(&'a [(ArenaValue<'a>, ArenaValue<'a>)]),
+    Extension(u8, &'a [u8]),
+}
+
+impl<'a> ArenaValue<'a> {
+    /**
+     * @name to_value
+     * @description
+     *
+     * Copies the containers out of the arena into a Value.
+     */
+    pub fn to_value(&self) -> Value<'a> {
+        match *self {
+            ArenaValue::Nil => Value::Nil,
+            ArenaValue::Bool(value) => Value::Bool(value),
+            ArenaValue::U8(value) => Value::U8(value),
+            ArenaValue::U16(value) => Value::U16(value),
+            ArenaValue::U32(value) => Value::U32(value),
+            ArenaValue::U64(value) => Value::U64(value),
+            ArenaValue::F32(value) => Value::F32(value),
+            ArenaValue::F64(value) => Value::F64(value),
+            ArenaValue::I8(value) => Value::I8(value),
+            ArenaValue::I16(value) => Value::I16(value),
+            ArenaValue::I32(value) => Value::I32(value),
+            ArenaValue::I64(value) => Value::I64(value),
+            ArenaValue::Str(value) => Value::Str(value),
+            ArenaValue::Bin(value) => Value::Bin(value),
+            ArenaValue::Array(values) => {
+                Value::Array(values.iter().map(ArenaValue::to_value).collect())
+            }
+            ArenaValue::Map(entries) => Value::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.to_value(), value.to_value()))
+                    .collect(),
+            ),
+            ArenaValue::Extension(type_, data) => {
+                Value::Extension(Extension::new(type_, data.to_vec()))
+            }
+        }
+    }
+}
+
+impl<'buf> Reader<'buf> {
+    /**
+     * @name pull_value_in
+     * @description
+     *
+     * Reads a value like `pull_value`, with its arrays and maps allocated
+     * in `arena`.
+     *
+     * ```
+     * use bumpalo::Bump;
+     * use msgpackp::{arena::ArenaValue, reader::Reader};
+     *
+     * let mut arena = Bump::new();
+     *
+     * for _tick in 0..3 {
+     *     let packet = [0x92, 0xa2, b'c', b'h', 0x91, 0x2a];
+     *     let value = Reader::new(&packet).pull_value_in(&arena).unwrap();
+     *
+     *     assert!(matches!(value, ArenaValue::Array([ArenaValue::Str("ch"), _])));
+     *
+     *     arena.reset();
+     * }
+     * ```
+     */
+    pub fn pull_value_in<'a>(&mut self, arena: &'a Bump) -> Result<ArenaValue<'a>>
+    where
+        'buf: 'a,
+    {
+        let packet_type = self.pull_marker()?;
+
+        Ok(match packet_type {
+            0x90..=0x9f | Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
+                let len = read_array_len(packet_type, self)?;
+                let len = self.container_len(self.limits().check_array(len)?)?;
+
+                self.nested(|reader| {
+                    let mut values = BumpVec::with_capacity_in(len, arena);
+
+                    for _ in 0..len {
+                        values.push(reader.pull_value_in(arena)?);
+                    }
+
+                    Ok(ArenaValue::Array(values.into_bump_slice()))
+                })?
+            }
+
+            0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+                let len = read_map_len(packet_type, self)?;
+                let len = self.container_len(self.limits().check_map(len)?)?;
+
+                self.nested(|reader| {
+                    let mut entries = BumpVec::with_capacity_in(len, arena);
+
+                    for _ in 0..len {
+                        entries.push((reader.pull_value_in(arena)?, reader.pull_value_in(arena)?));
+                    }
+
+                    Ok(ArenaValue::Map(entries.into_bump_slice()))
+                })?
+            }
+
+            0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+                ArenaValue::Str(self.pull_string(packet_type))
+            }
+
+            Families::BIN8 | Families::BIN16 | Families::BIN32 => {
+                ArenaValue::Bin(self.pull_bin(packet_type)?)
+            }
+
+            Families::FIXEXT1
+            | Families::FIXEXT2
+            | Families::FIXEXT4
+            | Families::FIXEXT8
+            | Families::FIXEXT16
+            | Families::EXT8
+            | Families::EXT16
+            | Families::EXT32 => {
+                let (type_, len) = read_ext_header(packet_type, self)?;
+
+                ArenaValue::Extension(type_, self.pull_borrowed(len)?)
+            }
+
+            _ => match self.pull_value_of(packet_type)? {
+                Value::Nil => ArenaValue::Nil,
+                Value::Bool(value) => ArenaValue::Bool(value),
+                Value::U8(value) => ArenaValue::U8(value),
+                Value::U16(value) => ArenaValue::U16(value),
+                Value::U32(value) => ArenaValue::U32(value),
+                Value::U64(value) => ArenaValue::U64(value),
+                Value::F32(value) => ArenaValue::F32(value),
+                Value::F64(value) => ArenaValue::F64(value),
+                Value::I8(value) => ArenaValue::I8(value),
+                Value::I16(value) => ArenaValue::I16(value),
+                Value::I32(value) => ArenaValue::I32(value),
+                Value::I64(value) => ArenaValue::I64(value),
+                _ => return Err(Error::UnexpectedMarker(packet_type)),
+            },
+        })
+    }
+}
+
+impl WriteTo for ArenaValue<'_> {
+    fn write_to<U: Write>(&self, buffer: &mut U) -> Result<()> {
+        match self {
+            ArenaValue::Array(values) => {
+                write_array_header(values.len(), buffer)?;
+
+                for value in values.iter() {
+                    value.write_to(buffer)?;
+                }
+            }
+            ArenaValue::Map(entries) => {
+                write_map_header(entries.len(), buffer)?;
+
+                for (key, value) in entries.iter() {
+                    key.write_to(buffer)?;
+                    value.write_to(buffer)?;
+                }
+            }
+            ArenaValue::Bin(value) => {
+                write_bin_header(value.len(), buffer)?;
+                buffer.write_all(value)?;
+            }
+            ArenaValue::Extension(type_, data) => {
+                write_ext_header(*type_, data.len(), buffer)?;
+                buffer.write_all(data)?;
+            }
+            scalar => scalar.to_value().write_to(buffer)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+    use crate::reader::Limits;
+
+    fn packet() -> Vec<u8> {
+        let mut buffer = Vec::new();
+
+        (
+            "update",
+            vec![(1u16, -5i32, 1.5f64), (300, 70000, -0.25)],
+            [("hp", 100u8), ("xp", 7)]
+                .into_iter()
+                .collect::<BTreeMap<_, _>>(),
+            (Option::<u8>::None, &b"raw"[..], String::from("tail")),
+            Extension::new(3, vec![1, 2, 3, 4]),
+        )
+            .write_to(&mut buffer)
+            .unwrap();
+
+        buffer
+    }
+
+    #[test]
+    fn test_matches_pull_value() {
+        let buffer = packet();
+        let arena = Bump::new();
+
+        let value = Reader::new(&buffer).pull_value_in(&arena).unwrap();
+
+        assert_eq!(value.to_value(), Reader::new(&buffer).pull_value().unwrap());
+
+        let mut encoded = Vec::new();
+
+        value.write_to(&mut encoded).unwrap();
+
+        assert_eq!(encoded, buffer);
+    }
+
+    #[test]
+    fn test_reset() {
+        let buffer = packet();
+        let mut arena = Bump::new();
+
+        Reader::new(&buffer).pull_value_in(&arena).unwrap();
+        arena.reset();
+        Reader::new(&buffer).pull_value_in(&arena).unwrap();
+
+        let allocated = arena.allocated_bytes();
+
+        /*
+         * Once it fits a tick, a reset arena reuses its chunk forever
+         */
+        for _ in 0..100 {
+            arena.reset();
+
+            let mut reader = Reader::new(&buffer);
+
+            assert!(matches!(
+                reader.pull_value_in(&arena).unwrap(),
+                ArenaValue::Array([ArenaValue::Str("update"), ..])
+            ));
+        }
+
+        assert_eq!(arena.allocated_bytes(), allocated);
+    }
+
+    #[test]
+    fn test_errors() {
+        let buffer = packet();
+        let arena = Bump::new();
+
+        assert!(
+            Reader::new(&buffer[..buffer.len() - 1])
+                .pull_value_in(&arena)
+                .is_err()
+        );
+
+        /*
+         * A huge length is refused before anything is allocated, even
+         * without limits
+         */
+        assert!(matches!(
+            Reader::with_limits(&[0xdd, 0xff, 0xff, 0xff, 0xff, 0xc0], Limits::UNLIMITED)
+                .pull_value_in(&arena),
+            Err(Error::UnexpectedEof("container"))
+        ));
+
+        let limits = Limits {
+            max_map_len: 1,
+            ..Limits::DEFAULT
+        };
+
+        assert!(matches!(
+            Reader::with_limits(&buffer, limits).pull_value_in(&arena),
+            Err(Error::LimitExceeded("Map"))
+        ));
+
+        /*
+         * Deep nesting stops at max_depth instead of the end of the stack
+         */
+        let mut deep = vec![0x91; 1_000_000];
+
+        deep.push(0xc0);
+
+        assert!(matches!(
+            Reader::new(&deep).pull_value_in(&arena),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+        assert!(
+            Reader::new(&deep[1_000_000 - 64..])
+                .pull_value_in(&arena)
+                .is_ok()
+        );
+    }
+}
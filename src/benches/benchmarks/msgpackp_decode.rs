@@ -1,28 +1,35 @@
 use criterion::{Criterion, criterion_group};
 use std::hint::black_box;
 
-use msgpackp::reader::Reader;
+use msgpackp::{reader::Reader, tape::Tape};
+
+const PACKET: [u8; 92] = [
+    135, 163, 105, 110, 116, 1, 165, 102, 108, 111, 97, 116, 203, 63, 224, 0, 0, 0, 0, 0, 0, 167,
+    98, 111, 111, 108, 101, 97, 110, 195, 164, 110, 117, 108, 108, 192, 166, 115, 116, 114, 105,
+    110, 103, 167, 102, 111, 111, 32, 98, 97, 114, 165, 97, 114, 114, 97, 121, 146, 163, 102, 111,
+    111, 163, 98, 97, 114, 166, 111, 98, 106, 101, 99, 116, 130, 163, 102, 111, 111, 1, 163, 98,
+    97, 122, 203, 63, 224, 0, 0, 0, 0, 0, 0,
+];
 
 fn bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("Msgpack decode");
 
     group.bench_function("Decode", |b| {
         b.iter(move || {
-            let packet = &[
-                135, 163, 105, 110, 116, 1, 165, 102, 108, 111, 97, 116, 203, 63, 224, 0, 0, 0, 0,
-                0, 0, 167, 98, 111, 111, 108, 101, 97, 110, 195, 164, 110, 117, 108, 108, 192, 166,
-                115, 116, 114, 105, 110, 103, 167, 102, 111, 111, 32, 98, 97, 114, 165, 97, 114,
-                114, 97, 121, 146, 163, 102, 111, 111, 163, 98, 97, 114, 166, 111, 98, 106, 101,
-                99, 116, 130, 163, 102, 111, 111, 1, 163, 98, 97, 122, 203, 63, 224, 0, 0, 0, 0, 0,
-                0,
-            ];
-
-            let mut reader = Reader::new(packet);
+            let mut reader = Reader::new(&PACKET);
 
             let _ = black_box(reader.pull_value());
         });
     });
 
+    group.bench_function("Decode (tape)", |b| {
+        let mut tape = Tape::new();
+
+        b.iter(|| {
+            let _ = black_box(tape.parse(&PACKET).map(|root| root.len()));
+        });
+    });
+
     group.finish();
 }
 
@@ -22,7 +22,7 @@ fn bench(c: &mut Criterion) {
             let mut buffer = [0u8; 70];
             let mut slice = &mut buffer[..];
 
-            black_box(packet.write_to(&mut slice).unwrap());
+            black_box(packet.write_to(&mut slice)).unwrap();
         });
     });
 
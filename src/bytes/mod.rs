@@ -0,0 +1,421 @@
+use alloc::vec::Vec;
+use core::{fmt, ops::Deref};
+
+use ::bytes::{BufMut, Bytes};
+use ordered_float::OrderedFloat;
+
+use crate::{
+    constants::Families,
+    error::{Error, Result},
+    io::Write,
+    msgpack::{
+        WriteTo,
+        array::{Array, read_array_len, write_array_header},
+        bin::write_bin_header,
+        ext::{read_ext_header, write_ext_header},
+        map::{read_map_len, write_map_header},
+        string::write_str_header,
+    },
+    reader::{Limits, Reader},
+    value::Value,
+};
+
+/**
+ * @name BufMutWriter
+ * @description
+ *
+ * Sink that writes straight into a bytes::BufMut (BytesMut, Vec<u8>, ...)
+ * without going through std::io::Write.
+ */
+pub struct BufMutWriter<B: BufMut>(pub B);
+
+impl<B: BufMut> Write for BufMutWriter<B> {
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        if self.0.remaining_mut() < buf.len() {
+            return Err(Error::WriteZero);
+        }
+
+        self.0.put_slice(buf);
+
+        Ok(())
+    }
+}
+
+/**
+ * @name write_to_buf
+ * @description
+ *
+ * Encodes a value into a BufMut.
+ *
+ * ```rust
+ * use bytes::BytesMut;
+ * use msgpackp::{bytes::write_to_buf, value::Value};
+ *
+ * let mut buffer = BytesMut::new();
+ *
+ * write_to_buf(&Value::Str("hai"), &mut buffer).unwrap();
+ *
+ * assert_eq!(&buffer[..], &[0xa3, b'h', b'a', b'i']);
+ * ```
+ */
+#[inline(always)]
+pub fn write_to_buf<T: WriteTo + ?Sized, B: BufMut>(value: &T, buf: &mut B) -> Result<()> {
+    value.write_to(&mut BufMutWriter(buf))
+}
+
+/**
+ * @name Utf8Bytes
+ * @description
+ *
+ * A Bytes slice that is known to hold valid UTF-8.
+ */
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Utf8Bytes(Bytes);
+
+impl Utf8Bytes {
+    #[inline(always)]
+    pub fn from_static(value: &'static str) -> Self {
+        Utf8Bytes(Bytes::from_static(value.as_bytes()))
+    }
+
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        /*
+         * Safety: only built from &str or validated slices
+         */
+        unsafe { core::str::from_utf8_unchecked(&self.0) }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    #[inline(always)]
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for Utf8Bytes {
+    type Target = str;
+
+    #[inline(always)]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for Utf8Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Utf8Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+/**
+ * @name BytesValue
+ * @description
+ *
+ * A 'static Value whose strings, binaries and ext payloads are
+ * zero-copy slices of a shared Bytes buffer.
+ *
+ * Unlike Value, it doesn't borrow the input slice, so decoded packets
+ * can be moved across tasks freely.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BytesValue {
+    Nil,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(OrderedFloat<f32>),
+    F64(OrderedFloat<f64>),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Str(Utf8Bytes),
+    Bin(Bytes),
+    Array(Vec<BytesValue>),
+    Map(Vec<(BytesValue, BytesValue)>),
+    Extension(u8, Bytes),
+}
+
+impl BytesValue {
+    /**
+     * @name decode
+     * @description
+     *
+     * Decodes the first value of `bytes`.
+     *
+     * Strings, binaries and ext payloads point into `bytes` instead of being copied.
+     */
+    #[inline]
+    pub fn decode(bytes: &Bytes) -> Result<Self> {
+        BytesValue::decode_with(bytes, Limits::DEFAULT)
+    }
+
+    /**
+     * @name decode_with
+     * @description
+     *
+     * `decode` with custom limits on container lengths and nesting.
+     */
+    pub fn decode_with(bytes: &Bytes, limits: Limits) -> Result<Self> {
+        let mut reader = Reader::with_limits(&bytes[..], limits);
+
+        decode_value(bytes, &mut reader)
+    }
+}
+
+fn decode_value(bytes: &Bytes, reader: &mut Reader<'_>) -> Result<BytesValue> {
+    let Some(&packet_type) = reader.pull(1).first() else {
+        return Err(Error::UnexpectedEof("marker"));
+    };
+
+    Ok(match packet_type {
+        0x90..=0x9f | Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
+            let len = read_array_len(packet_type, reader)?;
+            let len = reader.limits().check_array(len)?;
+
+            /*
+             * No preallocation: the length comes from the wire
+             */
+            reader.nested(|reader| {
+                let mut values = Vec::new();
+
+                for _ in 0..len {
+                    values.push(decode_value(bytes, reader)?);
+                }
+
+                Ok(BytesValue::Array(values))
+            })?
+        }
+
+        0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+            let len = read_map_len(packet_type, reader)?;
+            let len = reader.limits().check_map(len)?;
+
+            reader.nested(|reader| {
+                let mut values = Vec::new();
+
+                for _ in 0..len {
+                    values.push((decode_value(bytes, reader)?, decode_value(bytes, reader)?));
+                }
+
+                Ok(BytesValue::Map(values))
+            })?
+        }
+
+        0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+            /*
+             * Utf8Bytes promises valid UTF-8, so no lenient fallback here
+             */
+            let value = reader.pull_borrowed_str(packet_type)?;
+
+            BytesValue::Str(Utf8Bytes(bytes.slice_ref(value.as_bytes())))
+        }
+
+        Families::BIN8 | Families::BIN16 | Families::BIN32 => {
+            BytesValue::Bin(bytes.slice_ref(reader.pull_bin(packet_type)?))
+        }
+
+        Families::FIXEXT1
+        | Families::FIXEXT2
+        | Families::FIXEXT4
+        | Families::FIXEXT8
+        | Families::FIXEXT16
+        | Families::EXT8
+        | Families::EXT16
+        | Families::EXT32 => {
+            let (type_, len) = read_ext_header(packet_type, reader)?;
+            let data = reader.pull(len);
+
+            if data.len() != len {
+                return Err(Error::UnexpectedEof("Extension"));
+            }
+
+            BytesValue::Extension(type_, bytes.slice_ref(data))
+        }
+
+        _ => match reader.pull_value_of(packet_type)? {
+            Value::Nil => BytesValue::Nil,
+            Value::Bool(value) => BytesValue::Bool(value),
+            Value::U8(value) => BytesValue::U8(value),
+            Value::U16(value) => BytesValue::U16(value),
+            Value::U32(value) => BytesValue::U32(value),
+            Value::U64(value) => BytesValue::U64(value),
+            Value::F32(value) => BytesValue::F32(value),
+            Value::F64(value) => BytesValue::F64(value),
+            Value::I8(value) => BytesValue::I8(value),
+            Value::I16(value) => BytesValue::I16(value),
+            Value::I32(value) => BytesValue::I32(value),
+            Value::I64(value) => BytesValue::I64(value),
+            _ => return Err(Error::UnexpectedMarker(packet_type)),
+        },
+    })
+}
+
+impl WriteTo for BytesValue {
+    fn write_to<U: Write>(&self, buffer: &mut U) -> Result<()> {
+        match self {
+            BytesValue::Nil => buffer.write_all(&[Families::NIL])?,
+            BytesValue::Bool(value) => value.write_to(buffer)?,
+            BytesValue::U8(value) => value.write_to(buffer)?,
+            BytesValue::U16(value) => value.write_to(buffer)?,
+            BytesValue::U32(value) => value.write_to(buffer)?,
+            BytesValue::U64(value) => value.write_to(buffer)?,
+            BytesValue::F32(value) => value.write_to(buffer)?,
+            BytesValue::F64(value) => value.write_to(buffer)?,
+            BytesValue::I8(value) => value.write_to(buffer)?,
+            BytesValue::I16(value) => value.write_to(buffer)?,
+            BytesValue::I32(value) => value.write_to(buffer)?,
+            BytesValue::I64(value) => value.write_to(buffer)?,
+            BytesValue::Str(value) => {
+                write_str_header(value.len(), buffer)?;
+                buffer.write_all(value.as_bytes())?
+            }
+            BytesValue::Bin(value) => {
+                write_bin_header(value.len(), buffer)?;
+                buffer.write_all(value)?
+            }
+            BytesValue::Array(values) => {
+                write_array_header(values.len(), buffer)?;
+
+                for value in values {
+                    value.write_to(buffer)?;
+                }
+            }
+            BytesValue::Map(values) => {
+                write_map_header(values.len(), buffer)?;
+
+                for (key, value) in values {
+                    key.write_to(buffer)?;
+                    value.write_to(buffer)?;
+                }
+            }
+            BytesValue::Extension(type_, data) => {
+                write_ext_header(*type_, data.len(), buffer)?;
+                buffer.write_all(data)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::bytes::BytesMut;
+    use alloc::vec;
+
+    #[test]
+    fn test_zero_copy_decode() {
+        let mut buffer = BytesMut::new();
+
+        let packet = vec![
+            Value::Str("ch"),
+            Value::Array(vec![Value::Bin(&[1, 2, 3]), Value::U8(1)].into_boxed_slice()),
+        ]
+        .into_boxed_slice();
+
+        write_to_buf(&packet, &mut buffer).unwrap();
+
+        let bytes = buffer.freeze();
+        let value = BytesValue::decode(&bytes).unwrap();
+
+        let BytesValue::Array(values) = &value else {
+            panic!("Expected an array, got {value:?}");
+        };
+
+        let BytesValue::Str(code) = &values[0] else {
+            panic!("Expected a string");
+        };
+
+        /*
+         * The decoded string points into the original buffer
+         */
+        assert_eq!(code.as_str(), "ch");
+        assert_eq!(code.as_ptr(), bytes[2..].as_ptr());
+
+        assert_eq!(
+            values[1],
+            BytesValue::Array(vec![
+                BytesValue::Bin(Bytes::from_static(&[1, 2, 3])),
+                BytesValue::U8(1)
+            ])
+        );
+
+        /*
+         * Decoded values outlive any reader and round-trip
+         */
+        let mut encoded = BytesMut::new();
+
+        write_to_buf(&value, &mut encoded).unwrap();
+
+        assert_eq!(encoded.freeze(), bytes);
+    }
+
+    #[test]
+    fn test_bad_str() {
+        /*
+         * Truncated, then invalid UTF-8
+         */
+        for packet in [&[0xa3, b'h', b'i'][..], &[0xa2, 0xff, 0xfe][..]] {
+            assert!(BytesValue::decode(&Bytes::copy_from_slice(packet)).is_err());
+        }
+
+        assert!(BytesValue::decode(&Bytes::from_static(&[0x91, 0xd9, 0x05, b'a'])).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut deep = vec![0x91; 1_000_000];
+
+        deep.push(0xc0);
+
+        assert!(matches!(
+            BytesValue::decode(&deep.into()),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        let limits = Limits {
+            max_array_len: 1,
+            max_map_len: 1,
+            max_depth: 1,
+        };
+
+        for (packet, error) in [
+            (&[0x92, 0x01, 0x02][..], "Array"),
+            (&[0x82, 0x01, 0x02, 0x03, 0x04][..], "Map"),
+            (&[0x91, 0x91, 0x01][..], "Depth"),
+        ] {
+            assert!(matches!(
+                BytesValue::decode_with(&Bytes::copy_from_slice(packet), limits),
+                Err(Error::LimitExceeded(limit)) if limit == error
+            ));
+        }
+
+        assert_eq!(
+            BytesValue::decode_with(&Bytes::from_static(&[0x91, 0x01]), limits).unwrap(),
+            BytesValue::Array(vec![BytesValue::U8(1)])
+        );
+    }
+
+    #[test]
+    fn test_full_buf() {
+        let mut buffer = [0u8; 2];
+
+        assert!(write_to_buf(&Value::Str("hai"), &mut &mut buffer[..]).is_err());
+    }
+}
@@ -0,0 +1,537 @@
+use alloc::{boxed::Box, format, string::String, vec};
+
+use crate::{
+    constants::Families,
+    error::{Error, Result},
+    msgpack::{map::read_map_len, string::read_str_len},
+    reader::Reader,
+    value::{OwnedValue, Value},
+};
+
+#[cfg(feature = "derive")]
+pub use msgpackp_derive::{MsgpackP, Packet};
+
+/*
+ * Runtime support for the code generated by #[derive(MsgpackP)].
+ *
+ * Not meant to be called by hand, so none of it shows up in the docs.
+ */
+
+/**
+ * @name read_marker
+ * @description
+ *
+ * Pulls the marker of the next value.
+ */
+#[doc(hidden)]
+#[inline(always)]
+pub fn read_marker(reader: &mut Reader<'_>) -> Result<u8> {
+    reader.pull_marker()
+}
+
+/*
+ * Borrows the bytes of a string whose marker was already pulled
+ */
+#[inline]
+fn read_name<'buf>(
+    marker: u8,
+    reader: &mut Reader<'buf>,
+    what: &'static str,
+) -> Result<&'buf [u8]> {
+    let len = read_str_len(marker, reader)?;
+    let name = reader.pull(len);
+
+    if name.len() != len {
+        return Err(Error::UnexpectedEof(what));
+    }
+
+    Ok(name)
+}
+
+/**
+ * @name read_key
+ * @description
+ *
+ * Reads a map key. A key that isn't a string can't name a field, so it's
+ * stepped over along with its value and `None` is returned.
+ */
+#[doc(hidden)]
+#[inline]
+pub fn read_key<'buf>(reader: &mut Reader<'buf>) -> Result<Option<&'buf [u8]>> {
+    match reader.peek() {
+        Some(0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32) => {
+            let marker = read_marker(reader)?;
+
+            read_name(marker, reader, "key").map(Some)
+        }
+        _ => {
+            reader.skip_value()?;
+            reader.skip_value()?;
+
+            Ok(None)
+        }
+    }
+}
+
+/**
+ * @name read_code
+ * @description
+ *
+ * Reads the code of a packet envelope. Codes are strings, but whatever
+ * is there is decoded so an unknown one can be reported as is: a string
+ * that isn't valid UTF-8 comes back as its raw bytes in a `Bin`.
+ */
+#[doc(hidden)]
+#[inline]
+pub fn read_code<'buf>(reader: &mut Reader<'buf>) -> Result<Value<'buf>> {
+    match reader.peek() {
+        Some(0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32) => {
+            let marker = read_marker(reader)?;
+            let code = read_name(marker, reader, "code")?;
+
+            Ok(match core::str::from_utf8(code) {
+                Ok(code) => Value::Str(code),
+                Err(_) => Value::Bin(code),
+            })
+        }
+        _ => reader.pull_value(),
+    }
+}
+
+/**
+ * @name read_variant
+ * @description
+ *
+ * Reads the name of an externally tagged enum variant: either a bare
+ * string (unit variant) or the key of a single-entry map, in which
+ * case `true` is returned and the variant's content follows.
+ */
+#[doc(hidden)]
+#[inline]
+pub fn read_variant<'buf>(
+    packet_type: u8,
+    reader: &mut Reader<'buf>,
+) -> Result<(&'buf [u8], bool)> {
+    match packet_type {
+        0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+            Ok((read_name(packet_type, reader, "variant")?, false))
+        }
+        0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+            if read_map_len(packet_type, reader)? != 1 {
+                return Err(Error::Message("Enum map must have exactly one entry"));
+            }
+
+            match read_marker(reader)? {
+                marker @ (0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32) => {
+                    Ok((read_name(marker, reader, "variant")?, true))
+                }
+                marker => Err(Error::UnexpectedMarker(marker)),
+            }
+        }
+        _ => Err(Error::UnexpectedMarker(packet_type)),
+    }
+}
+
+/**
+ * @name skip
+ * @description
+ *
+ * Steps over the next value (unknown map keys, extra array elements).
+ */
+#[doc(hidden)]
+#[inline]
+pub fn skip(reader: &mut Reader<'_>) -> Result<()> {
+    reader.skip_value()
+}
+
+#[doc(hidden)]
+#[cold]
+pub fn missing_field(name: &'static str) -> Error {
+    Error::Custom(format!("missing field `{name}`"))
+}
+
+#[doc(hidden)]
+#[cold]
+pub fn unknown_variant(name: &[u8]) -> Error {
+    Error::Custom(format!(
+        "unknown variant `{}`",
+        String::from_utf8_lossy(name)
+    ))
+}
+
+/**
+ * @name packet_code
+ * @description
+ *
+ * Copies an unknown packet code out of the buffer, so the reader can
+ * be used to read the rest of the packet.
+ */
+#[doc(hidden)]
+#[cold]
+pub fn packet_code(code: Value<'_>) -> OwnedValue {
+    OwnedValue::from(code)
+}
+
+/**
+ * @name unknown_packet
+ * @description
+ *
+ * Error for a packet code no variant handles. Reads the arguments, so
+ * the error carries the whole envelope and the reader is past the packet.
+ */
+#[doc(hidden)]
+#[cold]
+pub fn unknown_packet(code: OwnedValue, reader: &mut Reader<'_>) -> Error {
+    match reader.pull_value() {
+        Ok(args) => Error::UnknownPacket(Box::new(OwnedValue::Array(vec![
+            code,
+            OwnedValue::from(args),
+        ]))),
+        Err(error) => error,
+    }
+}
+
+#[doc(hidden)]
+#[cold]
+pub fn packet_arity(code: &'static str, expected: usize, len: usize) -> Error {
+    Error::Custom(format!(
+        "packet `{code}` takes {expected} arguments, got {len}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{vec, vec::Vec};
+    use msgpackp_derive::{MsgpackP, Packet};
+
+    use crate::{
+        msgpack::WriteTo,
+        value::{OwnedValue, Value},
+    };
+
+    #[derive(Debug, PartialEq, MsgpackP)]
+    struct Player<'a> {
+        name: &'a str,
+        id: u32,
+        #[msgpackp(skip)]
+        cached: u8,
+        #[msgpackp(default)]
+        score: u16,
+    }
+
+    #[derive(Debug, PartialEq, MsgpackP)]
+    #[msgpackp(map)]
+    struct Settings<'a> {
+        #[msgpackp(rename = "n")]
+        name: &'a str,
+        volume: u8,
+        #[msgpackp(default)]
+        muted: bool,
+    }
+
+    #[derive(Debug, PartialEq, MsgpackP)]
+    struct Sid(u16);
+
+    #[derive(Debug, PartialEq, MsgpackP)]
+    enum Action<'a> {
+        Idle,
+        #[msgpackp(rename = "ch")]
+        Chat(&'a str),
+        Move(i16, i16),
+        Hit {
+            target: Sid,
+            damage: u8,
+        },
+    }
+
+    fn encode<T: WriteTo>(value: &T) -> Vec<u8> {
+        let mut buffer = Vec::new();
+
+        value.write_to(&mut buffer).unwrap();
+
+        buffer
+    }
+
+    #[test]
+    fn test_struct_as_array() {
+        let player = Player {
+            name: "0xffabc",
+            id: 7,
+            cached: 1,
+            score: 300,
+        };
+
+        let buffer = encode(&player);
+
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert_eq!(
+            reader.pull_value().unwrap(),
+            Value::Array(
+                vec![Value::Str("0xffabc"), Value::U32(7), Value::U16(300)].into_boxed_slice()
+            )
+        );
+
+        let mut reader = Reader::new(&buffer[..]);
+        let decoded: Player = reader.read().unwrap();
+
+        /*
+         * Skipped fields come back as Default, strings borrow the buffer
+         */
+        assert_eq!(
+            decoded,
+            Player {
+                cached: 0,
+                ..player
+            }
+        );
+        assert_eq!(decoded.name.as_ptr(), buffer[2..].as_ptr());
+    }
+
+    #[test]
+    fn test_optional_trailing_fields() {
+        let buffer = encode(&(vec![Value::Str("old"), Value::U32(1)].into_boxed_slice()));
+
+        let mut reader = Reader::new(&buffer[..]);
+        let decoded: Player = reader.read().unwrap();
+
+        assert_eq!(decoded.score, 0);
+
+        let buffer = encode(&(vec![Value::Str("old")].into_boxed_slice()));
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert!(reader.read::<Player>().is_err());
+
+        /*
+         * Extra elements from a newer peer are skipped
+         */
+        let buffer = encode(
+            &(vec![Value::Str("new"), Value::U32(1), Value::U16(2), Value::Nil].into_boxed_slice()),
+        );
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert_eq!(reader.read::<Player>().unwrap().score, 2);
+        assert!(reader.pull(1).is_empty());
+    }
+
+    #[test]
+    fn test_struct_as_map() {
+        let settings = Settings {
+            name: "x",
+            volume: 3,
+            muted: true,
+        };
+
+        let buffer = encode(&settings);
+
+        assert_eq!(&buffer[..3], &[0x83, 0xa1, b'n']);
+
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert_eq!(reader.read::<Settings>().unwrap(), settings);
+
+        /*
+         * Any key order, unknown keys skipped, `default` keys optional
+         */
+        let buffer = encode(
+            &(vec![
+                (Value::Str("volume"), Value::U8(9)),
+                (Value::Str("unknown"), Value::Nil),
+                (Value::Str("n"), Value::Str("y")),
+            ]
+            .into_boxed_slice()),
+        );
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert_eq!(
+            reader.read::<Settings>().unwrap(),
+            Settings {
+                name: "y",
+                volume: 9,
+                muted: false
+            }
+        );
+
+        /*
+         * Keys that aren't strings are skipped along with their values
+         */
+        let buffer = encode(
+            &(vec![
+                (Value::Str("n"), Value::Str("z")),
+                (
+                    Value::U8(1),
+                    Value::Array(vec![Value::Str("volume")].into_boxed_slice()),
+                ),
+                (Value::Str("volume"), Value::U8(4)),
+            ]
+            .into_boxed_slice()),
+        );
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert_eq!(
+            reader.read::<Settings>().unwrap(),
+            Settings {
+                name: "z",
+                volume: 4,
+                muted: false
+            }
+        );
+        assert!(reader.pull(1).is_empty());
+    }
+
+    #[test]
+    fn test_enums() {
+        assert_eq!(encode(&Action::Idle), [0xa4, b'I', b'd', b'l', b'e']);
+        assert_eq!(
+            encode(&Action::Chat("hi")),
+            [0x81, 0xa2, b'c', b'h', 0xa2, b'h', b'i']
+        );
+        assert_eq!(encode(&Sid(5)), [0xcd, 0x00, 0x05]);
+
+        for action in [
+            Action::Idle,
+            Action::Chat("hi"),
+            Action::Move(-1, 1),
+            Action::Hit {
+                target: Sid(2),
+                damage: 10,
+            },
+        ] {
+            let buffer = encode(&action);
+            let mut reader = Reader::new(&buffer[..]);
+
+            assert_eq!(reader.read::<Action>().unwrap(), action);
+        }
+
+        let buffer = encode(&"Fly");
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert!(reader.read::<Action>().is_err());
+    }
+
+    #[derive(Debug, PartialEq, Packet)]
+    enum Incoming<'a> {
+        #[packet("ch")]
+        Chat(&'a str),
+
+        #[packet("sp")]
+        Spawn(Settings<'a>),
+
+        #[packet("9")]
+        Aim(f64),
+
+        #[packet("pp")]
+        Ping,
+
+        #[packet("c")]
+        Gather { hitting: bool, tool: u8 },
+    }
+
+    #[test]
+    fn test_packets() {
+        let chat = Incoming::Chat("Hello");
+        let buffer = encode(&chat);
+
+        /*
+         * Same envelope as a Value tree
+         */
+        assert_eq!(
+            buffer,
+            encode(
+                &(vec![
+                    Value::Str("ch"),
+                    Value::Array(vec![Value::Str("Hello")].into_boxed_slice())
+                ]
+                .into_boxed_slice())
+            )
+        );
+
+        for packet in [
+            chat,
+            Incoming::Spawn(Settings {
+                name: "0xffabc",
+                volume: 1,
+                muted: false,
+            }),
+            Incoming::Aim(1.25),
+            Incoming::Ping,
+            Incoming::Gather {
+                hitting: true,
+                tool: 2,
+            },
+        ] {
+            let buffer = encode(&packet);
+            let mut reader = Reader::new(&buffer[..]);
+
+            assert_eq!(reader.read::<Incoming>().unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn test_packet_errors() {
+        /*
+         * Unknown codes carry the raw packet
+         */
+        let buffer = encode(
+            &(vec![
+                Value::Str("zz"),
+                Value::Array(vec![Value::U8(1)].into_boxed_slice()),
+            ]
+            .into_boxed_slice()),
+        );
+        let mut reader = Reader::new(&buffer[..]);
+
+        match reader.read::<Incoming>() {
+            Err(Error::UnknownPacket(packet)) => assert_eq!(
+                *packet,
+                OwnedValue::Array(vec![
+                    OwnedValue::Str("zz".into()),
+                    OwnedValue::Array(vec![OwnedValue::U8(1)])
+                ])
+            ),
+            other => panic!("Expected an unknown packet, got {other:?}"),
+        }
+
+        /*
+         * Codes that aren't strings, or not valid UTF-8, are carried unchanged
+         */
+        let mut buffer = vec![0x92, 0xa2, 0xff, 0xfe, 0x90];
+
+        buffer.extend(encode(
+            &(vec![Value::U16(300), Value::Array(Box::default())].into_boxed_slice()),
+        ));
+
+        let mut reader = Reader::new(&buffer[..]);
+
+        for code in [OwnedValue::Bin(vec![0xff, 0xfe]), OwnedValue::U16(300)] {
+            match reader.read::<Incoming>() {
+                Err(Error::UnknownPacket(packet)) => {
+                    assert_eq!(
+                        *packet,
+                        OwnedValue::Array(vec![code, OwnedValue::Array(vec![])])
+                    )
+                }
+                other => panic!("Expected an unknown packet, got {other:?}"),
+            }
+        }
+
+        assert!(reader.pull(1).is_empty());
+
+        /*
+         * Wrong argument count or type
+         */
+        for args in [
+            vec![],
+            vec![Value::Str("a"), Value::Str("b")],
+            vec![Value::U8(1)],
+        ] {
+            let buffer = encode(
+                &(vec![Value::Str("ch"), Value::Array(args.into_boxed_slice())].into_boxed_slice()),
+            );
+            let mut reader = Reader::new(&buffer[..]);
+
+            assert!(reader.read::<Incoming>().is_err());
+        }
+    }
+}
@@ -0,0 +1,1022 @@
+use alloc::vec::Vec;
+
+use crate::{
+    constants::Families,
+    error::{Error, Result},
+    io::{Backpatch, Write},
+    msgpack::{
+        WriteTo,
+        array::{Array, write_array_header},
+        bin::write_bin_header,
+        ext::write_ext_header,
+        integer::{write_int, write_uint},
+        map::{Map, write_map_header},
+        string::write_str_header,
+    },
+    value::Value,
+};
+
+/**
+ * @name Encoder
+ * @description
+ *
+ * Push-style encoder, the Rust version of CoreEncode from codec.js.
+ *
+ * Headers and scalars go straight into the sink, so there's no need
+ * to build a Value tree first:
+ *
+ * ```rust
+ * use msgpackp::encoder::Encoder;
+ *
+ * let mut encoder = Encoder::new(Vec::new());
+ *
+ * encoder.begin_array(2).unwrap();
+ * encoder.write_str("ch").unwrap();
+ * encoder.begin_array(1).unwrap();
+ * encoder.write_str("Hello").unwrap();
+ *
+ * let buffer = encoder.finish().unwrap();
+ *
+ * assert_eq!(buffer, [0x92, 0xa2, b'c', b'h', 0x91, 0xa5, b'H', b'e', b'l', b'l', b'o']);
+ * ```
+ *
+ * In debug builds the encoder tracks every open container and `finish`
+ * fails if fewer elements were written than their headers declared.
+ *
+ * Sinks that implement Backpatch can also open containers of unknown
+ * length with `begin_array_unsized`/`begin_map_unsized`, their headers
+ * are filled in by `end_array`/`end_map`.
+ */
+pub struct Encoder<W: Write> {
+    writer: W,
+    config: EncoderConfig,
+
+    /*
+     * Every open container, innermost last.
+     *
+     * Release builds only track sized containers nested inside
+     * unsized ones, so elements are attributed to the right one.
+     */
+    open: Vec<Frame>,
+}
+
+/**
+ * @name EncoderConfig
+ * @description
+ *
+ * Options for the Encoder. The default produces the smallest encoding.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncoderConfig {
+    /*
+     * Unsized containers keep a reserved 32-bit header instead of
+     * shifting their contents to fit the smallest one
+     */
+    pub fixed_unsized_headers: bool,
+
+    /*
+     * f64 values that survive a round trip through f32 bit for bit
+     * are written as float 32 (5 bytes instead of 9)
+     */
+    pub narrow_floats: bool,
+
+    /*
+     * Integral floats within the JS safe integer range are written as
+     * integers, the way codec.js add_num does. Decodes as an integer!
+     */
+    pub integral_floats_as_ints: bool,
+
+    /*
+     * Deterministic encoding for write_value: minimal integer widths,
+     * floats narrowed when lossless, one canonical NaN and map entries
+     * sorted by the bytes of their encoded keys
+     */
+    pub canonical: bool,
+
+    /*
+     * What canonical encoding does with maps that repeat a key
+     */
+    pub duplicate_keys: DuplicateKeys,
+}
+
+/**
+ * @name DuplicateKeys
+ * @description
+ *
+ * How canonical encoding treats map keys that encode to the same bytes.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /*
+     * Fail with an error
+     */
+    #[default]
+    Reject,
+
+    /*
+     * Keep the entry that comes first in the map
+     */
+    KeepFirst,
+
+    /*
+     * Keep the entry that comes last in the map, like JS objects do
+     */
+    KeepLast,
+}
+
+impl EncoderConfig {
+    /**
+     * @name canonical
+     * @description
+     *
+     * Config for deterministic encoding, rejecting duplicate keys.
+     */
+    #[inline(always)]
+    pub fn canonical() -> Self {
+        EncoderConfig {
+            canonical: true,
+            ..Default::default()
+        }
+    }
+
+    /*
+     * Whether write_value has to walk the tree instead of using WriteTo
+     */
+    #[inline(always)]
+    fn rewrites_values(&self) -> bool {
+        self.narrow_floats || self.integral_floats_as_ints || self.canonical
+    }
+}
+
+/*
+ * The only NaN canonical encoding writes: a quiet float 32 NaN
+ */
+const CANONICAL_NAN: [u8; 5] = [Families::FLOAT32, 0x7f, 0xc0, 0x00, 0x00];
+
+/*
+ * Number.MAX_SAFE_INTEGER, 2^53 - 1
+ */
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/**
+ * @name integral
+ * @description
+ *
+ * Returns the integer an integral float holds, if it's within the safe range.
+ *
+ * -0.0 is left alone so its sign survives.
+ */
+#[inline(always)]
+fn integral(value: f64) -> Option<i64> {
+    if !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&value) {
+        return None;
+    }
+
+    let int = value as i64;
+
+    if int as f64 != value || (int == 0 && value.is_sign_negative()) {
+        return None;
+    }
+
+    Some(int)
+}
+
+/**
+ * @name narrow
+ * @description
+ *
+ * Returns the f32 an f64 converts to if the conversion is lossless.
+ */
+#[inline(always)]
+fn narrow(value: f64) -> Option<f32> {
+    let narrow = value as f32;
+
+    if (narrow as f64).to_bits() != value.to_bits() {
+        return None;
+    }
+
+    Some(narrow)
+}
+
+enum Frame {
+    /*
+     * Elements left in a container with a declared length
+     */
+    Sized(usize),
+
+    /*
+     * Container opened without a length: where its reserved header
+     * starts and how many elements were written into it so far
+     */
+    Unsized {
+        at: usize,
+        map: bool,
+        written: usize,
+    },
+}
+
+/*
+ * Header bytes reserved for an unsized container: marker + u32 length
+ */
+const RESERVED_HEADER: usize = 5;
+
+impl<W: Write> Encoder<W> {
+    #[inline(always)]
+    pub fn new(writer: W) -> Self {
+        Encoder::with_config(writer, EncoderConfig::default())
+    }
+
+    #[inline(always)]
+    pub fn with_config(writer: W, config: EncoderConfig) -> Self {
+        Encoder {
+            writer,
+            config,
+            open: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn config(&self) -> &EncoderConfig {
+        &self.config
+    }
+
+    #[inline(always)]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /**
+     * @name into_inner
+     * @description
+     *
+     * Returns the sink without checking that every container was completed.
+     */
+    #[inline(always)]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /**
+     * @name finish
+     * @description
+     *
+     * Returns the sink.
+     *
+     * Fails if an unsized container wasn't ended, and in debug builds,
+     * if a container is still waiting for elements.
+     */
+    #[inline]
+    pub fn finish(self) -> Result<W> {
+        if !self.open.is_empty() {
+            return Err(Error::Message(
+                "Encoder finished before every declared container element was written",
+            ));
+        }
+
+        Ok(self.writer)
+    }
+
+    /**
+     * @name element
+     * @description
+     *
+     * Accounts for one element in the innermost open container.
+     *
+     * A container is done as soon as its last element starts, nested
+     * containers track their own elements.
+     */
+    #[inline(always)]
+    fn element(&mut self) {
+        match self.open.last_mut() {
+            Some(Frame::Sized(left)) => {
+                *left -= 1;
+
+                if *left == 0 {
+                    self.open.pop();
+                }
+            }
+            Some(Frame::Unsized { written, .. }) => *written += 1,
+            None => {}
+        }
+    }
+
+    #[inline(always)]
+    fn open(&mut self, elements: usize) {
+        if elements != 0 && (cfg!(debug_assertions) || !self.open.is_empty()) {
+            self.open.push(Frame::Sized(elements));
+        }
+    }
+
+    /**
+     * @name begin_array
+     * @description
+     *
+     * Writes an array header. The next `len` values belong to the array.
+     */
+    #[inline]
+    pub fn begin_array(&mut self, len: usize) -> Result<()> {
+        self.element();
+
+        write_array_header(len, &mut self.writer)?;
+
+        self.open(len);
+
+        Ok(())
+    }
+
+    /**
+     * @name begin_map
+     * @description
+     *
+     * Writes a map header. The next `len` key-value pairs belong to the map.
+     */
+    #[inline]
+    pub fn begin_map(&mut self, len: usize) -> Result<()> {
+        self.element();
+
+        write_map_header(len, &mut self.writer)?;
+
+        self.open(len * 2);
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn write_nil(&mut self) -> Result<()> {
+        self.element();
+
+        self.writer.write_all(&[Families::NIL])
+    }
+
+    #[inline]
+    pub fn write_bool(&mut self, value: bool) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_u8(&mut self, value: u8) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_u16(&mut self, value: u16) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_u32(&mut self, value: u32) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_u64(&mut self, value: u64) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_i8(&mut self, value: i8) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_i16(&mut self, value: i16) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_i32(&mut self, value: i32) -> Result<()> {
+        self.write(&value)
+    }
+
+    #[inline]
+    pub fn write_i64(&mut self, value: i64) -> Result<()> {
+        self.write(&value)
+    }
+
+    /**
+     * @name write_uint
+     * @description
+     *
+     * Writes an unsigned integer in the smallest format that fits.
+     */
+    #[inline]
+    pub fn write_uint(&mut self, value: u64) -> Result<()> {
+        self.element();
+
+        write_uint(value, &mut self.writer)
+    }
+
+    /**
+     * @name write_int
+     * @description
+     *
+     * Writes a signed integer in the smallest format that fits.
+     */
+    #[inline]
+    pub fn write_int(&mut self, value: i64) -> Result<()> {
+        self.element();
+
+        write_int(value, &mut self.writer)
+    }
+
+    /**
+     * @name write_f32
+     * @description
+     *
+     * Writes a float 32, or an integer if `integral_floats_as_ints` is set and it fits.
+     */
+    #[inline]
+    pub fn write_f32(&mut self, value: f32) -> Result<()> {
+        if self.config.integral_floats_as_ints
+            && let Some(int) = integral(value as f64)
+        {
+            return self.write_int(int);
+        }
+
+        if self.config.canonical && value.is_nan() {
+            return self.write_raw(&CANONICAL_NAN);
+        }
+
+        self.write(&value)
+    }
+
+    /**
+     * @name write_f64
+     * @description
+     *
+     * Writes a float 64, narrowed according to the config.
+     */
+    #[inline]
+    pub fn write_f64(&mut self, value: f64) -> Result<()> {
+        if self.config.integral_floats_as_ints
+            && let Some(int) = integral(value)
+        {
+            return self.write_int(int);
+        }
+
+        if self.config.canonical && value.is_nan() {
+            return self.write_raw(&CANONICAL_NAN);
+        }
+
+        if (self.config.narrow_floats || self.config.canonical)
+            && let Some(narrow) = narrow(value)
+        {
+            return self.write(&narrow);
+        }
+
+        self.write(&value)
+    }
+
+    /**
+     * @name write_str
+     * @description
+     *
+     * Writes a str header and the string bytes, no String allocation involved.
+     */
+    #[inline]
+    pub fn write_str(&mut self, value: &str) -> Result<()> {
+        self.element();
+
+        write_str_header(value.len(), &mut self.writer)?;
+
+        self.writer.write_all(value.as_bytes())
+    }
+
+    #[inline]
+    pub fn write_bin(&mut self, value: &[u8]) -> Result<()> {
+        self.element();
+
+        write_bin_header(value.len(), &mut self.writer)?;
+
+        self.writer.write_all(value)
+    }
+
+    #[inline]
+    pub fn write_ext(&mut self, type_: u8, data: &[u8]) -> Result<()> {
+        self.element();
+
+        write_ext_header(type_, data.len(), &mut self.writer)?;
+
+        self.writer.write_all(data)
+    }
+
+    /**
+     * @name write_value
+     * @description
+     *
+     * Writes a whole Value tree as a single element.
+     *
+     * Floats inside the tree follow the config too.
+     */
+    #[inline]
+    pub fn write_value(&mut self, value: &Value) -> Result<()> {
+        if !self.config.rewrites_values() {
+            return self.write(value);
+        }
+
+        match value {
+            Value::F32(value) => self.write_f32(value.0),
+            Value::F64(value) => self.write_f64(value.0),
+            Value::U16(value) if self.config.canonical => self.write_uint(*value as u64),
+            Value::U32(value) if self.config.canonical => self.write_uint(*value as u64),
+            Value::U64(value) if self.config.canonical => self.write_uint(*value),
+            Value::I8(value) if self.config.canonical => self.write_int(*value as i64),
+            Value::I16(value) if self.config.canonical => self.write_int(*value as i64),
+            Value::I32(value) if self.config.canonical => self.write_int(*value as i64),
+            Value::I64(value) if self.config.canonical => self.write_int(*value),
+            Value::Map(values) if self.config.canonical => self.write_canonical_map(values),
+            Value::Array(values) => {
+                self.begin_array(values.len())?;
+
+                for value in values {
+                    self.write_value(value)?;
+                }
+
+                Ok(())
+            }
+            Value::Map(values) => {
+                self.begin_map(values.len())?;
+
+                for (key, value) in values {
+                    self.write_value(key)?;
+                    self.write_value(value)?;
+                }
+
+                Ok(())
+            }
+            _ => self.write(value),
+        }
+    }
+
+    /**
+     * @name write_canonical_map
+     * @description
+     *
+     * Writes map entries sorted by the bytes of their canonically encoded keys.
+     */
+    fn write_canonical_map(&mut self, values: &[(Value, Value)]) -> Result<()> {
+        let mut entries = Vec::new();
+
+        entries.try_reserve(values.len())?;
+
+        for (key, value) in values {
+            let mut encoder = Encoder::with_config(Vec::new(), self.config);
+
+            encoder.write_value(key)?;
+            entries.push((encoder.into_inner(), value));
+        }
+
+        /*
+         * Stable, so equal keys keep their order for KeepFirst/KeepLast
+         */
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut unique = Vec::new();
+
+        unique.try_reserve(entries.len())?;
+
+        for entry in entries {
+            match unique.last_mut() {
+                Some((key, value)) if *key == entry.0 => match self.config.duplicate_keys {
+                    DuplicateKeys::Reject => {
+                        return Err(Error::Message("Map has duplicate keys"));
+                    }
+                    DuplicateKeys::KeepFirst => {}
+                    DuplicateKeys::KeepLast => *value = entry.1,
+                },
+                _ => unique.push(entry),
+            }
+        }
+
+        self.begin_map(unique.len())?;
+
+        for (key, value) in unique {
+            self.write_raw(&key)?;
+            self.write_value(value)?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * @name write_raw
+     * @description
+     *
+     * Writes bytes that already hold exactly one encoded value.
+     */
+    #[inline]
+    pub fn write_raw(&mut self, encoded: &[u8]) -> Result<()> {
+        self.element();
+
+        self.writer.write_all(encoded)
+    }
+
+    /**
+     * @name write
+     * @description
+     *
+     * Writes anything that implements WriteTo as a single element.
+     */
+    #[inline(always)]
+    pub fn write<T: WriteTo + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element();
+
+        value.write_to(&mut self.writer)
+    }
+}
+
+impl<W: Backpatch> Encoder<W> {
+    /**
+     * @name begin_array_unsized
+     * @description
+     *
+     * Opens an array whose length isn't known yet.
+     *
+     * Write any number of elements, then call `end_array`.
+     */
+    #[inline]
+    pub fn begin_array_unsized(&mut self) -> Result<()> {
+        self.begin_unsized(false)
+    }
+
+    /**
+     * @name begin_map_unsized
+     * @description
+     *
+     * Opens a map whose length isn't known yet.
+     *
+     * Write any number of key-value pairs, then call `end_map`.
+     */
+    #[inline]
+    pub fn begin_map_unsized(&mut self) -> Result<()> {
+        self.begin_unsized(true)
+    }
+
+    #[inline]
+    pub fn end_array(&mut self) -> Result<()> {
+        self.end_unsized(false)
+    }
+
+    #[inline]
+    pub fn end_map(&mut self) -> Result<()> {
+        self.end_unsized(true)
+    }
+
+    #[inline]
+    fn begin_unsized(&mut self, map: bool) -> Result<()> {
+        self.element();
+
+        let at = self.writer.position()?;
+
+        self.writer.write_all(&[0; RESERVED_HEADER])?;
+
+        self.open.push(Frame::Unsized {
+            at,
+            map,
+            written: 0,
+        });
+
+        Ok(())
+    }
+
+    fn end_unsized(&mut self, map: bool) -> Result<()> {
+        let (at, written) = match self.open.last() {
+            Some(&Frame::Unsized {
+                at,
+                map: is_map,
+                written,
+            }) if is_map == map => (at, written),
+            Some(Frame::Sized(_)) => {
+                return Err(Error::Message(
+                    "A nested container is still waiting for elements",
+                ));
+            }
+            _ => {
+                return Err(Error::Message(
+                    "Ended a container that wasn't opened as unsized",
+                ));
+            }
+        };
+
+        if map && written % 2 != 0 {
+            return Err(Error::Message("Map is missing a value for its last key"));
+        }
+
+        let len = if map { written / 2 } else { written };
+
+        if len > u32::MAX as usize {
+            return Err(Error::LengthOverflow(if map { "Map" } else { "Array" }));
+        }
+
+        self.open.pop();
+
+        if self.config.fixed_unsized_headers || !W::SHIFTS {
+            let bytes = (len as u32).to_be_bytes();
+            let marker = if map {
+                Map::MAP_32_TYPE
+            } else {
+                Array::ARRAY_32_TYPE
+            };
+
+            return self
+                .writer
+                .patch(at, &[marker, bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        let mut header = [0u8; RESERVED_HEADER];
+        let mut slice = &mut header[..];
+
+        if map {
+            write_map_header(len, &mut slice)?;
+        } else {
+            write_array_header(len, &mut slice)?;
+        }
+
+        let header_len = RESERVED_HEADER - slice.len();
+
+        /*
+         * Shift the contents left over the unused part of the reserved header
+         */
+        if header_len < RESERVED_HEADER {
+            self.writer.remove(at, RESERVED_HEADER - header_len)?;
+        }
+
+        self.writer.patch(at, &header[..header_len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec, vec::Vec};
+
+    #[test]
+    fn test_matches_value_encoding() {
+        let mut encoder = Encoder::new(Vec::new());
+
+        encoder.begin_array(2).unwrap();
+        encoder.write_str("sp").unwrap();
+        encoder.begin_array(1).unwrap();
+        encoder.begin_map(1).unwrap();
+        encoder.write_str("name").unwrap();
+        encoder.write_str("0xffabc").unwrap();
+
+        let mut expected = Vec::new();
+
+        let packet = vec![
+            Value::Str("sp"),
+            Value::Array(
+                vec![Value::Map(
+                    vec![(Value::Str("name"), Value::Str("0xffabc"))].into_boxed_slice(),
+                )]
+                .into_boxed_slice(),
+            ),
+        ]
+        .into_boxed_slice();
+
+        packet.write_to(&mut expected).unwrap();
+
+        assert_eq!(encoder.finish().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut encoder = Encoder::new(Vec::new());
+
+        encoder.begin_array(16).unwrap();
+
+        for i in 0..16 {
+            encoder.write_int(-i).unwrap();
+        }
+
+        encoder.begin_map(0x10000).unwrap();
+
+        let buffer = encoder.into_inner();
+
+        assert_eq!(&buffer[..3], &[0xdc, 0x00, 0x10]);
+        assert_eq!(&buffer[buffer.len() - 5..], &[0xdf, 0x00, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_unsized_backpatching() {
+        let mut encoder = Encoder::new(Vec::new());
+
+        encoder.begin_array_unsized().unwrap();
+        encoder.write_str("a").unwrap();
+        encoder.begin_map_unsized().unwrap();
+
+        for key in (0..20u64).filter(|key| key % 2 == 0) {
+            encoder.write_uint(key).unwrap();
+            encoder.begin_array(1).unwrap();
+            encoder.write_nil().unwrap();
+        }
+
+        encoder.end_map().unwrap();
+        encoder.end_array().unwrap();
+
+        let buffer = encoder.finish().unwrap();
+
+        assert_eq!(&buffer[..5], &[0x92, 0xa1, b'a', 0x8a, 0x00]);
+        assert_eq!(buffer.len(), 4 + 10 * 3);
+    }
+
+    #[test]
+    fn test_unsized_fixed_headers() {
+        let config = EncoderConfig {
+            fixed_unsized_headers: true,
+            ..Default::default()
+        };
+
+        let mut encoder = Encoder::with_config(Vec::new(), config);
+
+        encoder.begin_array_unsized().unwrap();
+        encoder.write_bool(true).unwrap();
+        encoder.end_array().unwrap();
+
+        assert_eq!(
+            encoder.finish().unwrap(),
+            &[0xdd, 0x00, 0x00, 0x00, 0x01, 0xc3]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_unsized_seekable() {
+        use crate::io::Seekable;
+
+        let mut encoder = Encoder::new(Seekable(std::io::Cursor::new(Vec::new())));
+
+        encoder.begin_map_unsized().unwrap();
+        encoder.write_str("x").unwrap();
+        encoder.write_u8(1).unwrap();
+        encoder.end_map().unwrap();
+
+        assert_eq!(
+            encoder.finish().unwrap().0.into_inner(),
+            &[0xdf, 0x00, 0x00, 0x00, 0x01, 0xa1, b'x', 0x01]
+        );
+    }
+
+    #[test]
+    fn test_unsized_unbalanced() {
+        let mut encoder = Encoder::new(Vec::new());
+
+        encoder.begin_array_unsized().unwrap();
+        encoder.begin_array(2).unwrap();
+        encoder.write_nil().unwrap();
+
+        assert!(encoder.end_array().is_err());
+        assert!(encoder.end_map().is_err());
+        assert!(encoder.finish().is_err());
+    }
+
+    #[test]
+    fn test_float_narrowing() {
+        let config = EncoderConfig {
+            narrow_floats: true,
+            ..Default::default()
+        };
+
+        let mut encoder = Encoder::with_config(Vec::new(), config);
+
+        encoder.write_f64(0.5).unwrap();
+        encoder.write_f64(1.3).unwrap();
+        encoder.write_f64(f64::NAN).unwrap();
+
+        let buffer = encoder.finish().unwrap();
+
+        assert_eq!(&buffer[..5], &[0xca, 0x3f, 0x00, 0x00, 0x00]);
+        assert_eq!(buffer[5], 0xcb);
+        assert_eq!(buffer.len(), 5 + 9 + 5);
+
+        let mut reader = crate::reader::Reader::new(&buffer);
+
+        assert_eq!(reader.pull_value().unwrap(), Value::f32(0.5.into()));
+    }
+
+    #[test]
+    fn test_integral_floats() {
+        let config = EncoderConfig {
+            narrow_floats: true,
+            integral_floats_as_ints: true,
+            ..Default::default()
+        };
+
+        let mut encoder = Encoder::with_config(Vec::new(), config);
+
+        let packet = Value::Array(
+            vec![
+                Value::f64(2.0.into()),
+                Value::f64((-300.0).into()),
+                Value::f32(1.25.into()),
+                Value::f64((-0.0).into()),
+                Value::f64(1e300.into()),
+            ]
+            .into_boxed_slice(),
+        );
+
+        encoder.write_value(&packet).unwrap();
+
+        let buffer = encoder.finish().unwrap();
+
+        assert_eq!(&buffer[..5], &[0x95, 0x02, 0xd1, 0xfe, 0xd4]);
+        assert_eq!(&buffer[5..10], &[0xca, 0x3f, 0xa0, 0x00, 0x00]);
+        assert_eq!(&buffer[10..15], &[0xca, 0x80, 0x00, 0x00, 0x00]);
+        assert_eq!(buffer[15], 0xcb);
+    }
+
+    #[test]
+    fn test_exact_floats_by_default() {
+        let mut encoder = Encoder::new(Vec::new());
+
+        encoder.write_f64(2.0).unwrap();
+
+        assert_eq!(encoder.finish().unwrap()[0], 0xcb);
+    }
+
+    #[test]
+    fn test_canonical_encoding() {
+        let a = Value::Map(
+            vec![
+                (Value::str("b"), Value::U16(1)),
+                (Value::str("a"), Value::f64(f64::NAN.into())),
+                (Value::I8(3), Value::I64(-1)),
+            ]
+            .into_boxed_slice(),
+        );
+
+        let b = Value::Map(
+            vec![
+                (Value::U64(3), Value::i8(-1)),
+                (
+                    Value::str("a"),
+                    Value::f32(f32::from_bits(0x7fc0_0001).into()),
+                ),
+                (Value::str("b"), Value::U8(1)),
+            ]
+            .into_boxed_slice(),
+        );
+
+        let mut encoder = Encoder::with_config(Vec::new(), EncoderConfig::canonical());
+
+        encoder.write_value(&a).unwrap();
+
+        let buffer = encoder.finish().unwrap();
+
+        assert_eq!(
+            buffer,
+            &[
+                0x83, 0x03, 0xff, 0xa1, b'a', 0xca, 0x7f, 0xc0, 0x00, 0x00, 0xa1, b'b', 0x01
+            ]
+        );
+
+        assert_eq!(a.canonical_hash().unwrap(), b.canonical_hash().unwrap());
+        assert_ne!(
+            a.canonical_hash().unwrap(),
+            Value::Map(Box::new([])).canonical_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonical_duplicate_keys() {
+        let map = Value::Map(
+            vec![
+                (Value::U8(1), Value::str("first")),
+                (Value::U16(1), Value::str("last")),
+            ]
+            .into_boxed_slice(),
+        );
+
+        assert!(map.canonical_hash().is_err());
+
+        let config = EncoderConfig {
+            duplicate_keys: DuplicateKeys::KeepLast,
+            ..EncoderConfig::canonical()
+        };
+
+        let mut encoder = Encoder::with_config(Vec::new(), config);
+
+        encoder.write_value(&map).unwrap();
+
+        assert_eq!(
+            encoder.finish().unwrap(),
+            &[0x81, 0x01, 0xa4, b'l', b'a', b's', b't']
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_unfinished_container() {
+        let mut encoder = Encoder::new(Vec::new());
+
+        encoder.begin_array(2).unwrap();
+        encoder.write_nil().unwrap();
+
+        assert!(encoder.finish().is_err());
+    }
+}
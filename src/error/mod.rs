@@ -0,0 +1,114 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::value::OwnedValue;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
+/**
+ * @name Result
+ * @description
+ *
+ * Shorthand for results produced by the codec.
+ */
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/**
+ * @name Error
+ * @description
+ *
+ * Crate-local error type.
+ *
+ * Works on core + alloc, so the codec doesn't depend on std or anyhow.
+ */
+#[derive(Debug)]
+pub enum Error {
+    /*
+     * The buffer ended before the named value could be read
+     */
+    UnexpectedEof(&'static str),
+
+    /*
+     * The marker byte doesn't belong to the family that was expected
+     */
+    UnexpectedMarker(u8),
+
+    /*
+     * The sink refused to take more bytes (e.g. a fixed slice is full)
+     */
+    WriteZero,
+
+    /*
+     * A length that doesn't fit into the msgpack format
+     */
+    LengthOverflow(&'static str),
+
+    /*
+     * The input is valid msgpack, but goes over the reader's Limits
+     */
+    LimitExceeded(&'static str),
+
+    /*
+     * Anything else that can be described with a static message
+     */
+    Message(&'static str),
+
+    /*
+     * Anything else that needs a formatted message
+     */
+    Custom(String),
+
+    /*
+     * A packet whose code no variant of a #[derive(Packet)] enum handles,
+     * with the whole [code, [args]] envelope
+     */
+    UnknownPacket(Box<OwnedValue>),
+
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof(what) => {
+                write!(f, "I have no idea how to read a {what} from this!")
+            }
+            Error::UnexpectedMarker(marker) => write!(f, "Unexpected marker 0x{marker:02x}"),
+            Error::WriteZero => write!(f, "The sink is full, failed to write the whole buffer"),
+            Error::LengthOverflow(what) => write!(f, "{what} is too long for msgpack"),
+            Error::LimitExceeded(what) => write!(f, "{what} goes over the configured limits"),
+            Error::Message(message) => write!(f, "{message}"),
+            Error::Custom(message) => write!(f, "{message}"),
+            Error::UnknownPacket(packet) => write!(f, "Unknown packet {packet:?}"),
+            #[cfg(feature = "std")]
+            Error::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    #[inline]
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<alloc::collections::TryReserveError> for Error {
+    #[inline]
+    fn from(_: alloc::collections::TryReserveError) -> Self {
+        Error::Message("Failed to allocate memory")
+    }
+}
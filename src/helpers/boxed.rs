@@ -1,6 +1,7 @@
-use std::mem::MaybeUninit;
+use alloc::{boxed::Box, vec::Vec};
+use core::mem::MaybeUninit;
 
-use anyhow::Result;
+use crate::error::Result;
 
 /**
  * @name try_new_uninit_slice
@@ -20,9 +21,9 @@ pub fn try_new_uninit_slice<T>(len: usize) -> Result<Box<[MaybeUninit<T>]>> {
          * Safety: We've just made a Vec, so it's aligned
          */
 
-        let ptr = vec.as_mut_ptr() as *mut MaybeUninit<T>;
+        let ptr = vec.as_mut_ptr();
 
-        let slice = std::slice::from_raw_parts_mut(ptr, len);
+        let slice = core::slice::from_raw_parts_mut(ptr, len);
 
         Ok(Box::from_raw(slice as *mut [MaybeUninit<T>]))
     }
@@ -0,0 +1,46 @@
+use crate::{error::Result, io::Write};
+
+/**
+ * @name Fnv1a
+ * @description
+ *
+ * 64-bit FNV-1a, usable as a sink so values can be hashed while encoding.
+ *
+ * The algorithm is fixed, so every peer gets the same hash for the same bytes.
+ * It is NOT a cryptographic hash.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Fnv1a(Self::OFFSET_BASIS)
+    }
+
+    #[inline(always)]
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    #[inline(always)]
+    fn default() -> Self {
+        Fnv1a::new()
+    }
+}
+
+impl Write for Fnv1a {
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        for &byte in buf {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(Self::PRIME);
+        }
+
+        Ok(())
+    }
+}
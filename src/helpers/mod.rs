@@ -1,4 +1,5 @@
 pub mod boxed;
+pub mod fnv;
 
 #[macro_export]
 macro_rules! array {
@@ -0,0 +1,196 @@
+use crate::error::Result;
+
+#[cfg(not(feature = "std"))]
+use crate::error::Error;
+use alloc::vec::Vec;
+
+/**
+ * @name Write
+ * @description
+ *
+ * Byte sink the encoders write into.
+ *
+ * With the `std` feature every std::io::Write is a sink.
+ * Without it, Vec<u8> and &mut [u8] are.
+ */
+pub trait Write {
+    fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> Write for W {
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        std::io::Write::write_all(self, buf)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.extend_from_slice(buf);
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for &mut [u8] {
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        if buf.len() > self.len() {
+            return Err(Error::WriteZero);
+        }
+
+        /*
+         * Same semantics as std: the slice shrinks from the front
+         */
+        let (head, tail) = core::mem::take(self).split_at_mut(buf.len());
+
+        head.copy_from_slice(buf);
+        *self = tail;
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        (**self).write_all(buf)
+    }
+}
+
+/**
+ * @name Backpatch
+ * @description
+ *
+ * Sink that can go back and rewrite bytes it has already taken.
+ *
+ * Used by the encoder to fill in headers of containers whose length
+ * wasn't known up front.
+ */
+pub trait Backpatch: Write {
+    /*
+     * Whether `remove` is supported, i.e. the sink can shift bytes after a patch
+     */
+    const SHIFTS: bool;
+
+    /*
+     * Position the next byte will be written at
+     */
+    fn position(&mut self) -> Result<usize>;
+
+    /*
+     * Overwrites already written bytes starting at `at`
+     */
+    fn patch(&mut self, at: usize, bytes: &[u8]) -> Result<()>;
+
+    /*
+     * Removes `len` bytes at `at`, shifting everything after them
+     */
+    fn remove(&mut self, at: usize, len: usize) -> Result<()>;
+}
+
+impl Backpatch for Vec<u8> {
+    const SHIFTS: bool = true;
+
+    #[inline(always)]
+    fn position(&mut self) -> Result<usize> {
+        Ok(self.len())
+    }
+
+    #[inline(always)]
+    fn patch(&mut self, at: usize, bytes: &[u8]) -> Result<()> {
+        self[at..at + bytes.len()].copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, at: usize, len: usize) -> Result<()> {
+        self.drain(at..at + len);
+
+        Ok(())
+    }
+}
+
+impl<B: Backpatch + ?Sized> Backpatch for &mut B
+where
+    for<'b> &'b mut B: Write,
+{
+    const SHIFTS: bool = B::SHIFTS;
+
+    #[inline(always)]
+    fn position(&mut self) -> Result<usize> {
+        (**self).position()
+    }
+
+    #[inline(always)]
+    fn patch(&mut self, at: usize, bytes: &[u8]) -> Result<()> {
+        (**self).patch(at, bytes)
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, at: usize, len: usize) -> Result<()> {
+        (**self).remove(at, len)
+    }
+}
+
+/**
+ * @name Seekable
+ * @description
+ *
+ * Adapter that lets any seekable std::io sink (files, cursors) be backpatched.
+ *
+ * Bytes can't be shifted in place, so headers stay 32-bit wide.
+ */
+#[cfg(feature = "std")]
+pub struct Seekable<W: std::io::Write + std::io::Seek>(pub W);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + std::io::Seek> std::io::Write for Seekable<W> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + std::io::Seek> Backpatch for Seekable<W> {
+    const SHIFTS: bool = false;
+
+    #[inline]
+    fn position(&mut self) -> Result<usize> {
+        Ok(self.0.stream_position()? as usize)
+    }
+
+    #[inline]
+    fn patch(&mut self, at: usize, bytes: &[u8]) -> Result<()> {
+        use std::io::SeekFrom;
+
+        let end = self.0.stream_position()?;
+
+        self.0.seek(SeekFrom::Start(at as u64))?;
+        self.0.write_all(bytes)?;
+        self.0.seek(SeekFrom::Start(end))?;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn remove(&mut self, _at: usize, _len: usize) -> Result<()> {
+        Err(crate::error::Error::Message(
+            "Seekable sinks can't shift bytes",
+        ))
+    }
+}
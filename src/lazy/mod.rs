@@ -0,0 +1,350 @@
+use crate::error::{Error, Result};
+
+use crate::{
+    constants::Families,
+    msgpack::{ReadFrom, array::read_array_len, map::read_map_len},
+    reader::{Kind, Limits, Reader},
+    value::Value,
+};
+
+/**
+ * @name LazyValue
+ * @description
+ *
+ * Cursor over an encoded value that decodes nothing up front.
+ *
+ * Navigating with `array_get` and `map_get` steps over siblings without
+ * decoding them, and only what's finally read gets materialised. The
+ * cursor starts at its value, but may run past its end: it's only ever
+ * read as far as needed.
+ *
+ * Stepping over values allocates nothing and isn't bounded, while reading
+ * and materialising go through the cursor's Limits, `Limits::DEFAULT`
+ * unless set with `with_limits`.
+ *
+ * ```
+ * use msgpackp::lazy::LazyValue;
+ *
+ * let snapshot = [0x92, 0xa2, b'c', b'h', 0x82, 0xa1, b'x', 0x01, 0xa1, b'y', 0x02];
+ *
+ * let args = LazyValue::new(&snapshot).array_get(1)?.unwrap();
+ *
+ * assert_eq!(args.map_get("y")?.unwrap().read::<u8>()?, 2);
+ * assert!(args.map_get("z")?.is_none());
+ * # Ok::<(), msgpackp::error::Error>(())
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct LazyValue<'buf> {
+    bytes: &'buf [u8],
+    limits: Limits,
+}
+
+impl<'buf> LazyValue<'buf> {
+    #[inline(always)]
+    pub fn new(bytes: &'buf [u8]) -> Self {
+        LazyValue::with_limits(bytes, Limits::DEFAULT)
+    }
+
+    #[inline(always)]
+    pub fn with_limits(bytes: &'buf [u8], limits: Limits) -> Self {
+        LazyValue { bytes, limits }
+    }
+
+    /*
+     * Skipping allocates nothing, so container sizes don't matter here
+     */
+    #[inline(always)]
+    fn reader(&self) -> Reader<'buf> {
+        Reader::with_limits(self.bytes, Limits::UNLIMITED)
+    }
+
+    #[inline(always)]
+    fn at(&self, reader: &Reader<'buf>) -> LazyValue<'buf> {
+        LazyValue::with_limits(&self.bytes[reader.position()..], self.limits)
+    }
+
+    /**
+     * @name peek_marker
+     * @description
+     *
+     * Marker of the value, to branch on its type before reading it.
+     */
+    #[inline(always)]
+    pub fn peek_marker(&self) -> Result<u8> {
+        self.bytes
+            .first()
+            .copied()
+            .ok_or(Error::UnexpectedEof("marker"))
+    }
+
+    #[inline(always)]
+    pub fn peek_kind(&self) -> Result<Kind> {
+        self.peek_marker().map(Kind::of_marker)
+    }
+
+    /**
+     * @name len
+     * @description
+     *
+     * Element count of an array or pair count of a map.
+     */
+    #[inline]
+    pub fn len(&self) -> Result<usize> {
+        let mut reader = self.reader();
+        let marker = reader.pull_marker()?;
+
+        match marker {
+            0x80..=0x8f | Families::MAP16 | Families::MAP32 => read_map_len(marker, &mut reader),
+            _ => read_array_len(marker, &mut reader),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /**
+     * @name array_get
+     * @description
+     *
+     * Element `index` of an array, stepping over the ones before it.
+     *
+     * An index past the end is `Ok(None)`, anything but an array is an
+     * `UnexpectedMarker` error.
+     */
+    pub fn array_get(&self, index: usize) -> Result<Option<LazyValue<'buf>>> {
+        let mut reader = self.reader();
+        let len = read_array_len(reader.pull_marker()?, &mut reader)?;
+
+        if index >= len {
+            return Ok(None);
+        }
+
+        for _ in 0..index {
+            reader.skip_value()?;
+        }
+
+        Ok(Some(self.at(&reader)))
+    }
+
+    /**
+     * @name map_get
+     * @description
+     *
+     * Value of the first entry whose key is the string `key`.
+     *
+     * Keys that aren't strings and values of other keys are stepped over.
+     * A missing key is `Ok(None)`, anything but a map is an
+     * `UnexpectedMarker` error.
+     */
+    pub fn map_get(&self, key: &str) -> Result<Option<LazyValue<'buf>>> {
+        let mut reader = self.reader();
+        let len = read_map_len(reader.pull_marker()?, &mut reader)?;
+
+        for _ in 0..len {
+            let matches = match reader.peek() {
+                Some(0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32) => {
+                    let marker = reader.pull_marker()?;
+
+                    reader.pull_borrowed_str(marker)? == key
+                }
+                _ => {
+                    reader.skip_value()?;
+                    false
+                }
+            };
+
+            if matches {
+                return Ok(Some(self.at(&reader)));
+            }
+
+            reader.skip_value()?;
+        }
+
+        Ok(None)
+    }
+
+    /**
+     * @name read
+     * @description
+     *
+     * Decodes the value into `T`, within the cursor's Limits.
+     */
+    #[inline(always)]
+    pub fn read<T: ReadFrom<'buf>>(&self) -> Result<T> {
+        Reader::with_limits(self.bytes, self.limits).read()
+    }
+
+    /**
+     * @name to_value
+     * @description
+     *
+     * Decodes the whole value, the only step that allocates. Nesting
+     * deeper than the cursor's `max_depth` is a `LimitExceeded` error.
+     */
+    #[inline]
+    pub fn to_value(&self) -> Result<Value<'buf>> {
+        Reader::with_limits(self.bytes, self.limits).pull_value()
+    }
+
+    /**
+     * @name raw
+     * @description
+     *
+     * The encoded bytes of exactly this value.
+     */
+    #[inline]
+    pub fn raw(&self) -> Result<&'buf [u8]> {
+        let mut reader = self.reader();
+
+        reader.skip_value()?;
+
+        Ok(&self.bytes[..reader.position()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+    use crate::msgpack::WriteTo;
+
+    fn snapshot() -> Vec<u8> {
+        let players = (0..150u16)
+            .map(|id| (id, ("player", id * 2)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut config = BTreeMap::new();
+
+        config.insert("tick", 30u16);
+        config.insert("width", 14400);
+
+        let mut buffer = Vec::new();
+
+        (vec![false; 1000], players, config, "end")
+            .write_to(&mut buffer)
+            .unwrap();
+
+        buffer
+    }
+
+    #[test]
+    fn test_array_get() {
+        let buffer = snapshot();
+        let root = LazyValue::new(&buffer);
+
+        assert_eq!(root.len().unwrap(), 4);
+        assert_eq!(
+            root.array_get(3).unwrap().unwrap().read::<&str>().unwrap(),
+            "end"
+        );
+        assert!(root.array_get(4).unwrap().is_none());
+
+        /*
+         * The map of players is over the default limits, but only stepped over
+         */
+        let players = root.array_get(1).unwrap().unwrap();
+
+        assert_eq!(players.peek_kind().unwrap(), Kind::Map);
+        assert_eq!(players.len().unwrap(), 150);
+        assert!(players.to_value().is_err());
+
+        let raw = root.array_get(0).unwrap().unwrap().raw().unwrap();
+
+        assert_eq!(raw.len(), 1003);
+        assert_eq!(raw[..3], [0xdc, 0x03, 0xe8]);
+    }
+
+    #[test]
+    fn test_map_get() {
+        let buffer = snapshot();
+        let root = LazyValue::new(&buffer);
+        let config = root.array_get(2).unwrap().unwrap();
+
+        assert_eq!(
+            config
+                .map_get("width")
+                .unwrap()
+                .unwrap()
+                .read::<u16>()
+                .unwrap(),
+            14400
+        );
+        assert!(config.map_get("height").unwrap().is_none());
+        assert_eq!(
+            config.to_value().unwrap(),
+            Reader::new(config.raw().unwrap()).pull_value().unwrap()
+        );
+
+        /*
+         * Integer keys are stepped over like any other value
+         */
+        let players = root.array_get(1).unwrap().unwrap();
+
+        assert!(players.map_get("0").unwrap().is_none());
+
+        assert!(matches!(
+            root.map_get("tick"),
+            Err(Error::UnexpectedMarker(0x94))
+        ));
+        assert!(matches!(
+            config.array_get(0),
+            Err(Error::UnexpectedMarker(0x82))
+        ));
+        assert!(
+            LazyValue::new(&buffer[..buffer.len() - 1])
+                .array_get(3)
+                .unwrap()
+                .unwrap()
+                .read::<&str>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut deep = vec![0x91; 100_000];
+
+        deep.push(0xc0);
+
+        let root = LazyValue::new(&deep);
+
+        /*
+         * Stepping in is fine, materialising stops at max_depth
+         */
+        assert_eq!(root.array_get(0).unwrap().unwrap().len().unwrap(), 1);
+        assert_eq!(root.raw().unwrap().len(), deep.len());
+        assert!(matches!(
+            root.to_value(),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+        assert!(matches!(
+            root.read::<Value>(),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        let limits = Limits {
+            max_depth: 1,
+            ..Limits::DEFAULT
+        };
+        let shallow = LazyValue::with_limits(&deep[deep.len() - 2..], limits);
+
+        assert!(shallow.to_value().is_ok());
+        assert!(
+            LazyValue::with_limits(&deep[deep.len() - 3..], limits)
+                .array_get(0)
+                .unwrap()
+                .unwrap()
+                .to_value()
+                .is_ok()
+        );
+        assert!(
+            LazyValue::with_limits(&deep[deep.len() - 3..], limits)
+                .to_value()
+                .is_err()
+        );
+    }
+}
@@ -1,5 +1,32 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/*
+ * Lets derived impls name `::msgpackp` from inside the crate too
+ */
+extern crate self as msgpackp;
+
+#[cfg(feature = "bumpalo")]
+pub mod arena;
+#[cfg(feature = "bytes")]
+pub mod bytes;
 pub mod constants;
+pub mod derive;
+pub mod encoder;
+pub mod error;
 pub mod helpers;
+pub mod io;
+pub mod lazy;
 pub mod msgpack;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod projection;
 pub mod reader;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod stream;
+pub mod tape;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 pub mod value;
@@ -1,8 +1,10 @@
-use std::{io::Write, mem::MaybeUninit};
+use alloc::boxed::Box;
+use core::mem::MaybeUninit;
 
-use anyhow::Result;
+use crate::error::{Error, Result};
 
 use crate::{
+    io::Write,
     msgpack::{ReadFrom, WriteTo},
     reader::Reader,
     value::Value,
@@ -16,6 +18,56 @@ impl Array {
     pub const FIXARRAY_TYPE: u8 = 0x90;
 }
 
+/**
+ * @name write_array_header
+ * @description
+ *
+ * Writes the smallest array header that fits `array_length` elements.
+ */
+#[inline(always)]
+pub fn write_array_header<U: Write>(array_length: usize, buffer: &mut U) -> Result<()> {
+    match array_length {
+        /*
+         * Fixarr size is 15
+         */
+        0..=15 => buffer.write_all(&[Array::FIXARRAY_TYPE + array_length as u8])?,
+
+        /*
+         * array 16 stores an array whose length is upto (2^16)-1 elements:
+         * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xdc  |YYYYYYYY|YYYYYYYY|    N objects    |
+         * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         */
+        16..=0xffff => {
+            let length = (array_length as u16).to_be_bytes();
+
+            buffer.write_all(&[Array::ARRAY_16_TYPE, length[0], length[1]])?;
+        }
+
+        /*
+         * array 32 stores an array whose length is upto (2^32)-1 elements:
+         * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xdd  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|    N objects    |
+         * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         */
+        0x10000..=0xffff_ffff => {
+            let length = (array_length as u32).to_be_bytes();
+
+            buffer.write_all(&[
+                Array::ARRAY_32_TYPE,
+                length[0],
+                length[1],
+                length[2],
+                length[3],
+            ])?;
+        }
+
+        _ => return Err(Error::LengthOverflow("Arr64")),
+    }
+
+    Ok(())
+}
+
 impl WriteTo for Box<[Value<'_>]> {
     #[inline(always)]
     /**
@@ -25,40 +77,7 @@ impl WriteTo for Box<[Value<'_>]> {
      * Implements an Array writer
      */
     fn write_to<U: Write>(&self, buffer: &mut U) -> Result<()> {
-        let array_length = self.len();
-
-        match array_length {
-            /*
-             * Fixarr size is 15
-             */
-            0..=15 => buffer.write_all(&[Array::FIXARRAY_TYPE + array_length as u8])?,
-
-            /*
-             * Arr16 size is u16-1
-             */
-            16..=65534 => {
-                let length = array_length.to_be_bytes();
-
-                buffer.write_all(&[Array::ARRAY_16_TYPE, length[0], length[1]])?;
-            }
-
-            /*
-             * Arr32 size is u32-1
-             */
-            65535..4294967295 => {
-                let length = array_length.to_be_bytes();
-
-                buffer.write_all(&[
-                    Array::ARRAY_32_TYPE,
-                    length[0],
-                    length[1],
-                    length[2],
-                    length[3],
-                ])?;
-            }
-
-            _ => return Err(anyhow::anyhow!("Arr64 is not supported by msgpack")),
-        }
+        write_array_header(self.len(), buffer)?;
 
         for value in self {
             value.write_to(buffer)?;
@@ -68,100 +87,123 @@ impl WriteTo for Box<[Value<'_>]> {
     }
 }
 
-impl<'a> ReadFrom<'a> for Box<[Value<'a>]> {
-    #[inline(always)]
-    fn read_from<U: AsRef<[u8]> + 'a>(array_type: u8, reader: &'a mut Reader<U>) -> Result<Self> {
-        let array_length = match array_type {
-            /*
-             * Fixarr ranges from 0x90 to 0x9f
-             *
-             * https://github.com/msgpack/msgpack/blob/master/spec.md#map-format-family:~:text=1001xxxx-,0x90%20%2D%200x9f,-fixstr
-             */
-            0x90..=0x9f => (array_type - 0x90) as usize,
+/**
+ * @name read_array_len
+ * @description
+ *
+ * Reads the element count of an array whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_array_len(array_type: u8, reader: &mut Reader<'_>) -> Result<usize> {
+    let array_length = match array_type {
+        /*
+         * Fixarr ranges from 0x90 to 0x9f
+         *
+         * https://github.com/msgpack/msgpack/blob/master/spec.md#map-format-family:~:text=1001xxxx-,0x90%20%2D%200x9f,-fixstr
+         */
+        0x90..=0x9f => (array_type - 0x90) as usize,
 
-            /*
-             * Arr16
-             */
-            Array::ARRAY_16_TYPE => {
-                let bytes = reader.pull(2);
-
-                if bytes.len() != 2 {
-                    return Err(anyhow::anyhow!(
-                        "I have no idea how to read a Array16 from this!"
-                    ));
-                }
-
-                /*
-                 * array 16 stores an array whose length is upto (2^16)-1 elements:
-                 * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
-                 * |  0xdc  |YYYYYYYY|YYYYYYYY|    N objects    |
-                 * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
-                 */
-
-                u16::from_be_bytes([bytes[0], bytes[1]]) as usize
+        /*
+         * Arr16
+         */
+        Array::ARRAY_16_TYPE => {
+            let bytes = reader.pull(2);
+
+            if bytes.len() != 2 {
+                return Err(Error::UnexpectedEof("Array16"));
             }
 
             /*
-             * Arr32
+             * array 16 stores an array whose length is upto (2^16)-1 elements:
+             * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+             * |  0xdc  |YYYYYYYY|YYYYYYYY|    N objects    |
+             * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
              */
-            Array::ARRAY_32_TYPE => {
-                let bytes = reader.pull(4);
-
-                if bytes.len() != 4 {
-                    return Err(anyhow::anyhow!(
-                        "I have no idea how to read a Array32 from this!"
-                    ));
-                }
-
-                /*
-                 * array 32 stores an array whose length is upto (2^32)-1 elements:
-                 * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
-                 * |  0xdd  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|    N objects    |
-                 * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
-                 */
-
-                u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
-            }
-            _ => return Ok(vec![].into_boxed_slice()),
-        };
+
+            u16::from_be_bytes([bytes[0], bytes[1]]) as usize
+        }
 
         /*
-         * Note: DO NOT USE with_capacity!
-         *
-         * Preemptive allocations slow down everything **4 times**
+         * Arr32
          */
+        Array::ARRAY_32_TYPE => {
+            let bytes = reader.pull(4);
+
+            if bytes.len() != 4 {
+                return Err(Error::UnexpectedEof("Array32"));
+            }
+
+            /*
+             * array 32 stores an array whose length is upto (2^32)-1 elements:
+             * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+             * |  0xdd  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|    N objects    |
+             * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+             */
 
-        let mut uninit = Box::<[Value]>::new_uninit_slice(array_length);
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+        }
+        _ => return Err(Error::UnexpectedMarker(array_type)),
+    };
 
-        unsafe {
-            let reader_ptr = reader as *mut Reader<U>;
-            let ptr = uninit.as_mut_ptr();
+    Ok(array_length)
+}
 
-            let mut i = 0;
+/**
+ * @name read_array
+ * @description
+ *
+ * Reads the elements of an array whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_array<'buf>(array_type: u8, reader: &mut Reader<'buf>) -> Result<Box<[Value<'buf>]>> {
+    let array_length = read_array_len(array_type, reader)?;
+    let array_length = reader.container_len(reader.limits().check_array(array_length)?)?;
+
+    reader.nested(|reader| read_elements(array_length, reader))
+}
 
-            while i + 4 < array_length {
-                ptr.add(i)
-                    .write(MaybeUninit::new((&mut *reader_ptr).pull_value()?));
-                ptr.add(i + 1)
-                    .write(MaybeUninit::new((&mut *reader_ptr).pull_value()?));
-                ptr.add(i + 2)
-                    .write(MaybeUninit::new((&mut *reader_ptr).pull_value()?));
-                ptr.add(i + 3)
-                    .write(MaybeUninit::new((&mut *reader_ptr).pull_value()?));
+#[inline]
+fn read_elements<'buf>(
+    array_length: usize,
+    reader: &mut Reader<'buf>,
+) -> Result<Box<[Value<'buf>]>> {
+    /*
+     * Note: DO NOT USE with_capacity!
+     *
+     * Preemptive allocations slow down everything **4 times**
+     */
 
-                i += 4;
-            }
+    let mut uninit = Box::<[Value]>::new_uninit_slice(array_length);
 
-            while i < array_length {
-                ptr.add(i)
-                    .write(MaybeUninit::new((&mut *reader_ptr).pull_value()?));
+    unsafe {
+        let ptr = uninit.as_mut_ptr();
 
-                i += 1;
-            }
+        let mut i = 0;
 
-            let values = uninit.assume_init();
+        while i + 4 < array_length {
+            ptr.add(i).write(MaybeUninit::new(reader.pull_value()?));
+            ptr.add(i + 1).write(MaybeUninit::new(reader.pull_value()?));
+            ptr.add(i + 2).write(MaybeUninit::new(reader.pull_value()?));
+            ptr.add(i + 3).write(MaybeUninit::new(reader.pull_value()?));
 
-            Ok(values)
+            i += 4;
         }
+
+        while i < array_length {
+            ptr.add(i).write(MaybeUninit::new(reader.pull_value()?));
+
+            i += 1;
+        }
+
+        let values = uninit.assume_init();
+
+        Ok(values)
+    }
+}
+
+impl<'a> ReadFrom<'a> for Box<[Value<'a>]> {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_array(reader.pull_marker()?, reader)
     }
 }
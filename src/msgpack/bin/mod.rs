@@ -1,24 +1,67 @@
+use alloc::vec::Vec;
+
 use crate::constants::Families;
+use crate::error::{Error, Result};
+use crate::io::Write;
 use crate::msgpack::{ReadFrom, WriteTo};
 use crate::reader::Reader;
-use anyhow::Result;
-use std::io::Write;
 
-impl WriteTo for Vec<u8> {
+/**
+ * @name write_bin_header
+ * @description
+ *
+ * Writes the smallest bin header that fits `len` bytes.
+ */
+#[inline(always)]
+pub fn write_bin_header<U: Write>(len: usize, writer: &mut U) -> Result<()> {
+    if len <= 0xff {
+        /*
+         * bin 8 stores a byte array whose length is upto (2^8)-1 bytes:
+         * +--------+--------+========+
+         * |  0xc4  |XXXXXXXX|  data  |
+         * +--------+--------+========+
+         */
+
+        writer.write_all(&[Families::BIN8, len as u8])?;
+    } else if len <= 0xffff {
+        /*
+         * bin 16 stores a byte array whose length is upto (2^16)-1 bytes:
+         * +--------+--------+--------+========+
+         * |  0xc5  |YYYYYYYY|YYYYYYYY|  data  |
+         * +--------+--------+--------+========+
+         */
+
+        let bytes = (len as u16).to_be_bytes();
+
+        writer.write_all(&[Families::BIN16, bytes[0], bytes[1]])?;
+    } else if len <= 0xffff_ffff {
+        /*
+         * bin 32 stores a byte array whose length is upto (2^32)-1 bytes:
+         * +--------+--------+--------+--------+--------+========+
+         * |  0xc6  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|  data  |
+         * +--------+--------+--------+--------+--------+========+
+         */
+
+        let bytes = (len as u32).to_be_bytes();
+
+        writer.write_all(&[Families::BIN32, bytes[0], bytes[1], bytes[2], bytes[3]])?;
+    } else {
+        return Err(Error::LengthOverflow("Bin"));
+    }
+
+    Ok(())
+}
+
+impl WriteTo for [u8] {
+    /**
+     * @name write_to
+     * @description
+     *
+     * Byte slices are bin, and so are `Vec<u8>`s.
+     */
     #[inline(always)]
     fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        let len = self.len();
-
-        if len <= 31 {
-            writer.write_all(&[Families::BIN8])?;
-            writer.write_all(&[len as u8])?;
-        } else if len <= 255 {
-            writer.write_all(&[Families::BIN16])?;
-            writer.write_all(&(len as u16).to_be_bytes())?;
-        } else if len <= 65535 {
-            writer.write_all(&[Families::BIN32])?;
-            writer.write_all(&(len as u32).to_be_bytes())?;
-        }
+        write_bin_header(self.len(), writer)?;
 
         writer.write_all(self)?;
 
@@ -26,38 +69,88 @@ impl WriteTo for Vec<u8> {
     }
 }
 
-impl<'a> ReadFrom<'a> for Vec<u8> {
-    #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let len = match packet_type {
-            Families::BIN8 => reader.pull(1)[0] as usize,
-            Families::BIN16 => {
-                let buf = reader.pull(2);
-
-                if buf.len() != 2 {
-                    return Err(anyhow::anyhow!(
-                        "I have no idea how to read a BIN16 from this!"
-                    ));
-                }
-
-                u16::from_be_bytes([buf[0], buf[1]]) as usize
+/**
+ * @name read_bin_len
+ * @description
+ *
+ * Reads the byte count of a bin whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_bin_len(packet_type: u8, reader: &mut Reader<'_>) -> Result<usize> {
+    let len = match packet_type {
+        Families::BIN8 => {
+            let buf = reader.pull(1);
+
+            if buf.len() != 1 {
+                return Err(Error::UnexpectedEof("BIN8"));
             }
-            Families::BIN32 => {
-                let buf = reader.pull(4);
 
-                if buf.len() != 4 {
-                    return Err(anyhow::anyhow!(
-                        "I have no idea how to read a BIN32 from this!"
-                    ));
-                }
+            buf[0] as usize
+        }
+        Families::BIN16 => {
+            let buf = reader.pull(2);
 
-                u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize
+            if buf.len() != 2 {
+                return Err(Error::UnexpectedEof("BIN16"));
             }
-            _ => 0,
-        };
 
-        let data = reader.pull(len);
+            u16::from_be_bytes([buf[0], buf[1]]) as usize
+        }
+        Families::BIN32 => {
+            let buf = reader.pull(4);
 
-        Ok(data.to_vec())
+            if buf.len() != 4 {
+                return Err(Error::UnexpectedEof("BIN32"));
+            }
+
+            u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize
+        }
+        _ => return Err(Error::UnexpectedMarker(packet_type)),
+    };
+
+    Ok(len)
+}
+
+impl<'a> ReadFrom<'a> for &'a [u8] {
+    /**
+     * @name read_from
+     * @description
+     *
+     * Borrows a bin payload from the reader's buffer.
+     */
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        let packet_type = reader.pull_marker()?;
+
+        reader.pull_bin(packet_type)
     }
 }
+
+/**
+ * @name read_byte_vec
+ * @description
+ *
+ * Reads a bin into a `Vec<u8>`.
+ */
+#[inline(always)]
+pub(crate) fn read_byte_vec(reader: &mut Reader<'_>) -> Result<Vec<u8>> {
+    let packet_type = reader.pull_marker()?;
+
+    reader.pull_bin(packet_type).map(<[u8]>::to_vec)
+}
+
+/**
+ * @name read_byte_array
+ * @description
+ *
+ * Reads a bin of exactly N bytes into a `[u8; N]`.
+ */
+#[inline(always)]
+pub(crate) fn read_byte_array<const N: usize>(reader: &mut Reader<'_>) -> Result<[u8; N]> {
+    let packet_type = reader.pull_marker()?;
+
+    reader
+        .pull_bin(packet_type)?
+        .try_into()
+        .map_err(|_| Error::Message("Array length doesn't match"))
+}
@@ -1,8 +1,8 @@
 use crate::constants::Families;
+use crate::error::{Error, Result};
+use crate::io::Write;
 use crate::msgpack::{ReadFrom, WriteTo};
 use crate::reader::Reader;
-use anyhow::Result;
-use std::io::Write;
 
 impl WriteTo for bool {
     #[inline(always)]
@@ -21,7 +21,11 @@ impl WriteTo for bool {
 
 impl<'a> ReadFrom<'a> for bool {
     #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(packet_type: u8, _reader: &mut Reader<T>) -> Result<Self> {
-        Ok(packet_type == Families::TRUE)
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        match reader.pull_marker()? {
+            Families::TRUE => Ok(true),
+            Families::FALSE => Ok(false),
+            marker => Err(Error::UnexpectedMarker(marker)),
+        }
     }
 }
@@ -0,0 +1,350 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+use crate::io::Write;
+use crate::msgpack::{
+    ReadFrom, WriteTo, array::read_array_len, array::write_array_header, map::read_map_len,
+    map::write_map_header,
+};
+use crate::reader::Reader;
+
+/*
+ * Sequences are arrays and maps are maps. Nothing is preallocated from
+ * the length in the header, it comes from the network.
+ */
+
+/**
+ * @name write_seq
+ * @description
+ *
+ * Writes `len` values as an array.
+ */
+#[inline(always)]
+pub(crate) fn write_seq<'v, V: WriteTo + 'v, U: Write>(
+    len: usize,
+    values: impl Iterator<Item = &'v V>,
+    writer: &mut U,
+) -> Result<()> {
+    write_array_header(len, writer)?;
+
+    for value in values {
+        value.write_to(writer)?;
+    }
+
+    Ok(())
+}
+
+/**
+ * @name write_entries
+ * @description
+ *
+ * Writes `len` key-value pairs as a map.
+ */
+#[inline(always)]
+fn write_entries<'v, K: WriteTo + 'v, V: WriteTo + 'v, U: Write>(
+    len: usize,
+    entries: impl Iterator<Item = (&'v K, &'v V)>,
+    writer: &mut U,
+) -> Result<()> {
+    write_map_header(len, writer)?;
+
+    for (key, value) in entries {
+        key.write_to(writer)?;
+        value.write_to(writer)?;
+    }
+
+    Ok(())
+}
+
+/**
+ * @name read_seq
+ * @description
+ *
+ * Reads an array into a Vec.
+ */
+#[inline]
+pub(crate) fn read_seq<'a, V: ReadFrom<'a>>(reader: &mut Reader<'a>) -> Result<Vec<V>> {
+    let len = read_array_len(reader.pull_marker()?, reader)?;
+    let mut values = Vec::new();
+
+    for _ in 0..len {
+        values.push(reader.read()?);
+    }
+
+    Ok(values)
+}
+
+/**
+ * @name read_array
+ * @description
+ *
+ * Reads an array of exactly N elements.
+ */
+#[inline]
+pub(crate) fn read_array<'a, V: ReadFrom<'a>, const N: usize>(
+    reader: &mut Reader<'a>,
+) -> Result<[V; N]> {
+    if read_array_len(reader.pull_marker()?, reader)? != N {
+        return Err(Error::Message("Array length doesn't match"));
+    }
+
+    let mut values = Vec::with_capacity(N);
+
+    for _ in 0..N {
+        values.push(reader.read()?);
+    }
+
+    values
+        .try_into()
+        .map_err(|_| Error::Message("Array length doesn't match"))
+}
+
+/*
+ * Vec<u8> and [u8; N] are bytes on the wire rather than arrays of
+ * integers: the element type decides, see `WriteTo::write_vec`
+ */
+
+impl<V: WriteTo> WriteTo for Vec<V> {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        V::write_vec(self, writer)
+    }
+}
+
+impl<'a, V: ReadFrom<'a>> ReadFrom<'a> for Vec<V> {
+    #[inline]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        V::read_vec(reader)
+    }
+}
+
+impl<V: WriteTo> WriteTo for VecDeque<V> {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        write_seq(self.len(), self.iter(), writer)
+    }
+}
+
+impl<'a, V: ReadFrom<'a>> ReadFrom<'a> for VecDeque<V> {
+    #[inline]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_seq(reader).map(VecDeque::from)
+    }
+}
+
+impl<V: WriteTo, const N: usize> WriteTo for [V; N] {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        V::write_array(self, writer)
+    }
+}
+
+impl<'a, V: ReadFrom<'a>, const N: usize> ReadFrom<'a> for [V; N] {
+    #[inline]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        V::read_array(reader)
+    }
+}
+
+impl<K: WriteTo, V: WriteTo> WriteTo for BTreeMap<K, V> {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        write_entries(self.len(), self.iter(), writer)
+    }
+}
+
+impl<'a, K: ReadFrom<'a> + Ord, V: ReadFrom<'a>> ReadFrom<'a> for BTreeMap<K, V> {
+    /**
+     * @name read_from
+     * @description
+     *
+     * Reads a map, the last entry wins on duplicate keys.
+     */
+    #[inline]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        let len = read_map_len(reader.pull_marker()?, reader)?;
+        let mut map = BTreeMap::new();
+
+        for _ in 0..len {
+            let key = reader.read()?;
+            let value = reader.read()?;
+
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: WriteTo, V: WriteTo, S> WriteTo for HashMap<K, V, S> {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        write_entries(self.len(), self.iter(), writer)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, K, V, S> ReadFrom<'a> for HashMap<K, V, S>
+where
+    K: ReadFrom<'a> + Eq + Hash,
+    V: ReadFrom<'a>,
+    S: BuildHasher + Default,
+{
+    /**
+     * @name read_from
+     * @description
+     *
+     * Reads a map, the last entry wins on duplicate keys.
+     */
+    #[inline]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        let len = read_map_len(reader.pull_marker()?, reader)?;
+        let mut map = HashMap::with_hasher(S::default());
+
+        for _ in 0..len {
+            let key = reader.read()?;
+            let value = reader.read()?;
+
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn round_trip<V: WriteTo + for<'a> ReadFrom<'a>>(value: &V) -> V {
+        let mut buffer = Vec::new();
+
+        value.write_to(&mut buffer).unwrap();
+
+        Reader::new(&buffer[..]).read().unwrap()
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut buffer = Vec::new();
+
+        vec![1u16, 300].write_to(&mut buffer).unwrap();
+        [true, false].write_to(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer,
+            &[0x92, 0xcd, 0x00, 0x01, 0xcd, 0x01, 0x2c, 0x92, 0xc3, 0xc2]
+        );
+
+        assert_eq!(round_trip(&vec![1u16, 300]), vec![1u16, 300]);
+        assert_eq!(round_trip(&[1u8, 2, 3]), [1u8, 2, 3]);
+        assert_eq!(
+            round_trip(&VecDeque::from(vec![-1i8, 5])),
+            VecDeque::from(vec![-1i8, 5])
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut buffer = Vec::new();
+
+        /*
+         * Vec<u8> and [u8; N] are bins, VecDeque<u8> stays an array
+         */
+        vec![1u8, 2].write_to(&mut buffer).unwrap();
+        [1u8, 2, 3, 4].write_to(&mut buffer).unwrap();
+        [1u8, 2, 3].write_to(&mut buffer).unwrap();
+        VecDeque::from(vec![1u8]).write_to(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer,
+            &[
+                0xc4, 0x02, 0x01, 0x02, 0xc4, 0x04, 0x01, 0x02, 0x03, 0x04, 0xc4, 0x03, 0x01, 0x02,
+                0x03, 0x91, 0x01
+            ]
+        );
+
+        let mut reader = Reader::new(&buffer[..]);
+
+        assert_eq!(reader.read::<Vec<u8>>().unwrap(), vec![1, 2]);
+        assert_eq!(reader.read::<[u8; 4]>().unwrap(), [1, 2, 3, 4]);
+        assert_eq!(reader.read::<[u8; 3]>().unwrap(), [1, 2, 3]);
+        assert_eq!(reader.read::<VecDeque<u8>>().unwrap(), vec![1]);
+
+        /*
+         * Sizes that used to be written as fixext without a type byte
+         */
+        fn check<const N: usize>() {
+            let mut buffer = Vec::new();
+
+            ([7u8; N], 5u8).write_to(&mut buffer).unwrap();
+
+            assert_eq!(&buffer[..3], &[0x92, 0xc4, N as u8]);
+            assert_eq!(crate::reader::validate(&buffer).unwrap(), buffer.len());
+            assert_eq!(
+                Reader::new(&buffer).read::<([u8; N], u8)>().unwrap(),
+                ([7u8; N], 5)
+            );
+        }
+
+        check::<1>();
+        check::<2>();
+        check::<4>();
+        check::<8>();
+        check::<16>();
+
+        assert!(
+            Reader::new(&[0xd4, 0x00, 0x09][..])
+                .read::<[u8; 1]>()
+                .is_err()
+        );
+        assert!(
+            Reader::new(&[0xc4, 0x02, 0x09, 0x09][..])
+                .read::<[u8; 1]>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_maps() {
+        let map = BTreeMap::from([(1u8, false), (2u8, true)]);
+        let mut buffer = Vec::new();
+
+        map.write_to(&mut buffer).unwrap();
+
+        assert_eq!(buffer, &[0x82, 0x01, 0xc2, 0x02, 0xc3]);
+        assert_eq!(round_trip(&map), map);
+
+        #[cfg(feature = "std")]
+        {
+            let map = HashMap::<u8, Vec<u8>>::from([(7, vec![1, 2])]);
+
+            assert_eq!(round_trip(&map), map);
+        }
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let buffer = [0x92, 0xa2, b'c', b'h', 0xa1, b'x'];
+        let mut reader = Reader::new(&buffer[..]);
+        assert_eq!(
+            Vec::<&str>::read_from(&mut reader).unwrap(),
+            vec!["ch", "x"]
+        );
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let buffer = [0x92, 0x01, 0x02];
+        let mut reader = Reader::new(&buffer[..]);
+        assert!(<[u8; 3]>::read_from(&mut reader).is_err());
+    }
+}
@@ -1,186 +1,183 @@
+use alloc::vec::Vec;
+
 use crate::constants::Families;
+use crate::error::{Error, Result};
+use crate::io::Write;
 use crate::msgpack::{ReadFrom, WriteTo};
 use crate::reader::Reader;
-use anyhow::Result;
-use std::io::Write;
 
-impl WriteTo for [u8; 1] {
-    #[inline(always)]
-    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        writer.write_all(&[Families::FIXEXT1])?;
-        writer.write_all(self)?;
-
-        Ok(())
-    }
-}
+/**
+ * @name read_ext_header
+ * @description
+ *
+ * Reads the type and data length of an ext whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_ext_header(packet_type: u8, reader: &mut Reader<'_>) -> Result<(u8, usize)> {
+    let len = match packet_type {
+        Families::FIXEXT1 => 1,
+        Families::FIXEXT2 => 2,
+        Families::FIXEXT4 => 4,
+        Families::FIXEXT8 => 8,
+        Families::FIXEXT16 => 16,
+
+        /*
+         * ext 8 stores an integer and a byte array whose length is upto (2^8)-1 bytes:
+         * +--------+--------+--------+========+
+         * |  0xc7  |XXXXXXXX|  type  |  data  |
+         * +--------+--------+--------+========+
+         */
+        Families::EXT8 => {
+            let bytes = reader.pull(1);
+
+            if bytes.len() != 1 {
+                return Err(Error::UnexpectedEof("EXT8"));
+            }
 
-impl<'a> ReadFrom<'a> for [u8; 1] {
-    #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(packet_type: u8, _reader: &mut Reader<T>) -> Result<Self> {
-        Ok([packet_type])
-    }
-}
+            bytes[0] as usize
+        }
 
-impl WriteTo for [u8; 2] {
-    #[inline(always)]
-    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        writer.write_all(&[Families::FIXEXT2])?;
-        writer.write_all(self)?;
+        /*
+         * ext 16 stores an integer and a byte array whose length is upto (2^16)-1 bytes:
+         * +--------+--------+--------+--------+========+
+         * |  0xc8  |YYYYYYYY|YYYYYYYY|  type  |  data  |
+         * +--------+--------+--------+--------+========+
+         */
+        Families::EXT16 => {
+            let bytes = reader.pull(2);
+
+            if bytes.len() != 2 {
+                return Err(Error::UnexpectedEof("EXT16"));
+            }
 
-        Ok(())
-    }
-}
+            u16::from_be_bytes([bytes[0], bytes[1]]) as usize
+        }
 
-impl<'a> ReadFrom<'a> for [u8; 2] {
-    #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(2);
+        /*
+         * ext 32 stores an integer and a byte array whose length is upto (2^32)-1 bytes:
+         * +--------+--------+--------+--------+--------+--------+========+
+         * |  0xc9  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|  type  |  data  |
+         * +--------+--------+--------+--------+--------+--------+========+
+         */
+        Families::EXT32 => {
+            let bytes = reader.pull(4);
+
+            if bytes.len() != 4 {
+                return Err(Error::UnexpectedEof("EXT32"));
+            }
 
-        if bytes.len() != 2 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an EXT16 from this!"
-            ));
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
         }
+        _ => return Err(Error::UnexpectedMarker(packet_type)),
+    };
 
-        Ok([bytes[0], bytes[1]])
-    }
-}
-
-impl WriteTo for [u8; 4] {
-    #[inline(always)]
-    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        writer.write_all(&[Families::FIXEXT4])?;
-        writer.write_all(self)?;
+    let type_ = reader.pull(1);
 
-        Ok(())
+    if type_.len() != 1 {
+        return Err(Error::UnexpectedEof("Extension"));
     }
-}
-
-impl<'a> ReadFrom<'a> for [u8; 4] {
-    #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(4);
 
-        if bytes.len() != 4 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an EXT4 from this!"
-            ));
-        }
-
-        Ok([bytes[0], bytes[1], bytes[2], bytes[3]])
-    }
+    Ok((type_[0], len))
 }
 
-impl WriteTo for [u8; 8] {
-    #[inline(always)]
-    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        writer.write_all(&[Families::FIXEXT8])?;
-        writer.write_all(self)?;
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Extension {
+    type_: u8,
+    data: Vec<u8>,
+}
 
-        Ok(())
+impl Extension {
+    pub fn new(type_: u8, data: Vec<u8>) -> Self {
+        Extension { type_, data }
     }
-}
 
-impl<'a> ReadFrom<'a> for [u8; 8] {
     #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(8);
-
-        if bytes.len() != 8 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an EXT64 from this!"
-            ));
-        }
-
-        Ok([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ])
+    pub fn type_(&self) -> u8 {
+        self.type_
     }
-}
 
-impl WriteTo for [u8; 16] {
     #[inline(always)]
-    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        writer.write_all(&[Families::FIXEXT16])?;
-        writer.write_all(self)?;
-
-        Ok(())
+    pub fn data(&self) -> &[u8] {
+        &self.data
     }
 }
 
-impl<'a> ReadFrom<'a> for [u8; 16] {
-    #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(16);
-
-        if bytes.len() != 16 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an EXT128 from this!"
-            ));
+/**
+ * @name write_ext_header
+ * @description
+ *
+ * Writes the smallest ext header for `len` bytes of data tagged with `type_`.
+ *
+ * Sizes 1, 2, 4, 8 and 16 use the fixext family.
+ */
+#[inline(always)]
+pub fn write_ext_header<U: Write>(type_: u8, len: usize, writer: &mut U) -> Result<()> {
+    match len {
+        1 => writer.write_all(&[Families::FIXEXT1, type_])?,
+        2 => writer.write_all(&[Families::FIXEXT2, type_])?,
+        4 => writer.write_all(&[Families::FIXEXT4, type_])?,
+        8 => writer.write_all(&[Families::FIXEXT8, type_])?,
+        16 => writer.write_all(&[Families::FIXEXT16, type_])?,
+        0..=0xff => writer.write_all(&[Families::EXT8, len as u8, type_])?,
+        0x100..=0xffff => {
+            let bytes = (len as u16).to_be_bytes();
+
+            writer.write_all(&[Families::EXT16, bytes[0], bytes[1], type_])?;
         }
-
-        Ok([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-            bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15],
-        ])
+        0x10000..=0xffff_ffff => {
+            let bytes = (len as u32).to_be_bytes();
+
+            writer.write_all(&[
+                Families::EXT32,
+                bytes[0],
+                bytes[1],
+                bytes[2],
+                bytes[3],
+                type_,
+            ])?;
+        }
+        _ => return Err(Error::LengthOverflow("Extension")),
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Extension {
-    type_: u8,
-    data: Vec<u8>,
-}
-
-impl Extension {
-    pub fn new(type_: u8, data: Vec<u8>) -> Self {
-        Extension { type_, data }
-    }
+    Ok(())
 }
 
 impl WriteTo for Extension {
     #[inline(always)]
     fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        let data_len = self.data.len();
+        write_ext_header(self.type_, self.data.len(), writer)?;
 
-        match data_len {
-            0..=255 => {
-                writer.write_all(&[Families::EXT8])?;
-                writer.write_all(&(data_len as u8).to_be_bytes())?;
-            }
-            256..=65535 => {
-                writer.write_all(&[Families::EXT16])?;
-                writer.write_all(&(data_len as u16).to_be_bytes())?;
-            }
-            _ => {
-                writer.write_all(&[Families::EXT32])?;
-                writer.write_all(&(data_len as u32).to_be_bytes())?;
-            }
-        }
-        writer.write_all(&self.type_.to_be_bytes())?;
         writer.write_all(&self.data)?;
 
         Ok(())
     }
 }
 
-impl<'a> ReadFrom<'a> for Extension {
-    #[inline(always)]
-    fn read_from<T: AsRef<[u8]>>(packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(4);
+/**
+ * @name read_extension
+ * @description
+ *
+ * Reads an ext whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_extension(packet_type: u8, reader: &mut Reader<'_>) -> Result<Extension> {
+    let (type_, data_len) = read_ext_header(packet_type, reader)?;
 
-        if bytes.len() != 4 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an Extension from this!"
-            ));
-        }
+    let data = reader.pull(data_len);
 
-        let data_len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
-        let data = reader.pull(data_len).to_vec();
+    if data.len() != data_len {
+        return Err(Error::UnexpectedEof("Extension"));
+    }
 
-        Ok(Extension {
-            type_: packet_type,
-            data,
-        })
+    Ok(Extension {
+        type_,
+        data: data.to_vec(),
+    })
+}
+
+impl<'a> ReadFrom<'a> for Extension {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_extension(reader.pull_marker()?, reader)
     }
 }
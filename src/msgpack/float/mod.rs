@@ -1,8 +1,8 @@
 use crate::constants::Families;
+use crate::error::{Error, Result};
+use crate::io::Write;
 use crate::msgpack::{ReadFrom, WriteTo};
 use crate::reader::Reader;
-use anyhow::Result;
-use std::io::Write;
 
 impl WriteTo for f32 {
     #[inline(always)]
@@ -28,31 +28,38 @@ impl WriteTo for f32 {
     }
 }
 
-impl<'a> ReadFrom<'a> for f32 {
-    #[inline(always)]
+/**
+ * @name read_f32
+ * @description
+ *
+ * Reads a f32 whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_f32(packet_type: u8, reader: &mut Reader<'_>) -> Result<f32> {
+    if packet_type != Families::FLOAT32 {
+        return Err(Error::UnexpectedMarker(packet_type));
+    }
+
+    let bytes = reader.pull(4);
+
+    if bytes.len() != 4 {
+        return Err(Error::UnexpectedEof("F32"));
+    }
+
     /*
-     * @name read_from
-     * @description
-     *
-     * Reads a f32 from the underlying buffer
+     * float 32 stores a floating point number in IEEE 754 single precision floating point number format:
+     * +--------+--------+--------+--------+--------+
+     * |  0xca  |XXXXXXXX|XXXXXXXX|XXXXXXXX|XXXXXXXX|
+     * +--------+--------+--------+--------+--------+
      */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(4);
-
-        if bytes.len() != 4 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read a F32 from this!"
-            ));
-        }
 
-        /*
-         * float 32 stores a floating point number in IEEE 754 single precision floating point number format:
-         * +--------+--------+--------+--------+--------+
-         * |  0xca  |XXXXXXXX|XXXXXXXX|XXXXXXXX|XXXXXXXX|
-         * +--------+--------+--------+--------+--------+
-         */
+    Ok(f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
 
-        Ok(f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+impl<'a> ReadFrom<'a> for f32 {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_f32(reader.pull_marker()?, reader)
     }
 }
 
@@ -90,32 +97,46 @@ impl WriteTo for f64 {
     }
 }
 
-impl<'a> ReadFrom<'a> for f64 {
-    #[inline(always)]
+/**
+ * @name read_f64
+ * @description
+ *
+ * Reads a f64 whose marker was already pulled, a float 32 is widened.
+ */
+#[inline(always)]
+pub fn read_f64(packet_type: u8, reader: &mut Reader<'_>) -> Result<f64> {
     /*
-     * @name read_from
-     * @description
-     *
-     * Reads a f64 from the underlying buffer
+     * Widening a float 32 is lossless
      */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(8);
+    if packet_type == Families::FLOAT32 {
+        return read_f32(packet_type, reader).map(f64::from);
+    }
 
-        if bytes.len() != 8 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read a F64 from this!"
-            ));
-        }
+    if packet_type != Families::FLOAT64 {
+        return Err(Error::UnexpectedMarker(packet_type));
+    }
 
-        /*
-         * float 64 stores a floating point number in IEEE 754 double precision floating point number format:
-         * +--------+--------+--------+--------+--------+--------+--------+--------+--------+
-         * |  0xcb  |YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|
-         * +--------+--------+--------+--------+--------+--------+--------+--------+--------+
-         */
+    let bytes = reader.pull(8);
+
+    if bytes.len() != 8 {
+        return Err(Error::UnexpectedEof("F64"));
+    }
+
+    /*
+     * float 64 stores a floating point number in IEEE 754 double precision floating point number format:
+     * +--------+--------+--------+--------+--------+--------+--------+--------+--------+
+     * |  0xcb  |YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|YYYYYYYY|
+     * +--------+--------+--------+--------+--------+--------+--------+--------+--------+
+     */
+
+    Ok(f64::from_be_bytes([
+        bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+    ]))
+}
 
-        Ok(f64::from_be_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ]))
+impl<'a> ReadFrom<'a> for f64 {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_f64(reader.pull_marker()?, reader)
     }
 }
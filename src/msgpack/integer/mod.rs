@@ -1,8 +1,13 @@
+use alloc::vec::Vec;
+
 use crate::constants::Families;
-use crate::msgpack::{ReadFrom, WriteTo};
+use crate::error::{Error, Result};
+use crate::io::Write;
+use crate::msgpack::{
+    ReadFrom, WriteTo,
+    bin::{read_byte_array, read_byte_vec},
+};
 use crate::reader::Reader;
-use anyhow::Result;
-use std::io::Write;
 
 impl WriteTo for u8 {
     #[inline(always)]
@@ -25,18 +30,18 @@ impl WriteTo for u8 {
 
         Ok(())
     }
-}
 
-impl<'a> ReadFrom<'a> for u8 {
-    #[inline(always)]
     /*
-     * @name read_from
-     * @description
-     *
-     * Reads a u8 from the underlying buffer
+     * Bytes are bin rather than arrays of integers
      */
-    fn read_from<T: AsRef<[u8]>>(packet_type: u8, _reader: &mut Reader<T>) -> Result<Self> {
-        Ok(packet_type)
+    #[inline(always)]
+    fn write_vec<U: Write>(values: &[u8], writer: &mut U) -> Result<()> {
+        values.write_to(writer)
+    }
+
+    #[inline(always)]
+    fn write_array<U: Write, const N: usize>(values: &[u8; N], writer: &mut U) -> Result<()> {
+        values[..].write_to(writer)
     }
 }
 
@@ -64,27 +69,6 @@ impl WriteTo for u16 {
     }
 }
 
-impl<'a> ReadFrom<'a> for u16 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads a u16 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let byte = reader.pull(2);
-
-        if byte.len() != 2 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read a U16 from this!"
-            ));
-        }
-
-        Ok(u16::from_be_bytes([byte[0], byte[1]]))
-    }
-}
-
 impl WriteTo for u32 {
     #[inline(always)]
     /*
@@ -109,27 +93,6 @@ impl WriteTo for u32 {
     }
 }
 
-impl<'a> ReadFrom<'a> for u32 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads a u32 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let byte = reader.pull(4);
-
-        if byte.len() != 4 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read a U32 from this!"
-            ));
-        }
-
-        Ok(u32::from_be_bytes([byte[0], byte[1], byte[2], byte[3]]))
-    }
-}
-
 impl WriteTo for u64 {
     #[inline(always)]
     /*
@@ -164,29 +127,6 @@ impl WriteTo for u64 {
     }
 }
 
-impl<'a> ReadFrom<'a> for u64 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads a u64 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let byte = reader.pull(8);
-
-        if byte.len() != 8 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read a U64 from this!"
-            ));
-        }
-
-        Ok(u64::from_be_bytes([
-            byte[0], byte[1], byte[2], byte[3], byte[4], byte[5], byte[6], byte[7],
-        ]))
-    }
-}
-
 impl WriteTo for i8 {
     #[inline(always)]
     /*
@@ -198,13 +138,11 @@ impl WriteTo for i8 {
     fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
         match self {
             -32..=-1 => {
-                let positive: u8 = u8::try_from(-self)?;
-
                 /*
-                 * Negative fixint
+                 * Negative fixint is the two's complement byte itself (111YYYYY)
                  */
 
-                writer.write_all(&[0xe0 + positive])?;
+                writer.write_all(&[*self as u8])?;
             }
 
             /*
@@ -220,36 +158,6 @@ impl WriteTo for i8 {
     }
 }
 
-impl<'a> ReadFrom<'a> for i8 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads an i8 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        if packet_type >= 0xe0 {
-            /*
-             * Read a negative fixint
-             */
-
-            return Ok(-((!packet_type as i8).wrapping_add(1)));
-        }
-
-        /*
-         * int 8 stores a 8-bit signed integer
-         * +--------+--------+
-         * |  0xd0  |ZZZZZZZZ|
-         * +--------+--------+
-         */
-
-        let byte = reader.pull(1);
-
-        Ok(i8::from_be_bytes([byte[0]]))
-    }
-}
-
 impl WriteTo for i16 {
     #[inline(always)]
     /*
@@ -274,34 +182,6 @@ impl WriteTo for i16 {
     }
 }
 
-impl<'a> ReadFrom<'a> for i16 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads an i16 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(2);
-
-        if bytes.len() != 2 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an I16 from this!"
-            ));
-        }
-
-        /*
-         * int 16 stores a 16-bit big-endian signed integer
-         * +--------+--------+--------+
-         * |  0xd1  |ZZZZZZZZ|ZZZZZZZZ|
-         * +--------+--------+--------+
-         */
-
-        Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
-    }
-}
-
 impl WriteTo for i32 {
     #[inline(always)]
     /*
@@ -326,34 +206,6 @@ impl WriteTo for i32 {
     }
 }
 
-impl<'a> ReadFrom<'a> for i32 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads an i32 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(4);
-
-        if bytes.len() != 4 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an I32 from this!"
-            ));
-        }
-
-        /*
-         * int 32 stores a 32-bit big-endian signed integer
-         * +--------+--------+--------+--------+--------+
-         * |  0xd2  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|
-         * +--------+--------+--------+--------+--------+
-         */
-
-        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
-    }
-}
-
 impl WriteTo for i64 {
     #[inline(always)]
     /*
@@ -388,32 +240,149 @@ impl WriteTo for i64 {
     }
 }
 
-impl<'a> ReadFrom<'a> for i64 {
-    #[inline(always)]
-    /*
-     * @name read_from
-     * @description
-     *
-     * Reads an i64 from the underlying buffer
-     */
-    fn read_from<T: AsRef<[u8]>>(_packet_type: u8, reader: &mut Reader<T>) -> Result<Self> {
-        let bytes = reader.pull(8);
+/**
+ * @name write_uint
+ * @description
+ *
+ * Writes an unsigned integer using the smallest format that fits it.
+ */
+#[inline(always)]
+pub fn write_uint<U: Write>(value: u64, writer: &mut U) -> Result<()> {
+    match value {
+        0..=0xff => (value as u8).write_to(writer),
+        0x100..=0xffff => (value as u16).write_to(writer),
+        0x10000..=0xffff_ffff => (value as u32).write_to(writer),
+        _ => value.write_to(writer),
+    }
+}
 
-        if bytes.len() != 8 {
-            return Err(anyhow::anyhow!(
-                "I have no idea how to read an I64 from this!"
-            ));
-        }
+/**
+ * @name write_int
+ * @description
+ *
+ * Writes a signed integer using the smallest format that fits it.
+ *
+ * Non-negative values go through the unsigned family, like every other msgpack encoder does.
+ */
+#[inline(always)]
+pub fn write_int<U: Write>(value: i64, writer: &mut U) -> Result<()> {
+    match value {
+        0.. => write_uint(value as u64, writer),
+        -0x80..=-1 => (value as i8).write_to(writer),
+        -0x8000..=-0x81 => (value as i16).write_to(writer),
+        -0x8000_0000..=-0x8001 => (value as i32).write_to(writer),
+        _ => value.write_to(writer),
+    }
+}
 
+/**
+ * @name read_integer
+ * @description
+ *
+ * Reads an integer of any width whose marker was already pulled,
+ * widened so that every msgpack integer fits.
+ */
+#[inline(always)]
+pub(crate) fn read_integer(packet_type: u8, reader: &mut Reader<'_>) -> Result<i128> {
+    macro_rules! pull {
+        ($ty:ty, $len:literal, $name:literal) => {{
+            let Ok(bytes) = <[u8; $len]>::try_from(reader.pull($len)) else {
+                return Err(Error::UnexpectedEof($name));
+            };
+
+            <$ty>::from_be_bytes(bytes) as i128
+        }};
+    }
+
+    Ok(match packet_type {
         /*
-         * int 64 stores a 64-bit big-endian signed integer
-         * +--------+--------+--------+--------+--------+--------+--------+--------+--------+
-         * |  0xd3  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|
-         * +--------+--------+--------+--------+--------+--------+--------+--------+--------+
+         * Positive fixint is the value itself (0XXXXXXX)
+         */
+        0x00..=0x7f => packet_type as i128,
+
+        /*
+         * Negative fixint is the two's complement byte itself (111YYYYY)
+         */
+        0xe0..=0xff => packet_type as i8 as i128,
+
+        /*
+         * uint 8/16/32/64 store a big-endian unsigned integer
+         * +--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xcc  |ZZZZZZZZ| (up to 0xcf, 8) |
+         * +--------+--------+~~~~~~~~~~~~~~~~~+
          */
+        Families::UINT8 => pull!(u8, 1, "U8"),
+        Families::UINT16 => pull!(u16, 2, "U16"),
+        Families::UINT32 => pull!(u32, 4, "U32"),
+        Families::UINT64 => pull!(u64, 8, "U64"),
 
-        Ok(i64::from_be_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-        ]))
+        /*
+         * int 8/16/32/64 store a big-endian signed integer
+         * +--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xd0  |ZZZZZZZZ| (up to 0xd3, 8) |
+         * +--------+--------+~~~~~~~~~~~~~~~~~+
+         */
+        Families::INT8 => pull!(i8, 1, "I8"),
+        Families::INT16 => pull!(i16, 2, "I16"),
+        Families::INT32 => pull!(i32, 4, "I32"),
+        Families::INT64 => pull!(i64, 8, "I64"),
+
+        _ => return Err(Error::UnexpectedMarker(packet_type)),
+    })
+}
+
+/*
+ * read_u8 ... read_i64 read an integer whose marker was already pulled,
+ * from any width as long as the value fits
+ */
+macro_rules! read_integers {
+    (@read $ty:ident => $read:ident) => {
+        #[inline(always)]
+        pub fn $read(packet_type: u8, reader: &mut Reader<'_>) -> Result<$ty> {
+            $ty::try_from(read_integer(packet_type, reader)?)
+                .map_err(|_| Error::Message(concat!("Integer doesn't fit into ", stringify!($ty))))
+        }
+    };
+
+    ($($ty:ident => $read:ident),+) => {
+        $(
+            read_integers!(@read $ty => $read);
+
+            impl<'a> ReadFrom<'a> for $ty {
+                #[inline(always)]
+                fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+                    $read(reader.pull_marker()?, reader)
+                }
+            }
+        )+
+    };
+}
+
+read_integers!(@read u8 => read_u8);
+
+impl<'a> ReadFrom<'a> for u8 {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_u8(reader.pull_marker()?, reader)
+    }
+
+    #[inline(always)]
+    fn read_vec(reader: &mut Reader<'a>) -> Result<Vec<u8>> {
+        read_byte_vec(reader)
+    }
+
+    #[inline(always)]
+    fn read_array<const N: usize>(reader: &mut Reader<'a>) -> Result<[u8; N]> {
+        read_byte_array(reader)
     }
 }
+
+read_integers!(
+    u16 => read_u16,
+    u32 => read_u32,
+    u64 => read_u64,
+    i8 => read_i8,
+    i16 => read_i16,
+    i32 => read_i32,
+    i64 => read_i64
+);
@@ -1,14 +1,16 @@
-use std::{io::Write, mem::MaybeUninit};
+use alloc::boxed::Box;
+use core::mem::MaybeUninit;
 
-use anyhow::Result;
+use crate::error::{Error, Result};
 
 use crate::{
+    io::Write,
     msgpack::{ReadFrom, WriteTo},
     reader::Reader,
     value::Value,
 };
 
-struct Map();
+pub struct Map();
 
 impl Map {
     pub const FIXMAP: u8 = 0x80;
@@ -16,53 +18,66 @@ impl Map {
     pub const MAP_32_TYPE: u8 = 0xdf;
 }
 
-impl WriteTo for Box<[(Value<'_>, Value<'_>)]> {
-    #[inline(always)]
-    fn write_to<U: Write>(&self, buffer: &mut U) -> Result<()> {
-        let map_length = self.len();
-
-        match map_length {
-            /*
-             * Fixmap size is 15
-             *
-             * https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=1000xxxx-,0x80%20%2D%200x8f,-fixarray
-             */
-            0..=15 => buffer.write_all(&[Map::FIXMAP + map_length as u8])?,
-
-            /*
-             * map 16 stores a map whose length is upto (2^16)-1 elements
-             * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             * |  0xde  |YYYYYYYY|YYYYYYYY|   N*2 objects   |
-             * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             */
-            16..=65534 => {
-                buffer.write_all(&[Map::MAP_16_TYPE])?;
-                buffer.write_all(&map_length.to_be_bytes())?
-            }
+/**
+ * @name write_map_header
+ * @description
+ *
+ * Writes the smallest map header that fits `map_length` key-value pairs.
+ */
+#[inline(always)]
+pub fn write_map_header<U: Write>(map_length: usize, buffer: &mut U) -> Result<()> {
+    match map_length {
+        /*
+         * Fixmap size is 15
+         *
+         * https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=1000xxxx-,0x80%20%2D%200x8f,-fixarray
+         */
+        0..=15 => buffer.write_all(&[Map::FIXMAP + map_length as u8])?,
 
-            /*
-             * map 32 stores a map whose length is upto (2^32)-1 elements
-             * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             * |  0xdf  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|   N*2 objects   |
-             * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             */
-            65535..4294967295 => {
-                buffer.write_all(&[Map::MAP_32_TYPE])?;
-                buffer.write_all(&map_length.to_be_bytes())?
-            }
+        /*
+         * map 16 stores a map whose length is upto (2^16)-1 elements
+         * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xde  |YYYYYYYY|YYYYYYYY|   N*2 objects   |
+         * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         */
+        16..=0xffff => {
+            let length = (map_length as u16).to_be_bytes();
 
-            /*
-             * Msgpack doesn't support map64.
-             *
-             * However you can make an extension if you need it.
-             */
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "MAP64 is not supported in msgpack. Consider making an extension instead."
-                ));
-            }
+            buffer.write_all(&[Map::MAP_16_TYPE, length[0], length[1]])?;
         }
 
+        /*
+         * map 32 stores a map whose length is upto (2^32)-1 elements
+         * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xdf  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|   N*2 objects   |
+         * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         */
+        0x10000..=0xffff_ffff => {
+            let length = (map_length as u32).to_be_bytes();
+
+            buffer.write_all(&[Map::MAP_32_TYPE, length[0], length[1], length[2], length[3]])?;
+        }
+
+        /*
+         * Msgpack doesn't support map64.
+         *
+         * However you can make an extension if you need it.
+         */
+        _ => {
+            return Err(Error::Message(
+                "MAP64 is not supported in msgpack. Consider making an extension instead.",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl WriteTo for Box<[(Value<'_>, Value<'_>)]> {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, buffer: &mut U) -> Result<()> {
+        write_map_header(self.len(), buffer)?;
+
         for (key, value) in self {
             key.write_to(buffer)?;
             value.write_to(buffer)?;
@@ -72,120 +87,142 @@ impl WriteTo for Box<[(Value<'_>, Value<'_>)]> {
     }
 }
 
-impl<'a> ReadFrom<'a> for Box<[(Value<'a>, Value<'a>)]> {
-    #[inline(always)]
-    fn read_from<U: AsRef<[u8]> + 'a>(packet_type: u8, reader: &'a mut Reader<U>) -> Result<Self> {
-        let map_length = match packet_type {
-            /*
-             * Fixmap ranges from 0x80 to 0x8f:
-             *
-             * https://github.com/msgpack/msgpack/blob/master/spec.md#map-format-family:~:text=1000xxxx-,0x80%20%2D%200x8f,-fixarray
-             */
-            0x80..0x8f => packet_type as usize - Map::FIXMAP as usize,
-
-            /*
-             * map 16 stores a map whose length is upto (2^16)-1 elements
-             * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             * |  0xde  |YYYYYYYY|YYYYYYYY|   N*2 objects   |
-             * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             */
-            Map::MAP_16_TYPE => {
-                let buffer = reader.pull(2);
-
-                if buffer.len() != 2 {
-                    return Err(anyhow::anyhow!(
-                        "I have no idea how to read a MAP16 from this!"
-                    ));
-                }
-
-                u16::from_be_bytes([buffer[0], buffer[1]]) as usize
-            }
+/**
+ * @name read_map_len
+ * @description
+ *
+ * Reads the key-value pair count of a map whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_map_len(packet_type: u8, reader: &mut Reader<'_>) -> Result<usize> {
+    let map_length = match packet_type {
+        /*
+         * Fixmap ranges from 0x80 to 0x8f:
+         *
+         * https://github.com/msgpack/msgpack/blob/master/spec.md#map-format-family:~:text=1000xxxx-,0x80%20%2D%200x8f,-fixarray
+         */
+        0x80..=0x8f => packet_type as usize - Map::FIXMAP as usize,
 
-            /*
-             * map 32 stores a map whose length is upto (2^32)-1 elements
-             * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             * |  0xdf  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|   N*2 objects   |
-             * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
-             */
-            Map::MAP_32_TYPE => {
-                let buffer = reader.pull(4);
-
-                if buffer.len() != 4 {
-                    return Err(anyhow::anyhow!(
-                        "I have no idea how to read a MAP32 from this!"
-                    ));
-                }
-
-                u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize
-            }
+        /*
+         * map 16 stores a map whose length is upto (2^16)-1 elements
+         * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xde  |YYYYYYYY|YYYYYYYY|   N*2 objects   |
+         * +--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         */
+        Map::MAP_16_TYPE => {
+            let buffer = reader.pull(2);
 
-            /*
-             * Do not read.
-             */
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Failed to read MAP64. The serializer knows something I don't possess."
-                ));
+            if buffer.len() != 2 {
+                return Err(Error::UnexpectedEof("MAP16"));
             }
-        };
 
-        /*
-         * Prevent people from allocating 4GB
-         */
-        if map_length > 100usize {
-            return Err(anyhow::anyhow!(
-                "A map size of 100??? This is VERY unrealistic for moomoo.io"
-            ));
+            u16::from_be_bytes([buffer[0], buffer[1]]) as usize
         }
 
         /*
-         * Note: DO NOT USE with_capacity!
-         *
-         * Preemptive allocations slow down everything **4 times**
+         * map 32 stores a map whose length is upto (2^32)-1 elements
+         * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
+         * |  0xdf  |ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|ZZZZZZZZ|   N*2 objects   |
+         * +--------+--------+--------+--------+--------+~~~~~~~~~~~~~~~~~+
          */
+        Map::MAP_32_TYPE => {
+            let buffer = reader.pull(4);
 
-        let mut uninit = Box::<[(Value, Value)]>::new_uninit_slice(map_length);
-        let ptr = uninit.as_mut_ptr();
-
-        let reader_ptr0 = reader as *mut Reader<U>;
+            if buffer.len() != 4 {
+                return Err(Error::UnexpectedEof("MAP32"));
+            }
 
-        unsafe {
-            let mut i = 0;
+            u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize
+        }
 
-            while i + 4 < map_length {
-                ptr.add(i).write(MaybeUninit::new((
-                    (&mut *reader_ptr0).pull_value()?,
-                    (&mut *reader_ptr0).pull_value()?,
-                )));
+        /*
+         * Do not read.
+         */
+        _ => return Err(Error::UnexpectedMarker(packet_type)),
+    };
 
-                ptr.add(i + 1).write(MaybeUninit::new((
-                    (&mut *reader_ptr0).pull_value()?,
-                    (&mut *reader_ptr0).pull_value()?,
-                )));
+    Ok(map_length)
+}
 
-                ptr.add(i + 2).write(MaybeUninit::new((
-                    (&mut *reader_ptr0).pull_value()?,
-                    (&mut *reader_ptr0).pull_value()?,
-                )));
+/**
+ * @name read_map
+ * @description
+ *
+ * Reads the key-value pairs of a map whose marker was already pulled.
+ */
+#[inline(always)]
+#[allow(clippy::type_complexity)]
+pub fn read_map<'buf>(
+    packet_type: u8,
+    reader: &mut Reader<'buf>,
+) -> Result<Box<[(Value<'buf>, Value<'buf>)]>> {
+    /*
+     * Prevent people from allocating 4GB
+     */
+    let map_length = read_map_len(packet_type, reader)?;
+    let map_length = reader.container_len(reader.limits().check_map(map_length)?)?;
+
+    reader.nested(|reader| read_entries(map_length, reader))
+}
 
-                ptr.add(i + 3).write(MaybeUninit::new((
-                    (&mut *reader_ptr0).pull_value()?,
-                    (&mut *reader_ptr0).pull_value()?,
-                )));
+#[inline]
+#[allow(clippy::type_complexity)]
+fn read_entries<'buf>(
+    map_length: usize,
+    reader: &mut Reader<'buf>,
+) -> Result<Box<[(Value<'buf>, Value<'buf>)]>> {
+    /*
+     * Note: DO NOT USE with_capacity!
+     *
+     * Preemptive allocations slow down everything **4 times**
+     */
+
+    let mut uninit = Box::<[(Value, Value)]>::new_uninit_slice(map_length);
+    let ptr = uninit.as_mut_ptr();
+
+    unsafe {
+        let mut i = 0;
+
+        while i + 4 < map_length {
+            ptr.add(i).write(MaybeUninit::new((
+                reader.pull_value()?,
+                reader.pull_value()?,
+            )));
+
+            ptr.add(i + 1).write(MaybeUninit::new((
+                reader.pull_value()?,
+                reader.pull_value()?,
+            )));
+
+            ptr.add(i + 2).write(MaybeUninit::new((
+                reader.pull_value()?,
+                reader.pull_value()?,
+            )));
+
+            ptr.add(i + 3).write(MaybeUninit::new((
+                reader.pull_value()?,
+                reader.pull_value()?,
+            )));
+
+            i += 4;
+        }
 
-                i += 4;
-            }
+        while i < map_length {
+            ptr.add(i).write(MaybeUninit::new((
+                reader.pull_value()?,
+                reader.pull_value()?,
+            )));
 
-            while i < map_length {
-                ptr.add(i).write(MaybeUninit::new((
-                    (&mut *reader_ptr0).pull_value()?,
-                    (&mut *reader_ptr0).pull_value()?,
-                )));
+            i += 1;
+        }
 
-                i += 1;
-            }
+        Ok(uninit.assume_init())
+    }
+}
 
-            Ok(uninit.assume_init())
-        }
+impl<'a> ReadFrom<'a> for Box<[(Value<'a>, Value<'a>)]> {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        read_map(reader.pull_marker()?, reader)
     }
 }
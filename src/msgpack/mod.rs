@@ -1,17 +1,22 @@
-use anyhow::Result;
-use std::io::Write;
+use alloc::vec::Vec;
+
+use crate::error::Result;
+use crate::io::Write;
 
 use crate::reader::Reader;
 
 pub mod array;
 pub mod bin;
 pub mod boolean;
+pub mod collections;
 pub mod ext;
 pub mod float;
 pub mod integer;
 pub mod map;
 pub mod null;
+pub mod pointer;
 pub mod string;
+pub mod tuple;
 
 /**
  * @name WriteTo
@@ -21,6 +26,32 @@ pub mod string;
  */
 pub trait WriteTo {
     fn write_to<U: Write>(&self, writer: &mut U) -> Result<()>;
+
+    /*
+     * How a Vec<Self> is written: an array, except for u8 whose Vecs
+     * are bin
+     */
+    #[doc(hidden)]
+    #[inline(always)]
+    fn write_vec<U: Write>(values: &[Self], writer: &mut U) -> Result<()>
+    where
+        Self: Sized,
+    {
+        collections::write_seq(values.len(), values.iter(), writer)
+    }
+
+    /*
+     * How a [Self; N] is written: an array, except for u8 whose arrays
+     * are bin like its Vecs
+     */
+    #[doc(hidden)]
+    #[inline(always)]
+    fn write_array<U: Write, const N: usize>(values: &[Self; N], writer: &mut U) -> Result<()>
+    where
+        Self: Sized,
+    {
+        collections::write_seq(N, values.iter(), writer)
+    }
 }
 
 /**
@@ -29,18 +60,41 @@ pub trait WriteTo {
  *
  * Reader trait for msgpack values.
  *
+ * Read_from pulls the marker itself and fails with `UnexpectedMarker`
+ * when it isn't of the expected family. Integers are read from any
+ * width whose value fits.
+ *
+ * `'buf` is the lifetime of the reader's input, so `&'buf str` and
+ * friends borrow the buffer rather than the reader.
+ *
  * Read_from MUST be somewhat close to being zero-copy.
  */
-pub trait ReadFrom<'a> {
-    fn read_from<T: AsRef<[u8]> + 'a>(packet_type: u8, reader: &'a mut Reader<T>) -> Result<Self>
-    where
-        Self: 'a + Sized;
+pub trait ReadFrom<'buf>: Sized {
+    fn read_from(reader: &mut Reader<'buf>) -> Result<Self>;
+
+    /*
+     * Counterparts of `WriteTo::write_vec` and `WriteTo::write_array`
+     */
+    #[doc(hidden)]
+    #[inline(always)]
+    fn read_vec(reader: &mut Reader<'buf>) -> Result<Vec<Self>> {
+        collections::read_seq(reader)
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn read_array<const N: usize>(reader: &mut Reader<'buf>) -> Result<[Self; N]> {
+        collections::read_array(reader)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
     use crate::value::Value;
+    use alloc::collections::BTreeMap;
+    use alloc::{vec, vec::Vec};
     use ordered_float::OrderedFloat;
 
     #[test]
@@ -51,6 +105,54 @@ mod tests {
         assert_eq!(buffer, &[0x2a]);
     }
 
+    #[test]
+    fn test_negative_fixint() {
+        let mut buffer = Vec::new();
+
+        Value::I8(-1).write_to(&mut buffer).unwrap();
+        Value::I8(-32).write_to(&mut buffer).unwrap();
+
+        assert_eq!(buffer, &[0xff, 0xe0]);
+
+        let mut reader = Reader::new(&buffer);
+
+        assert_eq!(reader.pull_value().unwrap(), Value::I8(-1));
+        assert_eq!(reader.pull_value().unwrap(), Value::I8(-32));
+    }
+
+    #[test]
+    fn test_slice_sink_overflow() {
+        let mut buffer = [0u8; 2];
+        let mut slice = &mut buffer[..];
+
+        assert!(Value::Str("hai").write_to(&mut slice).is_err());
+    }
+
+    #[test]
+    fn test_bin_and_ext() {
+        let mut buffer = Vec::new();
+
+        let packet = vec![
+            Value::bin(&[1, 2, 3]),
+            Value::extension(ext::Extension::new(7, vec![0xaa, 0xbb])),
+            Value::extension(ext::Extension::new(9, vec![0; 3])),
+        ]
+        .into_boxed_slice();
+
+        packet.write_to(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer,
+            &[
+                0x93, 0xc4, 0x03, 1, 2, 3, 0xd5, 7, 0xaa, 0xbb, 0xc7, 0x03, 9, 0, 0, 0
+            ]
+        );
+
+        let mut reader = Reader::new(&buffer);
+
+        assert_eq!(reader.pull_value().unwrap(), Value::Array(packet));
+    }
+
     #[test]
     fn test_arrays() {
         let mut buffer = Vec::new();
@@ -158,4 +260,387 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_std_types() {
+        use alloc::{borrow::Cow, boxed::Box, rc::Rc, string::String};
+
+        let mut buffer = Vec::new();
+
+        (
+            Some(1u8),
+            None::<u8>,
+            'é',
+            &[1u8, 2][..],
+            (String::from("ch"), (1.5f64, -2i8)),
+        )
+            .write_to(&mut buffer)
+            .unwrap();
+
+        assert_eq!(
+            buffer,
+            &[
+                0x95, 0x01, 0xc0, 0xa2, 0xc3, 0xa9, 0xc4, 0x02, 1, 2, 0x92, 0xa2, b'c', b'h', 0x92,
+                0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xfe
+            ]
+        );
+
+        let mut reader = Reader::new(&buffer[..]);
+
+        type Packet<'a> = (
+            Option<u8>,
+            Option<u8>,
+            char,
+            Cow<'a, [u8]>,
+            (Rc<String>, Box<(f64, i8)>),
+        );
+
+        assert_eq!(
+            reader.read::<Packet>().unwrap(),
+            (
+                Some(1),
+                None,
+                'é',
+                Cow::Borrowed(&[1u8, 2][..]),
+                (Rc::new(String::from("ch")), Box::new((1.5, -2)))
+            )
+        );
+    }
+
+    #[test]
+    fn test_typed_read() {
+        let buffer = [
+            0x05, 0xcd, 0x00, 0x2a, 0xd1, 0xff, 0x80, 0xcc, 0xff, 0xca, 0x3f, 0xc0, 0x00, 0x00,
+        ];
+        let mut reader = Reader::new(&buffer[..]);
+
+        /*
+         * Any width works as long as the value fits
+         */
+        assert_eq!(reader.read::<u64>().unwrap(), 5);
+        assert_eq!(reader.read::<u8>().unwrap(), 42);
+        assert_eq!(reader.read::<i32>().unwrap(), -128);
+        assert_eq!(reader.read::<i16>().unwrap(), 255);
+        assert_eq!(reader.read::<f64>().unwrap(), 1.5);
+
+        /*
+         * Too big, wrong sign, wrong family and lossy narrowing
+         */
+        assert!(Reader::new(&[0xcd, 0x01, 0x2c]).read::<u8>().is_err());
+        assert!(Reader::new(&[0xff]).read::<u32>().is_err());
+        assert!(Reader::new(&[0xa1, b'x']).read::<u8>().is_err());
+        assert!(
+            Reader::new(&[0xcb, 0, 0, 0, 0, 0, 0, 0, 0])
+                .read::<f32>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_consecutive_values() {
+        let buffer = [0xa2, b'c', b'h', 0x92, 0x01, 0xa1, b'x', 0xc3];
+        let mut reader = Reader::new(&buffer);
+
+        /*
+         * Values borrow the buffer, not the reader, so all of them can be kept
+         */
+        let code = reader.pull_value().unwrap();
+        let args = reader.pull_value().unwrap();
+        let flag: bool = reader.read().unwrap();
+
+        assert_eq!(code, Value::Str("ch"));
+        assert_eq!(
+            args,
+            Value::Array(vec![Value::U8(1), Value::Str("x")].into_boxed_slice())
+        );
+        assert!(flag);
+    }
+
+    #[test]
+    fn test_values() {
+        let buffer = [0x01, 0xa2, b'c', b'h', 0xc3, 0xc0];
+        let mut reader = Reader::new(&buffer);
+
+        assert_eq!(reader.remaining(), 6);
+        assert_eq!(reader.read::<u8>().unwrap(), 1);
+        assert_eq!(reader.remaining(), 5);
+
+        let values = reader.values().collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(
+            values,
+            vec![Value::Str("ch"), Value::Bool(true), Value::Nil]
+        );
+        assert!(reader.is_empty());
+
+        /*
+         * Stops at the first broken value
+         */
+        let mut reader = Reader::new(&[0x01, 0xcd, 0x00]);
+        let mut values = reader.values();
+
+        assert!(values.next().unwrap().is_ok());
+        assert!(values.next().unwrap().is_err());
+        assert!(values.next().is_none());
+    }
+
+    #[test]
+    fn test_decode_exact() {
+        use crate::reader::decode_exact;
+
+        assert_eq!(
+            decode_exact::<(&str, u16)>(&[0x92, 0xa1, b'x', 0x05]).unwrap(),
+            ("x", 5)
+        );
+        assert!(decode_exact::<Value>(&[0x92, 0xa1, b'x', 0x05, 0x00]).is_err());
+        assert!(decode_exact::<Value>(&[0xa5, b'h']).is_err());
+        assert!(decode_exact::<Value>(&[]).is_err());
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut buffer = Vec::new();
+
+        (
+            "ch",
+            vec![(1u8, "one"), (2u8, "two")],
+            [1.5f64, 2.5],
+            Some(7u32),
+        )
+            .write_to(&mut buffer)
+            .unwrap();
+        "next".write_to(&mut buffer).unwrap();
+
+        let mut reader = Reader::new(&buffer);
+
+        reader.skip_value().unwrap();
+
+        assert_eq!(reader.read::<&str>().unwrap(), "next");
+        assert!(reader.is_empty());
+        assert!(matches!(
+            reader.skip_value(),
+            Err(Error::UnexpectedEof("value"))
+        ));
+
+        /*
+         * Only the first element of a packet
+         */
+        let mut reader = Reader::new(&buffer);
+
+        let marker = reader.pull_marker().unwrap();
+
+        assert_eq!(array::read_array_len(marker, &mut reader).unwrap(), 4);
+        assert_eq!(reader.read::<&str>().unwrap(), "ch");
+
+        for _ in 0..3 {
+            reader.skip_value().unwrap();
+        }
+
+        assert_eq!(reader.read::<&str>().unwrap(), "next");
+
+        /*
+         * A value cut short is left where it was
+         */
+        let mut reader = Reader::new(&buffer[..buffer.len() - 6]);
+
+        assert!(reader.skip_value().is_err());
+        assert_eq!(reader.remaining(), buffer.len() - 6);
+    }
+
+    #[test]
+    fn test_huge_lengths() {
+        use crate::reader::Limits;
+
+        /*
+         * Refused by the default limits, and without them by the bytes
+         * actually left, before anything is allocated
+         */
+        let array = [0xdd, 0xff, 0xff, 0xff, 0xff];
+        let map = [0xdf, 0xff, 0xff, 0xff, 0xff, 0x01];
+
+        assert!(matches!(
+            Reader::new(&array).pull_value(),
+            Err(Error::LimitExceeded("Array"))
+        ));
+
+        for buffer in [&array[..], &map[..]] {
+            assert!(matches!(
+                Reader::with_limits(buffer, Limits::UNLIMITED).pull_value(),
+                Err(Error::UnexpectedEof("container"))
+            ));
+        }
+
+        assert_eq!(
+            Reader::with_limits(&[0x92, 0x01, 0x02], Limits::UNLIMITED)
+                .pull_value()
+                .unwrap(),
+            Value::Array(vec![Value::U8(1), Value::U8(2)].into())
+        );
+    }
+
+    #[test]
+    fn test_depth() {
+        use crate::reader::Limits;
+
+        fn nested(depth: usize, open: u8) -> Vec<u8> {
+            let mut buffer = vec![open; depth];
+
+            buffer.push(0xc0);
+            buffer
+        }
+
+        /*
+         * Arrays and maps count alike, and the reader is usable again
+         * after a failed pull
+         */
+        assert!(Reader::new(&nested(64, 0x91)).pull_value().is_ok());
+
+        for open in [0x91, 0x81] {
+            let buffer = nested(1_000_000, open);
+
+            assert!(matches!(
+                Reader::new(&buffer).pull_value(),
+                Err(Error::LimitExceeded("Depth"))
+            ));
+        }
+
+        let limits = Limits {
+            max_depth: 2,
+            ..Limits::DEFAULT
+        };
+        let buffer = [0x92, 0x91, 0x01, 0x91, 0x91, 0x02, 0x91, 0x03];
+        let mut reader = Reader::with_limits(&buffer, limits);
+
+        assert!(reader.pull_value().is_err());
+
+        reader.set_position(1).unwrap();
+
+        assert_eq!(
+            reader.values().collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![
+                Value::Array(vec![Value::U8(1)].into()),
+                Value::Array(vec![Value::Array(vec![Value::U8(2)].into())].into()),
+                Value::Array(vec![Value::U8(3)].into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_peek_and_rewind() {
+        use crate::reader::Kind;
+
+        let mut buffer = Vec::new();
+
+        (
+            -3i8,
+            2.5f32,
+            "x",
+            &b"y"[..],
+            [(1u8, true)].into_iter().collect::<BTreeMap<_, _>>(),
+        )
+            .write_to(&mut buffer)
+            .unwrap();
+
+        let mut reader = Reader::new(&buffer);
+
+        assert_eq!(reader.peek_marker().unwrap(), 0x95);
+        assert_eq!(reader.peek_kind().unwrap(), Kind::Array);
+        assert_eq!(reader.position(), 0);
+
+        reader.set_position(1).unwrap();
+
+        let kinds = [Kind::Int, Kind::Float, Kind::Str, Kind::Bin, Kind::Map];
+
+        for kind in kinds {
+            assert_eq!(reader.peek_kind().unwrap(), kind);
+            reader.skip_value().unwrap();
+        }
+
+        assert_eq!(reader.position(), buffer.len());
+        assert!(matches!(
+            reader.peek_kind(),
+            Err(Error::UnexpectedEof("marker"))
+        ));
+
+        assert!(reader.set_position(buffer.len()).is_ok());
+        assert!(reader.set_position(buffer.len() + 1).is_err());
+        assert_eq!(reader.position(), buffer.len());
+
+        /*
+         * A failed attempt leaves the reader where it was
+         */
+        reader.set_position(1).unwrap();
+
+        {
+            let mut attempt = reader.checkpoint();
+
+            assert_eq!(attempt.read::<i8>().unwrap(), -3);
+            assert!(attempt.read::<u64>().is_err());
+        }
+
+        assert_eq!(reader.position(), 1);
+
+        let mut attempt = reader.checkpoint();
+
+        assert!(attempt.read::<u8>().is_err());
+
+        attempt.rewind();
+
+        assert_eq!(attempt.position(), attempt.start());
+        assert_eq!(attempt.read::<i64>().unwrap(), -3);
+
+        attempt.commit();
+
+        assert_eq!(reader.read::<f32>().unwrap(), 2.5);
+    }
+
+    #[test]
+    fn test_validate() {
+        use crate::reader::{Limits, validate, validate_with};
+
+        let mut buffer = Vec::new();
+
+        vec![(0u8, "x"); 3].write_to(&mut buffer).unwrap();
+
+        assert_eq!(validate(&buffer).unwrap(), buffer.len());
+        assert!(validate(&buffer[..buffer.len() - 1]).is_err());
+        assert!(validate(&[]).is_err());
+
+        buffer.push(0xc0);
+
+        assert_eq!(validate(&buffer).unwrap(), buffer.len() - 1);
+
+        /*
+         * Maps keep their historical cap of 100 pairs
+         */
+        let map = (0..101u8).map(|key| (key, 0u8)).collect::<BTreeMap<_, _>>();
+        let mut encoded = Vec::new();
+
+        map.write_to(&mut encoded).unwrap();
+
+        assert!(matches!(
+            validate(&encoded),
+            Err(Error::LimitExceeded("Map"))
+        ));
+        assert!(matches!(
+            Reader::new(&encoded).pull_value(),
+            Err(Error::LimitExceeded("Map"))
+        ));
+
+        let limits = Limits {
+            max_array_len: 2,
+            max_map_len: 200,
+            ..Limits::DEFAULT
+        };
+
+        assert_eq!(validate_with(&encoded, &limits).unwrap(), encoded.len());
+        assert!(matches!(
+            validate_with(&buffer, &limits),
+            Err(Error::LimitExceeded("Array"))
+        ));
+        assert!(matches!(
+            Reader::with_limits(&buffer, limits).skip_value(),
+            Err(Error::LimitExceeded("Array"))
+        ));
+    }
 }
@@ -1,38 +1,43 @@
 use crate::constants::Families;
+use crate::error::Result;
+use crate::io::Write;
 use crate::msgpack::{ReadFrom, WriteTo};
 use crate::reader::Reader;
-use anyhow::Result;
-use std::io::Write;
 
-impl<O> WriteTo for Option<O> {
+impl<O: WriteTo> WriteTo for Option<O> {
+    /**
+     * @name write_to
+     * @description
+     *
+     * None is nil, Some is the inner value.
+     */
     #[inline(always)]
     fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        match *self {
-            /*
-             * Null
-             */
+        match self {
             None => writer.write_all(&[Families::NIL])?,
-
-            /*
-             * Reserved
-             */
-            Some(_) => writer.write_all(&[Families::NIL + 1])?,
+            Some(value) => value.write_to(writer)?,
         }
 
         Ok(())
     }
 }
 
-impl<'a> ReadFrom<'a> for Option<()> {
+impl<'a, O: ReadFrom<'a>> ReadFrom<'a> for Option<O> {
+    /**
+     * @name read_from
+     * @description
+     *
+     * Nil is None, anything else is read as the inner value. So an
+     * `Option<Option<O>>` can't tell `Some(None)` from `None`.
+     */
     #[inline(always)]
-    fn read_from<T: AsRef<[u8]> + 'a>(packet_type: u8, _reader: &'a mut Reader<T>) -> Result<Self> {
-        match packet_type {
-            Families::NIL => Ok(None),
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        if reader.peek() == Some(Families::NIL) {
+            reader.pull(1);
 
-            /*
-             * Reserved
-             */
-            _ => Ok(Some(())),
+            return Ok(None);
         }
+
+        O::read_from(reader).map(Some)
     }
 }
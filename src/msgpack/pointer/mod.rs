@@ -0,0 +1,59 @@
+use alloc::borrow::{Cow, ToOwned};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+
+use crate::error::Result;
+use crate::io::Write;
+use crate::msgpack::{ReadFrom, WriteTo};
+use crate::reader::Reader;
+
+/*
+ * Smart pointers are written as what they point to.
+ */
+macro_rules! pointer {
+    ($pointer:ident) => {
+        impl<V: WriteTo + ?Sized> WriteTo for $pointer<V> {
+            #[inline(always)]
+            fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+                (**self).write_to(writer)
+            }
+        }
+
+        impl<'a, V: ReadFrom<'a>> ReadFrom<'a> for $pointer<V> {
+            #[inline(always)]
+            fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+                V::read_from(reader).map($pointer::new)
+            }
+        }
+    };
+}
+
+pointer!(Box);
+pointer!(Rc);
+#[cfg(target_has_atomic = "ptr")]
+pointer!(Arc);
+
+impl<B: ToOwned + WriteTo + ?Sized> WriteTo for Cow<'_, B> {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        (**self).write_to(writer)
+    }
+}
+
+impl<'a, B: ToOwned + ?Sized + 'a> ReadFrom<'a> for Cow<'a, B>
+where
+    &'a B: ReadFrom<'a>,
+{
+    /**
+     * @name read_from
+     * @description
+     *
+     * Always borrows the reader's buffer, for `Cow<str>` and `Cow<[u8]>`.
+     */
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        <&'a B>::read_from(reader).map(Cow::Borrowed)
+    }
+}
@@ -1,70 +1,201 @@
+use alloc::string::String;
+
 use crate::constants::Families;
-use crate::msgpack::WriteTo;
-use anyhow::Result;
-use std::io::Write;
+use crate::error::{Error, Result};
+use crate::io::Write;
+use crate::msgpack::{ReadFrom, WriteTo};
+use crate::reader::Reader;
+
+/**
+ * @name write_str_header
+ * @description
+ *
+ * Writes the smallest str header that fits `len` bytes of UTF-8.
+ */
+#[inline(always)]
+pub fn write_str_header<U: Write>(len: usize, writer: &mut U) -> Result<()> {
+    if len <= 31 {
+        /*
+         * Fixstr ranges from 0xa0 to 0xbf (https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=101xxxxx-,0xa0%20%2D%200xbf,-nil)
+         *
+         * The distance between is obviously 31, therefore we would never overflow
+         * and write an incorrect byte.
+         */
+
+        writer.write_all(&[Families::FIXSTR + len as u8])?;
+    } else if len <= 255 {
+        /*
+         * str 8 stores a byte array whose length is upto (2^8)-1 bytes:
+         *
+         * +--------+--------+========+
+         * |  0xd9  |YYYYYYYY|  data  |
+         * +--------+--------+========+
+         *
+         * https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=8)%2D1%20bytes%3A%0A%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%3D%3D%3D%3D%3D%3D%3D%3D%2B%0A%7C-,0xd9,-%7CYYYYYYYY%7C%20%20data%20%20%7C%0A%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%3D%3D%3D%3D%3D%3D%3D%3D%2B%0A%0Astr
+         */
+
+        writer.write_all(&[Families::STR8, len as u8])?;
+    } else if len <= 65535 {
+        /*
+         * str 16 stores a byte array whose length is upto (2^16)-1 bytes:
+         * +--------+--------+--------+========+
+         * |  0xda  |ZZZZZZZZ|ZZZZZZZZ|  data  |
+         * +--------+--------+--------+========+
+         */
+
+        let bytes = (len as u16).to_be_bytes();
+
+        writer.write_all(&[Families::STR16, bytes[0], bytes[1]])?;
+    } else if len <= 0xffff_ffff {
+        /*
+         * str 32 stores a byte array whose length is upto (2^32)-1 bytes:
+         * +--------+--------+--------+--------+--------+========+
+         * |  0xdb  |AAAAAAAA|AAAAAAAA|AAAAAAAA|AAAAAAAA|  data  |
+         * +--------+--------+--------+--------+--------+========+
+         */
+
+        let bytes = (len as u32).to_be_bytes();
+
+        writer.write_all(&[Families::STR32, bytes[0], bytes[1], bytes[2], bytes[3]])?;
+    } else {
+        /*
+         * Msgpack format doesn't support 64 bit length strings.
+         *
+         * However, you can implement an extension for that.
+         */
+
+        return Err(Error::LengthOverflow("String"));
+    }
+
+    Ok(())
+}
 
 impl WriteTo for String {
     #[inline(always)]
     fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
-        let len = self.len();
-
-        if len <= 31 {
-            /*
-             * Fixstr ranges from 0xa0 to 0xbf (https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=101xxxxx-,0xa0%20%2D%200xbf,-nil)
-             *
-             * The distance between is obviously 31, therefore we would never overflow
-             * and write an incorrect byte.
-             */
-
-            writer.write_all(&[Families::FIXSTR + len as u8])?;
-        } else if len <= 255 {
-            /*
-             * str 8 stores a byte array whose length is upto (2^8)-1 bytes:
-             *
-             * +--------+--------+========+
-             * |  0xd9  |YYYYYYYY|  data  |
-             * +--------+--------+========+
-             *
-             * https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=8)%2D1%20bytes%3A%0A%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%3D%3D%3D%3D%3D%3D%3D%3D%2B%0A%7C-,0xd9,-%7CYYYYYYYY%7C%20%20data%20%20%7C%0A%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%2D%2D%2D%2D%2D%2D%2D%2D%2B%3D%3D%3D%3D%3D%3D%3D%3D%2B%0A%0Astr
-             */
-
-            writer.write_all(&[Families::STR8, len as u8])?;
-        } else if len <= 65535 {
-            /*
-             * str 16 stores a byte array whose length is upto (2^16)-1 bytes:
-             * +--------+--------+--------+========+
-             * |  0xda  |ZZZZZZZZ|ZZZZZZZZ|  data  |
-             * +--------+--------+--------+========+
-             */
-
-            let bytes = (len as u16).to_be_bytes();
-
-            writer.write_all(&[Families::STR16, bytes[0], bytes[1]])?;
-        } else if len <= 2147483648 {
-            /*
-             * str 32 stores a byte array whose length is upto (2^32)-1 bytes:
-             * +--------+--------+--------+--------+--------+========+
-             * |  0xdb  |AAAAAAAA|AAAAAAAA|AAAAAAAA|AAAAAAAA|  data  |
-             * +--------+--------+--------+--------+--------+========+
-             */
-
-            let bytes = (len as u32).to_be_bytes();
-
-            writer.write_all(&[Families::STR32, bytes[0], bytes[1], bytes[2], bytes[3]])?;
-        } else {
-            /*
-             * Msgpack format doesn't support 64 bit length strings.
-             *
-             * However, you can implement an extension for that.
-             */
-
-            return Err(anyhow::anyhow!(
-                "Error: Overflow while writing a string. Expected maximum string length is 2^31."
-            ));
-        }
+        self.as_str().write_to(writer)
+    }
+}
+
+impl WriteTo for str {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        write_str_header(self.len(), writer)?;
 
         writer.write_all(self.as_bytes())?;
 
         Ok(())
     }
 }
+
+impl<W: WriteTo + ?Sized> WriteTo for &W {
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        (**self).write_to(writer)
+    }
+}
+
+impl<'a> ReadFrom<'a> for &'a str {
+    /**
+     * @name read_from
+     * @description
+     *
+     * Borrows the string from the reader's buffer.
+     *
+     * Unlike `Reader::pull_string`, truncated or invalid UTF-8 strings are errors.
+     */
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        let len = read_str_len(reader.pull_marker()?, reader)?;
+        let bytes = reader.pull(len);
+
+        if bytes.len() != len {
+            return Err(Error::UnexpectedEof("String"));
+        }
+
+        core::str::from_utf8(bytes).map_err(|_| Error::Message("String is not valid UTF-8"))
+    }
+}
+
+impl<'a> ReadFrom<'a> for String {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        <&str>::read_from(reader).map(String::from)
+    }
+}
+
+impl WriteTo for char {
+    /**
+     * @name write_to
+     * @description
+     *
+     * Writes the char as a string of its UTF-8 encoding.
+     */
+    #[inline(always)]
+    fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+        let mut bytes = [0u8; 4];
+
+        (*self.encode_utf8(&mut bytes)).write_to(writer)
+    }
+}
+
+impl<'a> ReadFrom<'a> for char {
+    /**
+     * @name read_from
+     * @description
+     *
+     * Reads a string holding exactly one char.
+     */
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+        let mut chars = <&str>::read_from(reader)?.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(char), None) => Ok(char),
+            _ => Err(Error::Message("Expected a single char")),
+        }
+    }
+}
+
+/**
+ * @name read_str_len
+ * @description
+ *
+ * Reads the byte count of a str whose marker was already pulled.
+ */
+#[inline(always)]
+pub fn read_str_len(strtype: u8, reader: &mut Reader<'_>) -> Result<usize> {
+    let len = match strtype {
+        0xa0..=0xbf => (strtype - Families::FIXSTR) as usize,
+        Families::STR8 => {
+            let bytes = reader.pull(1);
+
+            if bytes.len() != 1 {
+                return Err(Error::UnexpectedEof("STR8"));
+            }
+
+            bytes[0] as usize
+        }
+        Families::STR16 => {
+            let bytes = reader.pull(2);
+
+            if bytes.len() != 2 {
+                return Err(Error::UnexpectedEof("STR16"));
+            }
+
+            u16::from_be_bytes([bytes[0], bytes[1]]) as usize
+        }
+        Families::STR32 => {
+            let bytes = reader.pull(4);
+
+            if bytes.len() != 4 {
+                return Err(Error::UnexpectedEof("STR32"));
+            }
+
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+        }
+        _ => return Err(Error::UnexpectedMarker(strtype)),
+    };
+
+    Ok(len)
+}
@@ -0,0 +1,52 @@
+use crate::error::{Error, Result};
+use crate::io::Write;
+use crate::msgpack::{ReadFrom, WriteTo, array::read_array_len, array::write_array_header};
+use crate::reader::Reader;
+
+/*
+ * Tuples are arrays of their elements, like moomoo's [x, y] positions.
+ */
+macro_rules! tuple {
+    ($len:literal => $($name:ident $index:tt),+) => {
+        impl<$($name: WriteTo),+> WriteTo for ($($name,)+) {
+            #[inline(always)]
+            fn write_to<U: Write>(&self, writer: &mut U) -> Result<()> {
+                write_array_header($len, writer)?;
+
+                $(self.$index.write_to(writer)?;)+
+
+                Ok(())
+            }
+        }
+
+        impl<'a, $($name: ReadFrom<'a>),+> ReadFrom<'a> for ($($name,)+) {
+            /**
+             * @name read_from
+             * @description
+             *
+             * Reads an array with exactly as many elements as the tuple.
+             */
+            #[inline(always)]
+            fn read_from(reader: &mut Reader<'a>) -> Result<Self> {
+                if read_array_len(reader.pull_marker()?, reader)? != $len {
+                    return Err(Error::Message("Tuple length doesn't match"));
+                }
+
+                Ok(($($name::read_from(reader)?,)+))
+            }
+        }
+    };
+}
+
+tuple!(1 => A 0);
+tuple!(2 => A 0, B 1);
+tuple!(3 => A 0, B 1, C 2);
+tuple!(4 => A 0, B 1, C 2, D 3);
+tuple!(5 => A 0, B 1, C 2, D 3, E 4);
+tuple!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+tuple!(7 => A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple!(8 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple!(9 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple!(10 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple!(11 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple!(12 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
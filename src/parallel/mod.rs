@@ -0,0 +1,245 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use ::rayon::prelude::*;
+
+use crate::{
+    error::Result,
+    msgpack::ReadFrom,
+    reader::{Limits, Reader, decode_exact_with},
+    value::Value,
+};
+
+/**
+ * @name Batch
+ * @description
+ *
+ * Messages stored back to back in one buffer, decoded on every core.
+ *
+ * Boundaries are found first by skipping over each message, which
+ * neither decodes nor allocates, then the messages are decoded in
+ * parallel. Results come back in order and borrow the shared buffer,
+ * like values pulled by a Reader.
+ *
+ * ```
+ * use msgpackp::{msgpack::WriteTo, parallel::Batch};
+ *
+ * let mut capture = Vec::new();
+ *
+ * for tick in 0..1000u16 {
+ *     ("ch", tick).write_to(&mut capture).unwrap();
+ * }
+ *
+ * let batch = Batch::scan(&capture).unwrap();
+ * let packets = batch.decode_as::<(&str, u16)>().unwrap();
+ *
+ * assert_eq!(packets.len(), 1000);
+ * assert_eq!(packets[999], ("ch", 999));
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct Batch<'buf> {
+    bytes: &'buf [u8],
+    limits: Limits,
+    frames: Vec<Range<usize>>,
+}
+
+impl<'buf> Batch<'buf> {
+    #[inline]
+    pub fn scan(bytes: &'buf [u8]) -> Result<Self> {
+        Batch::scan_with(bytes, Limits::DEFAULT)
+    }
+
+    /**
+     * @name scan_with
+     * @description
+     *
+     * Finds where every message of `bytes` starts and ends.
+     *
+     * A buffer that ends inside a message is an `UnexpectedEof` error,
+     * as with `Reader::skip_value`.
+     */
+    pub fn scan_with(bytes: &'buf [u8], limits: Limits) -> Result<Self> {
+        let mut reader = Reader::with_limits(bytes, limits);
+        let mut frames = Vec::new();
+
+        while !reader.is_empty() {
+            let start = reader.position();
+
+            reader.skip_value()?;
+            frames.push(start..reader.position());
+        }
+
+        Ok(Batch {
+            bytes,
+            limits,
+            frames,
+        })
+    }
+
+    /**
+     * @name frames
+     * @description
+     *
+     * Byte range of each message, in order.
+     */
+    #[inline(always)]
+    pub fn frames(&self) -> &[Range<usize>] {
+        &self.frames
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /**
+     * @name decode
+     * @description
+     *
+     * Decodes every message into a Value, in parallel.
+     */
+    #[inline]
+    pub fn decode(&self) -> Result<Vec<Value<'buf>>> {
+        self.decode_as()
+    }
+
+    /**
+     * @name decode_as
+     * @description
+     *
+     * Decodes every message into `T`, in parallel.
+     *
+     * Each message has to be consumed whole, as with `decode_exact`. If
+     * several fail, the error of any one of them is returned.
+     */
+    pub fn decode_as<T>(&self) -> Result<Vec<T>>
+    where
+        T: ReadFrom<'buf> + Send,
+    {
+        let bytes = self.bytes;
+        let limits = self.limits;
+
+        self.frames
+            .par_iter()
+            .map(|frame| decode_exact_with(&bytes[frame.clone()], limits))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::String, vec};
+
+    use crate::{error::Error, msgpack::WriteTo};
+
+    fn capture(count: u32) -> Vec<u8> {
+        let mut buffer = Vec::new();
+
+        for tick in 0..count {
+            (
+                "update",
+                tick,
+                vec![(tick as f64, -(tick as i64)); (tick % 7) as usize],
+                String::from("payload"),
+            )
+                .write_to(&mut buffer)
+                .unwrap();
+        }
+
+        buffer
+    }
+
+    #[test]
+    fn test_matches_sequential() {
+        let buffer = capture(5000);
+        let batch = Batch::scan(&buffer).unwrap();
+
+        assert_eq!(batch.len(), 5000);
+        assert_eq!(batch.frames().last().unwrap().end, buffer.len());
+
+        let sequential = Reader::new(&buffer)
+            .values()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(batch.decode().unwrap(), sequential);
+
+        let typed = batch.decode_as::<(&str, u32, Value, &str)>().unwrap();
+
+        assert!(typed.iter().enumerate().all(|(tick, packet)| {
+            packet.0 == "update" && packet.1 == tick as u32 && packet.3 == "payload"
+        }));
+    }
+
+    #[test]
+    fn test_errors() {
+        let buffer = capture(10);
+
+        assert!(Batch::scan(&[]).unwrap().is_empty());
+        assert!(matches!(
+            Batch::scan(&buffer[..buffer.len() - 1]),
+            Err(Error::UnexpectedEof("value"))
+        ));
+
+        /*
+         * Well-formed, but not every message fits the type
+         */
+        let mut buffer = Vec::new();
+
+        for tick in [1u16, 2, 300, 4] {
+            tick.write_to(&mut buffer).unwrap();
+        }
+
+        let batch = Batch::scan(&buffer).unwrap();
+
+        assert!(batch.decode_as::<u16>().is_ok());
+        assert!(batch.decode_as::<u8>().is_err());
+
+        /*
+         * A type that stops short of the end of its message
+         */
+        let buffer = capture(10);
+        let batch = Batch::scan(&buffer).unwrap();
+
+        assert!(batch.decode_as::<(&str, u32, Value, &str)>().is_ok());
+        assert!(matches!(
+            batch.decode_as::<&str>(),
+            Err(Error::UnexpectedMarker(_))
+        ));
+
+        struct Marker;
+
+        impl ReadFrom<'_> for Marker {
+            fn read_from(reader: &mut Reader<'_>) -> Result<Self> {
+                reader.pull_marker().map(|_| Marker)
+            }
+        }
+
+        assert!(batch.decode_as::<Marker>().is_err());
+        assert!(
+            Batch::scan(&[0x01, 0x02])
+                .unwrap()
+                .decode_as::<Marker>()
+                .is_ok()
+        );
+
+        /*
+         * Deep nesting is an error, not a stack overflow on a worker
+         */
+        let mut buffer = vec![0x91; 1_000_000];
+
+        buffer.push(0xc0);
+
+        assert!(matches!(
+            Batch::scan(&buffer).unwrap().decode(),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+    }
+}
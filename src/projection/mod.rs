@@ -0,0 +1,586 @@
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use crate::{
+    constants::Families,
+    error::{Error, Result},
+    msgpack::{array::read_array_len, map::read_map_len},
+    reader::{Kind, Limits, Reader},
+    value::Value,
+};
+
+/**
+ * @name Segment
+ * @description
+ *
+ * One step of a Path.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /*
+     * `[3]`: one array element
+     */
+    Index(usize),
+
+    /*
+     * `[*]`: every array element
+     */
+    AnyIndex,
+
+    /*
+     * `.name`: the map entry whose key is that string
+     */
+    Key(String),
+
+    /*
+     * `.*`: every map entry with a string key
+     */
+    AnyKey,
+}
+
+/**
+ * @name Path
+ * @description
+ *
+ * Where to find values in a message, e.g. `[1][*].sid`.
+ *
+ * Array elements are picked with `[index]` or `[*]`, map entries with
+ * `.key` or `.*`. The empty path is the whole message. Keys holding `.`
+ * or `[` can only be built from segments.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path {
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    #[inline(always)]
+    pub fn new(segments: Vec<Segment>) -> Self {
+        Path { segments }
+    }
+
+    #[inline(always)]
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = path;
+
+        while let Some(first) = rest.chars().next() {
+            let segment = match first {
+                '[' => {
+                    let end = rest
+                        .find(']')
+                        .ok_or(Error::Message("Unclosed `[` in path"))?;
+                    let index = &rest[1..end];
+
+                    rest = &rest[end + 1..];
+
+                    match index {
+                        "*" => Segment::AnyIndex,
+                        _ => Segment::Index(
+                            index
+                                .parse()
+                                .map_err(|_| Error::Message("Invalid array index in path"))?,
+                        ),
+                    }
+                }
+
+                /*
+                 * The dot is optional at the start: `sid` is `.sid`
+                 */
+                _ => {
+                    let key = rest.strip_prefix('.').unwrap_or(rest);
+                    let end = key.find(['.', '[']).unwrap_or(key.len());
+
+                    if end == 0 {
+                        return Err(Error::Message("Empty key in path"));
+                    }
+
+                    rest = &key[end..];
+
+                    match &key[..end] {
+                        "*" => Segment::AnyKey,
+                        key => Segment::Key(key.into()),
+                    }
+                }
+            };
+
+            segments.push(segment);
+        }
+
+        Ok(Path { segments })
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Index(index) => write!(f, "[{index}]")?,
+                Segment::AnyIndex => write!(f, "[*]")?,
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::AnyKey => write!(f, ".*")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * @name Step
+ * @description
+ *
+ * Where a match was actually found: the index or key taken at each
+ * level, wildcards resolved.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step<'buf> {
+    Index(usize),
+    Key(&'buf str),
+}
+
+/**
+ * @name Match
+ * @description
+ *
+ * A value selected by one of the paths.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'buf> {
+    pub location: Vec<Step<'buf>>,
+    pub value: Value<'buf>,
+}
+
+/**
+ * @name Projector
+ * @description
+ *
+ * A set of paths to pull out of messages.
+ *
+ * Messages are walked without decoding them: only the subtrees at the
+ * end of a path are materialised, within the projector's limits, and
+ * everything else is skipped.
+ *
+ * ```
+ * use msgpackp::{projection::Projector, value::Value};
+ *
+ * /*
+ *  * ["state", [{"sid": 1, "x": 10, "hp": 100}, {"sid": 2, "x": 20, "hp": 90}]]
+ *  */
+ * let packet = [
+ *     0x92, 0xa5, b's', b't', b'a', b't', b'e', 0x92,
+ *     0x83, 0xa3, b's', b'i', b'd', 0x01, 0xa1, b'x', 0x0a, 0xa2, b'h', b'p', 0x64,
+ *     0x83, 0xa3, b's', b'i', b'd', 0x02, 0xa1, b'x', 0x14, 0xa2, b'h', b'p', 0x5a,
+ * ];
+ *
+ * let projector = Projector::new(["[1][*].sid", "[1][*].x"]).unwrap();
+ * let projection = projector.project(&packet).unwrap();
+ *
+ * let xs = projection.get("[1][*].x").iter().map(|x| x.value.clone());
+ *
+ * assert_eq!(xs.collect::<Vec<_>>(), [Value::U8(10), Value::U8(20)]);
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct Projector {
+    sources: Vec<String>,
+    paths: Vec<Path>,
+    limits: Limits,
+}
+
+impl Projector {
+    /**
+     * @name new
+     * @description
+     *
+     * Parses the paths, results are looked up by the same strings.
+     */
+    #[inline]
+    pub fn new<'p, I: IntoIterator<Item = &'p str>>(paths: I) -> Result<Self> {
+        Projector::with_limits(paths, Limits::DEFAULT)
+    }
+
+    /**
+     * @name with_limits
+     * @description
+     *
+     * `new` with custom limits for the materialised subtrees.
+     */
+    pub fn with_limits<'p, I: IntoIterator<Item = &'p str>>(
+        paths: I,
+        limits: Limits,
+    ) -> Result<Self> {
+        let mut projector = Projector {
+            sources: Vec::new(),
+            paths: Vec::new(),
+            limits,
+        };
+
+        for path in paths {
+            projector.paths.push(path.parse()?);
+            projector.sources.push(path.into());
+        }
+
+        Ok(projector)
+    }
+
+    /**
+     * @name project
+     * @description
+     *
+     * Walks the value at the front of `bytes` and collects the matches
+     * of every path, in the order they appear.
+     */
+    pub fn project<'p, 'buf>(&'p self, bytes: &'buf [u8]) -> Result<Projection<'p, 'buf>> {
+        let mut walk = Walk {
+            paths: &self.paths,
+            matches: self.paths.iter().map(|_| Vec::new()).collect(),
+            active: (0..self.paths.len()).map(|path| (path, 0)).collect(),
+            location: Vec::new(),
+            limits: self.limits,
+        };
+
+        /*
+         * Walking and skipping allocate nothing, only the materialised
+         * subtrees are held to the limits
+         */
+        let mut reader = Reader::with_limits(bytes, Limits::UNLIMITED);
+
+        walk.value(&mut reader, 0)?;
+
+        Ok(Projection {
+            sources: &self.sources,
+            matches: walk.matches,
+        })
+    }
+}
+
+/**
+ * @name Projection
+ * @description
+ *
+ * Matches of a Projector, keyed by path.
+ */
+#[derive(Debug, Clone)]
+pub struct Projection<'p, 'buf> {
+    sources: &'p [String],
+    matches: Vec<Vec<Match<'buf>>>,
+}
+
+impl<'p, 'buf> Projection<'p, 'buf> {
+    /**
+     * @name get
+     * @description
+     *
+     * Matches of `path`, nothing if it wasn't one of the projector's.
+     */
+    #[inline]
+    pub fn get(&self, path: &str) -> &[Match<'buf>] {
+        self.sources
+            .iter()
+            .position(|source| source == path)
+            .map_or(&[], |index| &self.matches[index])
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&'p str, &[Match<'buf>])> {
+        self.sources
+            .iter()
+            .map(String::as_str)
+            .zip(self.matches.iter().map(Vec::as_slice))
+    }
+}
+
+struct Walk<'p, 'buf> {
+    paths: &'p [Path],
+    matches: Vec<Vec<Match<'buf>>>,
+
+    /*
+     * (path, segments matched so far) of every path still alive at some
+     * level, each level's set pushed after its parent's
+     */
+    active: Vec<(usize, usize)>,
+
+    location: Vec<Step<'buf>>,
+    limits: Limits,
+}
+
+impl<'buf> Walk<'_, 'buf> {
+    /*
+     * Handles the value at the reader, with the paths in active[from..]
+     */
+    fn value(&mut self, reader: &mut Reader<'buf>, from: usize) -> Result<()> {
+        if from == self.active.len() {
+            return reader.skip_value();
+        }
+
+        let mut deeper = false;
+
+        for index in from..self.active.len() {
+            let (path, depth) = self.active[index];
+
+            if depth < self.paths[path].segments.len() {
+                deeper = true;
+                continue;
+            }
+
+            let rest = &reader.buffer()[reader.position()..];
+
+            self.matches[path].push(Match {
+                location: self.location.clone(),
+                value: Reader::with_limits(rest, self.limits).pull_value()?,
+            });
+        }
+
+        if !deeper {
+            return reader.skip_value();
+        }
+
+        match reader.peek_kind()? {
+            Kind::Array => self.array(reader, from),
+            Kind::Map => self.map(reader, from),
+            _ => reader.skip_value(),
+        }
+    }
+
+    fn array(&mut self, reader: &mut Reader<'buf>, from: usize) -> Result<()> {
+        let len = read_array_len(reader.pull_marker()?, reader)?;
+        let to = self.active.len();
+
+        for element in 0..len {
+            self.advance(from, to, |segment| match segment {
+                Segment::Index(index) => *index == element,
+                Segment::AnyIndex => true,
+                _ => false,
+            });
+
+            self.location.push(Step::Index(element));
+            self.value(reader, to)?;
+            self.location.pop();
+            self.active.truncate(to);
+        }
+
+        Ok(())
+    }
+
+    fn map(&mut self, reader: &mut Reader<'buf>, from: usize) -> Result<()> {
+        let len = read_map_len(reader.pull_marker()?, reader)?;
+        let to = self.active.len();
+
+        for _ in 0..len {
+            /*
+             * Only string keys can be matched
+             */
+            let key = match reader.peek() {
+                Some(0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32) => {
+                    let marker = reader.pull_marker()?;
+
+                    reader.pull_borrowed_str(marker)?
+                }
+                _ => {
+                    reader.skip_value()?;
+                    reader.skip_value()?;
+                    continue;
+                }
+            };
+
+            self.advance(from, to, |segment| match segment {
+                Segment::Key(name) => name == key,
+                Segment::AnyKey => true,
+                _ => false,
+            });
+
+            self.location.push(Step::Key(key));
+            self.value(reader, to)?;
+            self.location.pop();
+            self.active.truncate(to);
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Pushes the paths of active[from..to] whose next segment matches
+     */
+    #[inline]
+    fn advance(&mut self, from: usize, to: usize, matches: impl Fn(&Segment) -> bool) {
+        for index in from..to {
+            let (path, depth) = self.active[index];
+
+            if self.paths[path].segments.get(depth).is_some_and(&matches) {
+                self.active.push((path, depth + 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, string::ToString, vec};
+
+    use crate::msgpack::WriteTo;
+
+    fn state() -> Vec<u8> {
+        let players = (0..3u8)
+            .map(|sid| {
+                let mut player = BTreeMap::new();
+
+                player.insert("sid", sid);
+                player.insert("x", sid * 10);
+                player.insert("hp", 100);
+
+                player
+            })
+            .collect::<Vec<_>>();
+
+        let mut buffer = Vec::new();
+
+        ("state", players, vec![0u8; 500])
+            .write_to(&mut buffer)
+            .unwrap();
+
+        buffer
+    }
+
+    #[test]
+    fn test_paths() {
+        let path = "[1][*].sid".parse::<Path>().unwrap();
+
+        assert_eq!(
+            path.segments(),
+            [
+                Segment::Index(1),
+                Segment::AnyIndex,
+                Segment::Key("sid".into())
+            ]
+        );
+        assert_eq!(path.to_string(), "[1][*].sid");
+        assert_eq!(
+            "sid.*[0]".parse::<Path>().unwrap().segments(),
+            [
+                Segment::Key("sid".into()),
+                Segment::AnyKey,
+                Segment::Index(0)
+            ]
+        );
+        assert!("".parse::<Path>().unwrap().segments().is_empty());
+
+        for invalid in ["[1", "[x]", "[1].", "a..b"] {
+            assert!(invalid.parse::<Path>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_project() {
+        let buffer = state();
+        let projector =
+            Projector::new(["[1][*].sid", "[1][*].x", "[0]", "[1][5].x", "[1][2]"]).unwrap();
+        let projection = projector.project(&buffer).unwrap();
+
+        let sids = projection.get("[1][*].sid");
+
+        assert_eq!(sids.len(), 3);
+        assert_eq!(
+            sids[2],
+            Match {
+                location: vec![Step::Index(1), Step::Index(2), Step::Key("sid")],
+                value: Value::U8(2),
+            }
+        );
+        assert_eq!(
+            projection
+                .get("[1][*].x")
+                .iter()
+                .map(|x| x.value.clone())
+                .collect::<Vec<_>>(),
+            [Value::U8(0), Value::U8(10), Value::U8(20)]
+        );
+        assert_eq!(projection.get("[0]")[0].value, Value::Str("state"));
+        assert!(projection.get("[1][5].x").is_empty());
+        assert!(projection.get("unknown").is_empty());
+
+        /*
+         * A whole subtree, while other paths still look inside it
+         */
+        let player = &projection.get("[1][2]")[0].value;
+
+        assert!(matches!(player, Value::Map(entries) if entries.len() == 3));
+        assert_eq!(projection.iter().count(), 5);
+    }
+
+    #[test]
+    fn test_wildcard_keys() {
+        let buffer = state();
+        let projector = Projector::new([".*", "[1][0].*"]).unwrap();
+        let projection = projector.project(&buffer).unwrap();
+
+        /*
+         * The root is an array, `.*` only matches maps
+         */
+        assert!(projection.get(".*").is_empty());
+
+        let keys = projection
+            .get("[1][0].*")
+            .iter()
+            .map(|found| found.location[2])
+            .collect::<Vec<_>>();
+
+        assert_eq!(keys, [Step::Key("hp"), Step::Key("sid"), Step::Key("x")]);
+
+        assert!(projector.project(&buffer[..buffer.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let buffer = state();
+
+        /*
+         * Only the subtrees that are materialised count
+         */
+        let limits = Limits {
+            max_depth: 1,
+            ..Limits::DEFAULT
+        };
+
+        let projector = Projector::with_limits(["[1][0]"], limits).unwrap();
+
+        assert_eq!(projector.project(&buffer).unwrap().get("[1][0]").len(), 1);
+
+        let projector = Projector::with_limits(["[1]"], limits).unwrap();
+
+        assert!(matches!(
+            projector.project(&buffer),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        let mut deep = vec![0x92, 0x01];
+
+        deep.extend_from_slice(&[0x91; 100_000]);
+        deep.push(0xc0);
+
+        let projector = Projector::new(["[0]", "[1]"]).unwrap();
+
+        assert!(matches!(
+            projector.project(&deep),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+        assert_eq!(
+            Projector::new(["[0]"])
+                .unwrap()
+                .project(&deep)
+                .unwrap()
+                .get("[0]")[0]
+                .value,
+            Value::U8(1)
+        );
+    }
+}
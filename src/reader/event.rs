@@ -0,0 +1,262 @@
+use crate::{
+    constants::Families,
+    error::Result,
+    msgpack::{
+        array::{Array, read_array_len},
+        ext::read_ext_header,
+        float::{read_f32, read_f64},
+        integer::read_integer,
+        map::read_map_len,
+    },
+    reader::Reader,
+};
+
+/**
+ * @name Event
+ * @description
+ *
+ * One token of the encoded input, as returned by `Reader::next_event`.
+ *
+ * Containers only announce their length: the next `len` values (twice
+ * as many for a map, keys and values alternating) are their elements,
+ * so there's no end event and nothing to keep per level.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'a> {
+    Nil,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Str(&'a str),
+    Bin(&'a [u8]),
+    ArrayStart(usize),
+    MapStart(usize),
+    Ext(u8, &'a [u8]),
+}
+
+impl<'buf> Reader<'buf> {
+    /**
+     * @name next_event
+     * @description
+     *
+     * Reads the next token, or None at the end of the buffer.
+     *
+     * Nothing is allocated, so huge or deeply nested input can be
+     * transcoded or filtered in constant memory. Container lengths are
+     * checked against the reader's limits, and strings must be UTF-8.
+     *
+     * ```
+     * use msgpackp::reader::{Event, Reader};
+     *
+     * let buffer = [0x92, 0xa2, b'c', b'h', 0xd0, 0xfe];
+     * let mut reader = Reader::new(&buffer[..]);
+     *
+     * assert_eq!(reader.next_event().unwrap(), Some(Event::ArrayStart(2)));
+     * assert_eq!(reader.next_event().unwrap(), Some(Event::Str("ch")));
+     * assert_eq!(reader.next_event().unwrap(), Some(Event::Int(-2)));
+     * assert_eq!(reader.next_event().unwrap(), None);
+     * ```
+     */
+    pub fn next_event(&mut self) -> Result<Option<Event<'buf>>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+
+        let packet_type = self.pull_marker()?;
+
+        Ok(Some(match packet_type {
+            0x90..=0x9f | Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
+                let len = read_array_len(packet_type, self)?;
+
+                Event::ArrayStart(self.limits().check_array(len)?)
+            }
+
+            0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+                let len = read_map_len(packet_type, self)?;
+
+                Event::MapStart(self.limits().check_map(len)?)
+            }
+
+            0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+                Event::Str(self.pull_borrowed_str(packet_type)?)
+            }
+
+            Families::BIN8 | Families::BIN16 | Families::BIN32 => {
+                Event::Bin(self.pull_borrowed_bin(packet_type)?)
+            }
+
+            Families::FLOAT32 => Event::Float(read_f32(packet_type, self)? as f64),
+            Families::FLOAT64 => Event::Float(read_f64(packet_type, self)?),
+
+            Families::NIL | Families::RESERVED => Event::Nil,
+            Families::FALSE | Families::TRUE => Event::Bool(packet_type == Families::TRUE),
+
+            Families::FIXEXT1
+            | Families::FIXEXT2
+            | Families::FIXEXT4
+            | Families::FIXEXT8
+            | Families::FIXEXT16
+            | Families::EXT8
+            | Families::EXT16
+            | Families::EXT32 => {
+                let (type_, len) = read_ext_header(packet_type, self)?;
+
+                Event::Ext(type_, self.pull_borrowed(len)?)
+            }
+
+            /*
+             * Fixints and every uint/int width
+             */
+            _ => Event::Int(read_integer(packet_type, self)?),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+
+    use crate::{
+        error::Error,
+        msgpack::{WriteTo, ext::Extension},
+        reader::Limits,
+    };
+
+    /*
+     * msgpack -> JSON-ish text, keeping only how many elements each open
+     * container still owes
+     */
+    fn transcode(bytes: &[u8]) -> Result<String> {
+        let mut reader = Reader::new(bytes);
+        let mut output = String::new();
+        let mut owed: Vec<(usize, bool, char)> = Vec::new();
+
+        while let Some(event) = reader.next_event()? {
+            if let Some((left, is_map, _)) = owed.last() {
+                let index = match is_map {
+                    true => left % 2,
+                    false => 1,
+                };
+
+                if !output.ends_with(['[', '{']) {
+                    output.push(match (is_map, index) {
+                        (true, 1) => ':',
+                        _ => ',',
+                    });
+                }
+            }
+
+            let opened = match event {
+                Event::Nil => {
+                    output.push_str("null");
+                    None
+                }
+                Event::Bool(value) => {
+                    output.push_str(&format!("{value}"));
+                    None
+                }
+                Event::Int(value) => {
+                    output.push_str(&format!("{value}"));
+                    None
+                }
+                Event::Float(value) => {
+                    output.push_str(&format!("{value}"));
+                    None
+                }
+                Event::Str(value) => {
+                    output.push_str(&format!("{value:?}"));
+                    None
+                }
+                Event::Bin(value) | Event::Ext(_, value) => {
+                    output.push_str(&format!("{value:?}"));
+                    None
+                }
+                Event::ArrayStart(len) => {
+                    output.push('[');
+                    Some((len, false, ']'))
+                }
+                Event::MapStart(len) => {
+                    output.push('{');
+                    Some((len * 2, true, '}'))
+                }
+            };
+
+            if let Some((left, _, _)) = owed.last_mut() {
+                *left -= 1;
+            }
+
+            if let Some(container) = opened {
+                owed.push(container);
+            }
+
+            while let Some(&(0, _, close)) = owed.last() {
+                output.push(close);
+                owed.pop();
+            }
+        }
+
+        Ok(output)
+    }
+
+    #[test]
+    fn test_events() {
+        let mut buffer = Vec::new();
+
+        (
+            "ch",
+            vec![(1u32, -300i32), (70000, i32::MIN)],
+            [("hp", 100u8)].into_iter().collect::<BTreeMap<_, _>>(),
+            (Option::<u8>::None, true, 1.5f32, u64::MAX),
+            (&b"raw"[..], Extension::new(3, vec![1, 2]), Vec::<u8>::new()),
+        )
+            .write_to(&mut buffer)
+            .unwrap();
+
+        assert_eq!(
+            transcode(&buffer).unwrap(),
+            "[\"ch\",[[1,-300],[70000,-2147483648]],{\"hp\":100},\
+             [null,true,1.5,18446744073709551615],[[114, 97, 119],[1, 2],[]]]"
+        );
+
+        let mut reader = Reader::new(&buffer);
+
+        assert_eq!(reader.next_event().unwrap(), Some(Event::ArrayStart(5)));
+        assert_eq!(reader.next_event().unwrap(), Some(Event::Str("ch")));
+
+        /*
+         * Events and whole values mix freely
+         */
+        reader.skip_value().unwrap();
+
+        assert_eq!(reader.next_event().unwrap(), Some(Event::MapStart(1)));
+    }
+
+    #[test]
+    fn test_errors() {
+        /*
+         * Without limits, a huge array is only announced, it's up to the
+         * caller to stop early
+         */
+        let mut reader = Reader::with_limits(&[0xdd, 0xff, 0xff, 0xff, 0xff], Limits::UNLIMITED);
+
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(Event::ArrayStart(u32::MAX as usize))
+        );
+        assert_eq!(reader.next_event().unwrap(), None);
+
+        let limits = Limits {
+            max_map_len: 0,
+            ..Limits::DEFAULT
+        };
+
+        assert!(matches!(
+            Reader::with_limits(&[0x81, 0x01, 0x02], limits).next_event(),
+            Err(Error::LimitExceeded("Map"))
+        ));
+        assert!(Reader::new(&[0xa2, b'h']).next_event().is_err());
+        assert!(Reader::new(&[0xa1, 0xff]).next_event().is_err());
+        assert!(Reader::new(&[0xcd, 0x01]).next_event().is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::constants::Families;
+use crate::msgpack::array::Array;
+
+/**
+ * @name Kind
+ * @description
+ *
+ * Type of a value as told by its marker, before anything else is read.
+ *
+ * Integers are split by sign as encoded: a positive fixint or uint is
+ * `Uint`, a negative fixint or int is `Int` even if it holds a positive
+ * number.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Nil,
+    Bool,
+    Uint,
+    Int,
+    Float,
+    Str,
+    Bin,
+    Array,
+    Map,
+    Extension,
+}
+
+impl Kind {
+    /**
+     * @name of_marker
+     * @description
+     *
+     * Kind of the value starting with `marker`. The reserved marker is
+     * read as nil, like everywhere else.
+     */
+    #[inline]
+    pub const fn of_marker(marker: u8) -> Kind {
+        match marker {
+            0x00..=0x7f => Kind::Uint,
+            0x80..=0x8f => Kind::Map,
+            0x90..=0x9f => Kind::Array,
+            0xa0..=0xbf => Kind::Str,
+            0xe0..=0xff => Kind::Int,
+
+            Families::NIL | Families::RESERVED => Kind::Nil,
+            Families::FALSE | Families::TRUE => Kind::Bool,
+            Families::BIN8 | Families::BIN16 | Families::BIN32 => Kind::Bin,
+            Families::FLOAT32 | Families::FLOAT64 => Kind::Float,
+
+            Families::UINT8 | Families::UINT16 | Families::UINT32 | Families::UINT64 => Kind::Uint,
+            Families::INT8 | Families::INT16 | Families::INT32 | Families::INT64 => Kind::Int,
+            Families::STR8 | Families::STR16 | Families::STR32 => Kind::Str,
+
+            Families::FIXEXT1
+            | Families::FIXEXT2
+            | Families::FIXEXT4
+            | Families::FIXEXT8
+            | Families::FIXEXT16
+            | Families::EXT8
+            | Families::EXT16
+            | Families::EXT32 => Kind::Extension,
+
+            Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => Kind::Array,
+            Families::MAP16 | Families::MAP32 => Kind::Map,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::error::{Error, Result};
+
+/**
+ * @name Limits
+ * @description
+ *
+ * Caps on what untrusted input may ask the decoder to allocate.
+ *
+ * Strings and bins are borrowed from the buffer, so only containers are
+ * limited. The defaults keep the historical 100 entry cap on maps and
+ * allow arrays of up to a million elements. Decoding a Value or through serde recurses
+ * once per nesting level, so it also stops at `max_depth` before the
+ * stack runs out.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /*
+     * Most elements a single array may hold
+     */
+    pub max_array_len: usize,
+
+    /*
+     * Most key-value pairs a single map may hold
+     */
+    pub max_map_len: usize,
+
+    /*
+     * Most containers a value may be nested in. The default is low enough
+     * for a 2 MiB thread stack even in debug builds
+     */
+    pub max_depth: usize,
+}
+
+impl Limits {
+    pub const DEFAULT: Limits = Limits {
+        max_array_len: 1 << 20,
+        max_map_len: 100,
+        max_depth: 64,
+    };
+
+    /*
+     * For walks that allocate nothing, e.g. skipping values
+     */
+    pub const UNLIMITED: Limits = Limits {
+        max_array_len: usize::MAX,
+        max_map_len: usize::MAX,
+        max_depth: usize::MAX,
+    };
+
+    /**
+     * @name check_array
+     * @description
+     *
+     * Fails if an array of `len` elements is over the limit.
+     */
+    #[inline(always)]
+    pub(crate) fn check_array(&self, len: usize) -> Result<usize> {
+        match len > self.max_array_len {
+            true => Err(Error::LimitExceeded("Array")),
+            false => Ok(len),
+        }
+    }
+
+    /**
+     * @name check_map
+     * @description
+     *
+     * Fails if a map of `len` pairs is over the limit.
+     */
+    #[inline(always)]
+    pub(crate) fn check_map(&self, len: usize) -> Result<usize> {
+        match len > self.max_map_len {
+            true => Err(Error::LimitExceeded("Map")),
+            false => Ok(len),
+        }
+    }
+
+    /**
+     * @name check_depth
+     * @description
+     *
+     * Fails if a container at `depth` is nested too deep.
+     */
+    #[inline(always)]
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        match depth > self.max_depth {
+            true => Err(Error::LimitExceeded("Depth")),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Default for Limits {
+    #[inline(always)]
+    fn default() -> Self {
+        Limits::DEFAULT
+    }
+}
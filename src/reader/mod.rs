@@ -1,3 +1,12 @@
+mod event;
+mod kind;
+mod limits;
+#[allow(clippy::module_inception)]
 mod reader;
+mod scan;
 
+pub use event::Event;
+pub use kind::Kind;
+pub use limits::Limits;
 pub use reader::*;
+pub(crate) use scan::{Scan, scan, scan_owing};
@@ -1,28 +1,163 @@
-use anyhow::Result;
+use ordered_float::OrderedFloat;
+
+use crate::error::{Error, Result};
 
 use crate::{
     constants::Families,
-    msgpack::{ReadFrom, array::Array, ext::Extension},
+    msgpack::{
+        ReadFrom,
+        array::{Array, read_array},
+        bin::read_bin_len,
+        ext::read_extension,
+        float::{read_f32, read_f64},
+        integer::{read_i8, read_i16, read_i32, read_i64, read_u8, read_u16, read_u32, read_u64},
+        map::read_map,
+        string::read_str_len,
+    },
+    reader::{Kind, Limits, Scan, scan},
     value::Value,
 };
 
-pub struct Reader<R: AsRef<[u8]>> {
-    read: R,
+/**
+ * @name Reader
+ * @description
+ *
+ * Packet reader over a byte slice.
+ *
+ * Everything it hands out borrows the slice for `'buf`, not the reader,
+ * so values pulled one after the other can all be kept around.
+ */
+pub struct Reader<'buf> {
+    read: &'buf [u8],
     index: usize,
+    limits: Limits,
+
+    /*
+     * Containers the next value is nested in
+     */
+    depth: usize,
 }
 
-impl<R: AsRef<[u8]>> Reader<R> {
+impl<'buf> Reader<'buf> {
     #[inline(always)]
     /**
      * @name new
      * @description
      *
      * Creates a packet reader.
+     */
+    pub fn new(read: &'buf [u8]) -> Self {
+        Reader::with_limits(read, Limits::DEFAULT)
+    }
+
+    /**
+     * @name with_limits
+     * @description
+     *
+     * Creates a packet reader that enforces custom limits.
+     */
+    #[inline(always)]
+    pub fn with_limits(read: &'buf [u8], limits: Limits) -> Self {
+        Reader {
+            read,
+            index: 0,
+            limits,
+            depth: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /**
+     * @name nested
+     * @description
+     *
+     * Runs `read` one container deeper, failing past the limits'
+     * `max_depth`. Everything that recurses once per nesting level goes
+     * through here, so hostile input can't run the stack out.
+     */
+    #[inline(always)]
+    pub(crate) fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.enter()?;
+        let value = read(self);
+        self.leave();
+
+        value
+    }
+
+    /*
+     * `nested` in two halves, for callers that wrap the reader. Every
+     * successful `enter` has to be matched by a `leave`
+     */
+    #[inline(always)]
+    pub(crate) fn enter(&mut self) -> Result<()> {
+        self.limits.check_depth(self.depth + 1)?;
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /**
+     * @name peek
+     * @description
      *
-     * Remember: It's always better to pass a slice: not a Vec<u8> or Box<[u8]>.
+     * Returns the next byte without consuming it.
      */
-    pub fn new(read: R) -> Self {
-        Reader { read, index: 0 }
+    #[inline(always)]
+    pub fn peek(&self) -> Option<u8> {
+        self.read.get(self.index).copied()
+    }
+
+    /**
+     * @name peek_marker
+     * @description
+     *
+     * Returns the marker of the next value without consuming it.
+     */
+    #[inline(always)]
+    pub fn peek_marker(&self) -> Result<u8> {
+        self.peek().ok_or(Error::UnexpectedEof("marker"))
+    }
+
+    /**
+     * @name peek_kind
+     * @description
+     *
+     * Returns the type of the next value without consuming anything.
+     *
+     * ```
+     * use msgpackp::reader::{Kind, Reader};
+     *
+     * let reader = Reader::new(&[0x92, 0x01, 0x02]);
+     *
+     * assert_eq!(reader.peek_kind().unwrap(), Kind::Array);
+     * ```
+     */
+    #[inline(always)]
+    pub fn peek_kind(&self) -> Result<Kind> {
+        self.peek_marker().map(Kind::of_marker)
+    }
+
+    /**
+     * @name pull_marker
+     * @description
+     *
+     * Pulls the marker of the next value.
+     */
+    #[inline(always)]
+    pub(crate) fn pull_marker(&mut self) -> Result<u8> {
+        match self.pull(1).first() {
+            Some(&marker) => Ok(marker),
+            None => Err(Error::UnexpectedEof("marker")),
+        }
     }
 
     #[inline]
@@ -32,16 +167,216 @@ impl<R: AsRef<[u8]>> Reader<R> {
      *
      * Reads a certain value from inner byte slice.
      */
-    pub fn pull_value<'a>(&'a mut self) -> Result<Value<'a>> {
-        let packet_type = self.pull(1)[0];
+    pub fn pull_value(&mut self) -> Result<Value<'buf>> {
+        let packet_type = self.pull_marker()?;
+
+        self.pull_value_of(packet_type)
+    }
+
+    /**
+     * @name skip_value
+     * @description
+     *
+     * Steps over the next value without decoding or allocating it.
+     *
+     * ```
+     * use msgpackp::reader::Reader;
+     *
+     * let buffer = [0x92, 0x01, 0x02, 0xa2, b'h', b'i'];
+     * let mut reader = Reader::new(&buffer[..]);
+     *
+     * reader.skip_value().unwrap();
+     *
+     * assert_eq!(reader.read::<&str>().unwrap(), "hi");
+     * ```
+     */
+    #[inline]
+    pub fn skip_value(&mut self) -> Result<()> {
+        let rest = self.read.get(self.index..).unwrap_or_default();
+
+        match scan(rest, &self.limits)? {
+            Scan::Complete(len) => {
+                self.index += len;
+
+                Ok(())
+            }
+            Scan::Incomplete(_) => Err(Error::UnexpectedEof("value")),
+        }
+    }
+
+    /**
+     * @name values
+     * @description
+     *
+     * Iterates over values stored back to back until the buffer ends.
+     *
+     * The iterator stops after the first error, as there's no telling
+     * where the next value would start.
+     */
+    #[inline(always)]
+    pub fn values(&mut self) -> Values<'_, 'buf> {
+        Values {
+            reader: self,
+            failed: false,
+        }
+    }
+
+    /**
+     * @name remaining
+     * @description
+     *
+     * Number of bytes left to read.
+     */
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        self.read.len().saturating_sub(self.index)
+    }
+
+    /**
+     * @name position
+     * @description
+     *
+     * Offset of the next byte to read, e.g. to report where a decode
+     * failed.
+     *
+     * Lenient reads like `pull_string` may leave it past the end of a
+     * truncated buffer.
+     */
+    #[inline(always)]
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    /**
+     * @name set_position
+     * @description
+     *
+     * Moves to `position`, which may be anywhere up to the end of the
+     * buffer. It should be the start of a value for reads to make sense.
+     */
+    #[inline]
+    pub fn set_position(&mut self, position: usize) -> Result<()> {
+        if position > self.read.len() {
+            return Err(Error::Message("Position is past the end of the buffer"));
+        }
+
+        self.index = position;
+
+        Ok(())
+    }
+
+    /**
+     * @name checkpoint
+     * @description
+     *
+     * Starts a speculative read: the returned guard derefs to the reader
+     * and rewinds it to the current position when dropped, unless it's
+     * committed.
+     *
+     * ```
+     * use msgpackp::reader::Reader;
+     *
+     * let mut reader = Reader::new(&[0xa2, b'h', b'i']);
+     *
+     * {
+     *     let mut attempt = reader.checkpoint();
+     *
+     *     assert!(attempt.read::<u32>().is_err());
+     * }
+     *
+     * let mut attempt = reader.checkpoint();
+     *
+     * assert_eq!(attempt.read::<&str>().unwrap(), "hi");
+     *
+     * attempt.commit();
+     *
+     * assert!(reader.is_empty());
+     * ```
+     */
+    #[inline(always)]
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, 'buf> {
+        Checkpoint {
+            position: self.index,
+            reader: self,
+            committed: false,
+        }
+    }
+
+    /**
+     * @name buffer
+     * @description
+     *
+     * The whole slice being read, including the bytes already read.
+     */
+    #[inline(always)]
+    pub(crate) fn buffer(&self) -> &'buf [u8] {
+        self.read
+    }
+
+    /**
+     * @name is_empty
+     * @description
+     *
+     * Whether every byte was read.
+     */
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
 
+    /**
+     * @name read
+     * @description
+     *
+     * Decodes the next value straight into `F`, without building a `Value`.
+     *
+     * ```
+     * use msgpackp::reader::Reader;
+     *
+     * let buffer = [0x92, 0xa2, b'c', b'h', 0x92, 0x01, 0xcc, 0xff];
+     * let mut reader = Reader::new(&buffer[..]);
+     *
+     * let (code, (x, y)): (&str, (u16, u16)) = reader.read().unwrap();
+     *
+     * assert_eq!((code, x, y), ("ch", 1, 255));
+     * ```
+     */
+    #[inline(always)]
+    pub fn read<F: ReadFrom<'buf>>(&mut self) -> Result<F> {
+        F::read_from(self)
+    }
+
+    /**
+     * @name pull_value_of
+     * @description
+     *
+     * Reads the rest of a value whose marker was already pulled.
+     */
+    #[inline]
+    pub(crate) fn pull_value_of(&mut self, packet_type: u8) -> Result<Value<'buf>> {
         Ok(match packet_type {
             /* Array */
-            0x90..0x9f => Value::array(Box::<[Value]>::read_from(packet_type, self)?),
-            Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
-                Value::array(Box::<[Value]>::read_from(packet_type, self)?)
+            0x90..=0x9f | Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
+                Value::array(read_array(packet_type, self)?)
+            }
+
+            /* Map */
+            0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+                Value::map(read_map(packet_type, self)?)
             }
 
+            _ => self.pull_scalar_of(packet_type)?,
+        })
+    }
+
+    /*
+     * Everything but containers. Kept out of `pull_value_of`, which is
+     * on the stack once per nesting level, so that its frame stays small
+     * in debug builds too
+     */
+    #[inline]
+    fn pull_scalar_of(&mut self, packet_type: u8) -> Result<Value<'buf>> {
+        Ok(match packet_type {
             /* String */
             0xa0..=0xbf => Value::str(self.pull_string(packet_type)),
             Families::STR8 | Families::STR16 | Families::STR32 => {
@@ -49,45 +384,30 @@ impl<R: AsRef<[u8]>> Reader<R> {
             }
 
             /* Positive fixint */
-            0x00..0x7f => Value::u8(u8::read_from(packet_type, self)?),
+            0x00..=0x7f => Value::u8(read_u8(packet_type, self)?),
 
             /* Negative fixiunt */
-            0xe0..=0xff => Value::i8(i8::read_from(packet_type, self)?),
-
-            /* Map */
-            0x80..0x8f => Value::map(Box::<[(Value, Value)]>::read_from(packet_type, self)?),
-            Families::MAP16 | Families::MAP32 => {
-                Value::map(Box::<[(Value, Value)]>::read_from(packet_type, self)?)
-            }
+            0xe0..=0xff => Value::i8(read_i8(packet_type, self)?),
 
             /* Float */
-            Families::FLOAT32 => Value::f32(ordered_float::OrderedFloat(f32::read_from(
-                packet_type,
-                self,
-            )?)),
-            Families::FLOAT64 => Value::f64(ordered_float::OrderedFloat(f64::read_from(
-                packet_type,
-                self,
-            )?)),
+            Families::FLOAT32 => Value::f32(OrderedFloat(read_f32(packet_type, self)?)),
+            Families::FLOAT64 => Value::f64(OrderedFloat(read_f64(packet_type, self)?)),
 
             /* Integer */
-            Families::UINT8 => Value::u8(u8::read_from(packet_type, self)?),
-            Families::UINT16 => Value::u16(u16::read_from(packet_type, self)?),
-            Families::UINT32 => Value::u32(u32::read_from(packet_type, self)?),
-            Families::UINT64 => Value::u64(u64::read_from(packet_type, self)?),
-            Families::INT8 => Value::i8(i8::read_from(packet_type, self)?),
-            Families::INT16 => Value::i16(i16::read_from(packet_type, self)?),
-            Families::INT32 => Value::i32(i32::read_from(packet_type, self)?),
-            Families::INT64 => Value::i64(i64::read_from(packet_type, self)?),
+            Families::UINT8 => Value::u8(read_u8(packet_type, self)?),
+            Families::UINT16 => Value::u16(read_u16(packet_type, self)?),
+            Families::UINT32 => Value::u32(read_u32(packet_type, self)?),
+            Families::UINT64 => Value::u64(read_u64(packet_type, self)?),
+            Families::INT8 => Value::i8(read_i8(packet_type, self)?),
+            Families::INT16 => Value::i16(read_i16(packet_type, self)?),
+            Families::INT32 => Value::i32(read_i32(packet_type, self)?),
+            Families::INT64 => Value::i64(read_i64(packet_type, self)?),
 
             Families::NIL | Families::RESERVED => Value::Nil,
-            Families::FALSE | Families::TRUE => Value::bool(bool::read_from(packet_type, self)?),
-            Families::BIN8 | Families::BIN16 | Families::BIN32 => Value::bin(
-                Vec::<u8>::read_from(packet_type, self)?
-                    .iter()
-                    .map(|&byte| byte)
-                    .collect::<Vec<_>>(),
-            ),
+            Families::FALSE | Families::TRUE => Value::bool(packet_type == Families::TRUE),
+            Families::BIN8 | Families::BIN16 | Families::BIN32 => {
+                Value::bin(self.pull_bin(packet_type)?)
+            }
 
             /* Ext */
             Families::FIXEXT1
@@ -97,34 +417,48 @@ impl<R: AsRef<[u8]>> Reader<R> {
             | Families::FIXEXT16
             | Families::EXT8
             | Families::EXT16
-            | Families::EXT32 => Value::extension(Extension::read_from(packet_type, self)?),
+            | Families::EXT32 => Value::extension(read_extension(packet_type, self)?),
 
-            /* Reserved */
-            127..=191 => Value::Nil,
+            _ => return Err(Error::UnexpectedMarker(packet_type)),
         })
     }
 
+    /*
+     * Containers are allocated up front, so their length can't be more
+     * than the bytes left: every element takes at least one
+     */
+    #[inline(always)]
+    pub(crate) fn container_len(&self, len: usize) -> Result<usize> {
+        match len > self.remaining() {
+            true => Err(Error::UnexpectedEof("container")),
+            false => Ok(len),
+        }
+    }
+
     /**
      * @name pull_string
      * @description
      *
-     * Pulls a &'a str from underlying buffer
+     * Pulls a &'buf str from underlying buffer
      * Guaranteed to never crash
      */
     #[inline]
-    pub fn pull_string<'a>(&'a mut self, strtype: u8) -> &'a str {
+    pub fn pull_string(&mut self, strtype: u8) -> &'buf str {
         let len = match strtype {
             /*
              * Fixed strings start from 0xa0 and end at 0xbf
              *
              * https://github.com/msgpack/msgpack/blob/master/spec.md#:~:text=101xxxxx-,0xa0%20%2D%200xbf,-nil
              */
-            0xa0..0xbf => (strtype - 0xa0) as usize,
+            0xa0..=0xbf => (strtype - 0xa0) as usize,
 
             /*
              * 1 byte per u8
              */
-            Families::STR8 => self.pull(1)[0] as usize,
+            Families::STR8 => match self.pull(1).first() {
+                Some(&len) => len as usize,
+                None => return "",
+            },
 
             /*
              * 2 bytes per u16
@@ -162,7 +496,25 @@ impl<R: AsRef<[u8]>> Reader<R> {
             _ => 1usize,
         };
 
-        std::str::from_utf8(self.pull(len)).unwrap_or("")
+        core::str::from_utf8(self.pull(len)).unwrap_or("")
+    }
+
+    /**
+     * @name pull_bin
+     * @description
+     *
+     * Pulls a &'buf [u8] bin payload from underlying buffer
+     */
+    #[inline]
+    pub fn pull_bin(&mut self, bintype: u8) -> Result<&'buf [u8]> {
+        let len = read_bin_len(bintype, self)?;
+        let bytes = self.pull(len);
+
+        if bytes.len() != len {
+            return Err(Error::UnexpectedEof("BIN"));
+        }
+
+        Ok(bytes)
     }
 
     /**
@@ -170,26 +522,256 @@ impl<R: AsRef<[u8]>> Reader<R> {
      * @description
      *
      * Pulls a slice from the underlying buffer.
-     * Safe as long as everything is aligned properly.
+     *
+     * Near the end of the buffer the slice is shorter than `len`,
+     * which is why every caller checks its length.
      */
     #[inline(always)]
-    pub fn pull<'a>(&'a mut self, len: usize) -> &'a [u8] {
-        let bytes = self.read.as_ref();
+    pub fn pull(&mut self, len: usize) -> &'buf [u8] {
+        let bytes = self.read;
 
-        unsafe {
-            /*
-             * Safety: bytes slice is aligned, self.index is aligned too
-             *
-             * We don't pull them from C++ (thank god)
-             *
-             * Data is non-null and valid.
-             */
+        /*
+         * Clamp instead of reading past the end: the input comes from the network
+         */
+        let start = self.index.min(bytes.len());
+        let end = self.index.saturating_add(len).min(bytes.len());
 
-            let ptr = bytes.as_ptr().add(self.index);
+        self.index = self.index.saturating_add(len);
 
-            self.index += len;
+        &bytes[start..end]
+    }
+
+    /**
+     * @name pull_borrowed
+     * @description
+     *
+     * Pulls a slice from the underlying buffer.
+     *
+     * Unlike `pull`, it's bounds-checked.
+     */
+    #[inline(always)]
+    pub fn pull_borrowed(&mut self, len: usize) -> Result<&'buf [u8]> {
+        let bytes = self.read;
+
+        let slice = self
+            .index
+            .checked_add(len)
+            .and_then(|end| bytes.get(self.index..end))
+            .ok_or(Error::UnexpectedEof("slice"))?;
+
+        self.index += len;
+
+        Ok(slice)
+    }
+
+    /**
+     * @name pull_borrowed_str
+     * @description
+     *
+     * Pulls a &'buf str whose marker was already pulled.
+     *
+     * Unlike `pull_string`, invalid UTF-8 is an error.
+     */
+    #[inline]
+    pub fn pull_borrowed_str(&mut self, strtype: u8) -> Result<&'buf str> {
+        let len = read_str_len(strtype, self)?;
+
+        core::str::from_utf8(self.pull_borrowed(len)?)
+            .map_err(|_| Error::Message("String is not valid UTF-8"))
+    }
 
-            std::slice::from_raw_parts(ptr, len)
+    /**
+     * @name pull_borrowed_bin
+     * @description
+     *
+     * Pulls a &'buf [u8] bin payload whose marker was already pulled.
+     */
+    #[inline]
+    pub fn pull_borrowed_bin(&mut self, bintype: u8) -> Result<&'buf [u8]> {
+        let len = read_bin_len(bintype, self)?;
+
+        self.pull_borrowed(len)
+    }
+}
+
+/**
+ * @name Checkpoint
+ * @description
+ *
+ * Guard returned by `Reader::checkpoint`.
+ */
+pub struct Checkpoint<'r, 'buf> {
+    reader: &'r mut Reader<'buf>,
+    position: usize,
+    committed: bool,
+}
+
+impl Checkpoint<'_, '_> {
+    /**
+     * @name commit
+     * @description
+     *
+     * Keeps everything read since the checkpoint.
+     */
+    #[inline(always)]
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /**
+     * @name rewind
+     * @description
+     *
+     * Goes back to the checkpoint now, to try another way of reading
+     * the same bytes.
+     */
+    #[inline(always)]
+    pub fn rewind(&mut self) {
+        self.reader.index = self.position;
+    }
+
+    /**
+     * @name start
+     * @description
+     *
+     * Position the checkpoint was taken at.
+     */
+    #[inline(always)]
+    pub fn start(&self) -> usize {
+        self.position
+    }
+}
+
+impl<'buf> core::ops::Deref for Checkpoint<'_, 'buf> {
+    type Target = Reader<'buf>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        self.reader
+    }
+}
+
+impl core::ops::DerefMut for Checkpoint<'_, '_> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.reader
+    }
+}
+
+impl Drop for Checkpoint<'_, '_> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        if !self.committed {
+            self.rewind();
+        }
+    }
+}
+
+/**
+ * @name Values
+ * @description
+ *
+ * Iterator returned by `Reader::values`.
+ */
+pub struct Values<'r, 'buf> {
+    reader: &'r mut Reader<'buf>,
+    failed: bool,
+}
+
+impl<'buf> Iterator for Values<'_, 'buf> {
+    type Item = Result<Value<'buf>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.reader.is_empty() {
+            return None;
         }
+
+        let value = self.reader.pull_value();
+
+        self.failed = value.is_err();
+
+        Some(value)
+    }
+}
+
+impl core::iter::FusedIterator for Values<'_, '_> {}
+
+/**
+ * @name decode_exact
+ * @description
+ *
+ * Decodes a buffer holding exactly one value, trailing bytes are an error.
+ *
+ * ```
+ * use msgpackp::{reader::decode_exact, value::Value};
+ *
+ * assert_eq!(decode_exact::<u8>(&[0x2a]).unwrap(), 42);
+ * assert!(decode_exact::<Value>(&[0x2a, 0xc0]).is_err());
+ * ```
+ */
+#[inline]
+pub fn decode_exact<'buf, T: ReadFrom<'buf>>(bytes: &'buf [u8]) -> Result<T> {
+    decode_exact_with(bytes, Limits::DEFAULT)
+}
+
+/**
+ * @name decode_exact_with
+ * @description
+ *
+ * `decode_exact` with custom limits.
+ */
+#[inline]
+pub fn decode_exact_with<'buf, T: ReadFrom<'buf>>(bytes: &'buf [u8], limits: Limits) -> Result<T> {
+    let mut reader = Reader::with_limits(bytes, limits);
+    let value = reader.read()?;
+
+    /*
+     * Lenient pulls (pull_string) clamp at the end instead of failing
+     */
+    if reader.index > bytes.len() {
+        return Err(Error::UnexpectedEof("value"));
+    }
+
+    if !reader.is_empty() {
+        return Err(Error::Message("Trailing bytes after the value"));
+    }
+
+    Ok(value)
+}
+
+/**
+ * @name validate
+ * @description
+ *
+ * Checks that `bytes` starts with a well-formed value within the default
+ * limits, and returns how many bytes it takes.
+ *
+ * Nothing is decoded or allocated, so it's cheap enough to filter
+ * garbage straight off the socket. Trailing bytes are left alone.
+ *
+ * ```
+ * use msgpackp::reader::validate;
+ *
+ * assert_eq!(validate(&[0x92, 0x01, 0xa1, b'x', 0xc0]).unwrap(), 4);
+ * assert!(validate(&[0x92, 0x01]).is_err());
+ * ```
+ */
+#[inline]
+pub fn validate(bytes: &[u8]) -> Result<usize> {
+    validate_with(bytes, &Limits::DEFAULT)
+}
+
+/**
+ * @name validate_with
+ * @description
+ *
+ * `validate` with custom limits.
+ */
+#[inline]
+pub fn validate_with(bytes: &[u8], limits: &Limits) -> Result<usize> {
+    match scan(bytes, limits)? {
+        Scan::Complete(len) => Ok(len),
+        Scan::Incomplete(_) => Err(Error::UnexpectedEof("value")),
     }
 }
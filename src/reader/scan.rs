@@ -0,0 +1,141 @@
+use crate::constants::Families;
+use crate::error::{Error, Result};
+use crate::msgpack::{array::Array, map::Map};
+use crate::reader::Limits;
+
+/**
+ * @name Scan
+ * @description
+ *
+ * Outcome of scanning the front of a buffer for one value.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scan {
+    /*
+     * The value is complete and takes that many bytes
+     */
+    Complete(usize),
+
+    /*
+     * The buffer ends inside the value, at least that many more
+     * bytes are needed
+     */
+    Incomplete(usize),
+}
+
+/**
+ * @name scan
+ * @description
+ *
+ * Finds where the value at the front of `bytes` ends, without decoding
+ * or allocating anything.
+ *
+ * Containers aren't recursed into: the scan keeps a count of the values
+ * still owed, so deeply nested input can't blow the stack. Their lengths
+ * are checked against `limits` as they're met.
+ */
+#[inline]
+pub(crate) fn scan(bytes: &[u8], limits: &Limits) -> Result<Scan> {
+    scan_owing(bytes, limits).map(|(scan, _)| scan)
+}
+
+/**
+ * @name scan_owing
+ * @description
+ *
+ * Same as `scan`, also telling how many values were still owed when the
+ * buffer ended. Each of them takes one more byte at least, which bounds
+ * how long an incomplete value claims to be.
+ */
+pub(crate) fn scan_owing(bytes: &[u8], limits: &Limits) -> Result<(Scan, usize)> {
+    let mut position = 0usize;
+    let mut left = 1usize;
+
+    /*
+     * Bytes `len` past the position, or how many are missing
+     */
+    macro_rules! need {
+        ($len:expr) => {{
+            let end = position.saturating_add($len);
+
+            match bytes.get(position..end) {
+                Some(slice) => slice,
+                None => return Ok((Scan::Incomplete(end - bytes.len()), left)),
+            }
+        }};
+    }
+
+    macro_rules! length {
+        ($width:literal) => {{
+            let mut length = 0usize;
+
+            for &byte in need!($width) {
+                length = (length << 8) | byte as usize;
+            }
+
+            position += $width;
+            length
+        }};
+    }
+
+    while left > 0 {
+        left -= 1;
+
+        let marker = need!(1)[0];
+        position += 1;
+
+        let (skip, values) = match marker {
+            /*
+             * Fixint, nil, booleans and the reserved marker (read as nil)
+             */
+            0x00..=0x7f
+            | 0xe0..=0xff
+            | Families::NIL
+            | Families::RESERVED
+            | Families::FALSE
+            | Families::TRUE => (0, 0),
+
+            0xa0..=0xbf => ((marker - Families::FIXSTR) as usize, 0),
+            0x90..=0x9f => (
+                0,
+                limits.check_array((marker - Array::FIXARRAY_TYPE) as usize)?,
+            ),
+            0x80..=0x8f => (0, limits.check_map((marker - Map::FIXMAP) as usize)? * 2),
+
+            Families::UINT8 | Families::INT8 => (1, 0),
+            Families::UINT16 | Families::INT16 => (2, 0),
+            Families::UINT32 | Families::INT32 | Families::FLOAT32 => (4, 0),
+            Families::UINT64 | Families::INT64 | Families::FLOAT64 => (8, 0),
+
+            Families::STR8 | Families::BIN8 => (length!(1), 0),
+            Families::STR16 | Families::BIN16 => (length!(2), 0),
+            Families::STR32 | Families::BIN32 => (length!(4), 0),
+
+            /*
+             * Ext data is preceded by its type byte
+             */
+            Families::FIXEXT1 => (2, 0),
+            Families::FIXEXT2 => (3, 0),
+            Families::FIXEXT4 => (5, 0),
+            Families::FIXEXT8 => (9, 0),
+            Families::FIXEXT16 => (17, 0),
+            Families::EXT8 => (length!(1) + 1, 0),
+            Families::EXT16 => (length!(2) + 1, 0),
+            Families::EXT32 => (length!(4) + 1, 0),
+
+            Array::ARRAY_16_TYPE => (0, limits.check_array(length!(2))?),
+            Array::ARRAY_32_TYPE => (0, limits.check_array(length!(4))?),
+            Map::MAP_16_TYPE => (0, limits.check_map(length!(2))? * 2),
+            Map::MAP_32_TYPE => (0, limits.check_map(length!(4))? * 2),
+        };
+
+        need!(skip);
+        position += skip;
+
+        left = left
+            .checked_add(values)
+            .ok_or(Error::LengthOverflow("Scan"))?;
+    }
+
+    Ok((Scan::Complete(position), 0))
+}
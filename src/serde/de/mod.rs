@@ -0,0 +1,443 @@
+use alloc::string::ToString;
+use core::fmt::Display;
+
+use ::serde::de::{
+    self, DeserializeSeed, IgnoredAny, IntoDeserializer, Visitor,
+    value::{BorrowedBytesDeserializer, BorrowedStrDeserializer},
+};
+
+use crate::{
+    constants::Families,
+    error::{Error, Result},
+    msgpack::{
+        array::{Array, read_array_len},
+        ext::read_ext_header,
+        float::{read_f32, read_f64},
+        integer::{read_i8, read_i16, read_i32, read_i64, read_u8, read_u16, read_u32, read_u64},
+        map::read_map_len,
+    },
+    reader::{Limits, Reader},
+    serde::value::EXTENSION,
+};
+
+impl de::Error for Error {
+    #[cold]
+    fn custom<T: Display>(message: T) -> Self {
+        Error::Custom(message.to_string())
+    }
+}
+
+/**
+ * @name Deserializer
+ * @description
+ *
+ * serde data format on top of a Reader over a byte slice.
+ *
+ * Strings and binaries are handed out borrowed from the input, so
+ * `&'de str` and `&'de [u8]` fields decode without copying.
+ *
+ * Ext values are visited as a newtype struct (see `EXTENSION`) around
+ * a `(type, data)` sequence.
+ *
+ * Every nested container is a recursive call, so nesting deeper than
+ * the limits' `max_depth` is an error.
+ */
+pub struct Deserializer<'de> {
+    reader: Reader<'de>,
+}
+
+impl<'de> Deserializer<'de> {
+    #[inline(always)]
+    pub fn from_slice(bytes: &'de [u8]) -> Self {
+        Deserializer::with_limits(bytes, Limits::DEFAULT)
+    }
+
+    #[inline(always)]
+    pub fn with_limits(bytes: &'de [u8], limits: Limits) -> Self {
+        Deserializer {
+            reader: Reader::with_limits(bytes, limits),
+        }
+    }
+
+    /**
+     * @name end
+     * @description
+     *
+     * Checks that the whole input was consumed.
+     */
+    #[inline]
+    pub fn end(&self) -> Result<()> {
+        match self.reader.peek() {
+            Some(_) => Err(Error::Message("Trailing bytes after the value")),
+            None => Ok(()),
+        }
+    }
+
+    #[inline(always)]
+    fn marker(&mut self) -> Result<u8> {
+        match self.reader.pull_borrowed(1)? {
+            [marker] => Ok(*marker),
+            _ => Err(Error::UnexpectedEof("marker")),
+        }
+    }
+
+    fn visit_marker<V: Visitor<'de>>(&mut self, marker: u8, visitor: V) -> Result<V::Value> {
+        let reader = &mut self.reader;
+
+        match marker {
+            /* Positive and negative fixint */
+            0x00..=0x7f => visitor.visit_u8(marker),
+            0xe0..=0xff => visitor.visit_i8(marker as i8),
+
+            /* Array */
+            0x90..=0x9f | Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
+                let len = read_array_len(marker, reader)?;
+
+                self.visit_container(len, visitor, false)
+            }
+
+            /* Map */
+            0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+                let len = read_map_len(marker, reader)?;
+
+                self.visit_container(len, visitor, true)
+            }
+
+            /* String */
+            0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+                visitor.visit_borrowed_str(reader.pull_borrowed_str(marker)?)
+            }
+
+            /* Bin */
+            Families::BIN8 | Families::BIN16 | Families::BIN32 => {
+                visitor.visit_borrowed_bytes(reader.pull_borrowed_bin(marker)?)
+            }
+
+            /* Ext */
+            Families::FIXEXT1
+            | Families::FIXEXT2
+            | Families::FIXEXT4
+            | Families::FIXEXT8
+            | Families::FIXEXT16
+            | Families::EXT8
+            | Families::EXT16
+            | Families::EXT32 => {
+                let (type_, len) = read_ext_header(marker, reader)?;
+                let data = reader.pull_borrowed(len)?;
+
+                visitor.visit_newtype_struct(ExtDeserializer { type_, data })
+            }
+
+            Families::NIL => visitor.visit_unit(),
+            Families::FALSE => visitor.visit_bool(false),
+            Families::TRUE => visitor.visit_bool(true),
+
+            /* Float */
+            Families::FLOAT32 => visitor.visit_f32(read_f32(marker, reader)?),
+            Families::FLOAT64 => visitor.visit_f64(read_f64(marker, reader)?),
+
+            /* Integer */
+            Families::UINT8 => visitor.visit_u8(read_u8(marker, reader)?),
+            Families::UINT16 => visitor.visit_u16(read_u16(marker, reader)?),
+            Families::UINT32 => visitor.visit_u32(read_u32(marker, reader)?),
+            Families::UINT64 => visitor.visit_u64(read_u64(marker, reader)?),
+            Families::INT8 => visitor.visit_i8(read_i8(marker, reader)?),
+            Families::INT16 => visitor.visit_i16(read_i16(marker, reader)?),
+            Families::INT32 => visitor.visit_i32(read_i32(marker, reader)?),
+            Families::INT64 => visitor.visit_i64(read_i64(marker, reader)?),
+
+            _ => Err(Error::UnexpectedMarker(marker)),
+        }
+    }
+
+    /**
+     * @name nested
+     * @description
+     *
+     * Runs `visit` one container deeper, counted by the reader like any
+     * other decode.
+     */
+    #[inline(always)]
+    fn nested<T>(&mut self, visit: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.reader.enter()?;
+        let value = visit(self);
+        self.reader.leave();
+
+        value
+    }
+
+    fn visit_container<V: Visitor<'de>>(
+        &mut self,
+        len: usize,
+        visitor: V,
+        map: bool,
+    ) -> Result<V::Value> {
+        self.nested(|de| de.visit_elements(len, visitor, map))
+    }
+
+    fn visit_elements<V: Visitor<'de>>(
+        &mut self,
+        len: usize,
+        visitor: V,
+        map: bool,
+    ) -> Result<V::Value> {
+        let mut access = Access {
+            de: self,
+            left: len,
+        };
+
+        let value = if map {
+            visitor.visit_map(&mut access)?
+        } else {
+            visitor.visit_seq(&mut access)?
+        };
+
+        /*
+         * The visitor may stop early (e.g. a struct that gained fields on the
+         * other end): skip what it left so the stream stays aligned
+         */
+        let left = if map { access.left * 2 } else { access.left };
+
+        for _ in 0..left {
+            <IgnoredAny as de::Deserialize>::deserialize(&mut *self)?;
+        }
+
+        Ok(value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let marker = self.marker()?;
+
+        self.visit_marker(marker, visitor)
+    }
+
+    #[inline]
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.reader.peek() == Some(Families::NIL) {
+            self.marker()?;
+
+            return visitor.visit_none();
+        }
+
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        /*
+         * Ext values already come as a newtype struct
+         */
+        if name == EXTENSION {
+            return self.deserialize_any(visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let marker = self.marker()?;
+
+        match marker {
+            /*
+             * Unit variants are just their name
+             */
+            0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+                let variant = self.reader.pull_borrowed_str(marker)?;
+
+                visitor.visit_enum(BorrowedStrDeserializer::new(variant))
+            }
+
+            /*
+             * Everything else is { name: content }
+             */
+            0x80..=0x8f | Families::MAP16 | Families::MAP32 => {
+                if read_map_len(marker, &mut self.reader)? != 1 {
+                    return Err(Error::Message("Enum map must have exactly one entry"));
+                }
+
+                self.nested(|de| visitor.visit_enum(de))
+            }
+
+            _ => Err(Error::UnexpectedMarker(marker)),
+        }
+    }
+
+    #[inline]
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier
+    }
+}
+
+/*
+ * Elements of an array, or entries of a map
+ */
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    left: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    #[inline]
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+
+        self.left -= 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    #[inline]
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+
+        self.left -= 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    #[inline]
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    #[inline]
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self)?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    #[inline]
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    #[inline]
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    #[inline]
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    #[inline]
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+/*
+ * Content of an ext value: its (type, data) pair
+ */
+struct ExtDeserializer<'de> {
+    type_: u8,
+    data: &'de [u8],
+}
+
+impl<'de> de::Deserializer<'de> for ExtDeserializer<'de> {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(ExtAccess {
+            type_: Some(self.type_),
+            data: Some(self.data),
+        })
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ExtAccess<'de> {
+    type_: Option<u8>,
+    data: Option<&'de [u8]>,
+}
+
+impl<'de> de::SeqAccess<'de> for ExtAccess<'de> {
+    type Error = Error;
+
+    #[inline]
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if let Some(type_) = self.type_.take() {
+            return seed
+                .deserialize(IntoDeserializer::<Error>::into_deserializer(type_))
+                .map(Some);
+        }
+
+        match self.data.take() {
+            Some(data) => seed
+                .deserialize(BorrowedBytesDeserializer::new(data))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.type_.is_some() as usize + self.data.is_some() as usize)
+    }
+}
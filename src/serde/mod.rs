@@ -0,0 +1,322 @@
+use alloc::vec::Vec;
+
+use ::serde::{Deserialize, Serialize};
+
+use crate::{error::Result, io::Write};
+
+pub mod de;
+pub mod ser;
+pub mod value;
+
+pub use de::Deserializer;
+pub use ser::Serializer;
+
+/**
+ * @name to_vec
+ * @description
+ *
+ * Serializes a value into a new buffer, structs are written as arrays.
+ *
+ * ```rust
+ * use msgpackp::serde::to_vec;
+ *
+ * assert_eq!(to_vec(&("ch", [1u8])).unwrap(), [0x92, 0xa2, b'c', b'h', 0x91, 0x01]);
+ * ```
+ */
+#[inline]
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut serializer = Serializer::new(Vec::new());
+
+    value.serialize(&mut serializer)?;
+
+    Ok(serializer.into_inner())
+}
+
+/**
+ * @name to_vec_named
+ * @description
+ *
+ * Serializes a value into a new buffer, structs are written as maps keyed by field name.
+ */
+#[inline]
+pub fn to_vec_named<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut serializer = Serializer::new(Vec::new()).with_struct_map();
+
+    value.serialize(&mut serializer)?;
+
+    Ok(serializer.into_inner())
+}
+
+/**
+ * @name to_writer
+ * @description
+ *
+ * Serializes a value into any sink, structs are written as arrays.
+ */
+#[inline]
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<W> {
+    let mut serializer = Serializer::new(writer);
+
+    value.serialize(&mut serializer)?;
+
+    Ok(serializer.into_inner())
+}
+
+/**
+ * @name to_writer_named
+ * @description
+ *
+ * Serializes a value into any sink, structs are written as maps keyed by field name.
+ */
+#[inline]
+pub fn to_writer_named<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<W> {
+    let mut serializer = Serializer::new(writer).with_struct_map();
+
+    value.serialize(&mut serializer)?;
+
+    Ok(serializer.into_inner())
+}
+
+/**
+ * @name from_slice
+ * @description
+ *
+ * Deserializes a value from the whole buffer.
+ *
+ * Structs accept both the array and the map form.
+ */
+#[inline]
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
+    let mut deserializer = Deserializer::from_slice(bytes);
+    let value = T::deserialize(&mut deserializer)?;
+
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, string::String, vec};
+    use serde::{Deserialize, Serialize, de::IgnoredAny};
+
+    use crate::{
+        error::Error,
+        reader::{Limits, Reader},
+        value::Value,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Player<'a> {
+        name: &'a str,
+        #[serde(with = "serde_bytes_compat")]
+        skin: &'a [u8],
+        position: (f32, f32),
+        clan: Option<String>,
+        state: State,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum State {
+        Alive,
+        Dead(u32),
+        Moving { x: i16, y: i16 },
+    }
+
+    /*
+     * serde writes &[u8] as a sequence, this keeps it a bin
+     */
+    mod serde_bytes_compat {
+        use serde::{Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(value)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<&'de [u8], D::Error> {
+            <&[u8]>::deserialize(deserializer)
+        }
+
+        use serde::Deserialize;
+    }
+
+    fn player() -> Player<'static> {
+        Player {
+            name: "0xffabc",
+            skin: &[1, 2, 3],
+            position: (1.5, -2.0),
+            clan: None,
+            state: State::Moving { x: -1, y: 300 },
+        }
+    }
+
+    #[test]
+    fn test_struct_as_array() {
+        let buffer = to_vec(&player()).unwrap();
+
+        assert_eq!(buffer[0], 0x95);
+
+        let mut reader = Reader::new(&buffer[..]);
+        let value = reader.pull_value().unwrap();
+
+        let Value::Array(fields) = value else {
+            panic!("Expected an array");
+        };
+
+        assert_eq!(fields[0], Value::Str("0xffabc"));
+        assert_eq!(fields[1], Value::Bin(&[1, 2, 3]));
+        assert_eq!(fields[3], Value::Nil);
+
+        assert_eq!(from_slice::<Player>(&buffer).unwrap(), player());
+    }
+
+    #[test]
+    fn test_struct_as_map() {
+        let buffer = to_vec_named(&player()).unwrap();
+
+        assert_eq!(&buffer[..6], &[0x85, 0xa4, b'n', b'a', b'm', b'e']);
+
+        let decoded: Player = from_slice(&buffer).unwrap();
+
+        /*
+         * Borrowed fields point into the input
+         */
+        assert_eq!(decoded, player());
+        assert_eq!(decoded.name.as_ptr(), buffer[7..].as_ptr());
+    }
+
+    #[test]
+    fn test_enums() {
+        assert_eq!(
+            to_vec(&State::Alive).unwrap(),
+            [0xa5, b'A', b'l', b'i', b'v', b'e']
+        );
+        assert_eq!(
+            to_vec(&State::Dead(7)).unwrap(),
+            [0x81, 0xa4, b'D', b'e', b'a', b'd', 0x07]
+        );
+
+        for state in [
+            State::Alive,
+            State::Dead(70000),
+            State::Moving { x: 5, y: -5 },
+        ] {
+            assert_eq!(
+                from_slice::<State>(&to_vec(&state).unwrap()).unwrap(),
+                state
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsized_collections() {
+        let map: BTreeMap<&str, Vec<i64>> = [("a", vec![-1, 1 << 40]), ("b", vec![])]
+            .into_iter()
+            .collect();
+
+        /*
+         * Iterators serialize without a length, the header is written afterwards
+         */
+        struct Unsized<'a>(&'a BTreeMap<&'a str, Vec<i64>>);
+
+        impl Serialize for Unsized<'_> {
+            fn serialize<S: ::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> core::result::Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter())
+            }
+        }
+
+        let buffer = to_vec(&Unsized(&map)).unwrap();
+
+        assert_eq!(buffer, to_vec(&map).unwrap());
+        assert_eq!(
+            from_slice::<BTreeMap<&str, Vec<i64>>>(&buffer).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn test_skips_unknown_fields() {
+        #[derive(Serialize)]
+        struct New {
+            a: u8,
+            extra: Vec<u8>,
+            b: u8,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Old {
+            a: u8,
+            b: u8,
+        }
+
+        let buffer = to_vec_named(&(
+            New {
+                a: 1,
+                extra: vec![1, 2],
+                b: 2,
+            },
+            3u8,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            from_slice::<(Old, u8)>(&buffer).unwrap(),
+            (Old { a: 1, b: 2 }, 3)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(from_slice::<u8>(&[]).is_err());
+        assert!(from_slice::<u8>(&[0x01, 0x02]).is_err());
+        assert!(from_slice::<&str>(&[0xa2, 0xff, 0xfe]).is_err());
+        assert!(from_slice::<u8>(&[0xcd, 0x01, 0x00]).is_err());
+        assert!(to_vec(&u128::MAX).is_err());
+    }
+
+    #[test]
+    fn test_depth() {
+        /*
+         * Each 0x91 opens one more array, down to a nil
+         */
+        let nested = |depth: usize| {
+            let mut buffer = vec![0x91; depth];
+
+            buffer.push(0xc0);
+            buffer
+        };
+
+        assert!(from_slice::<IgnoredAny>(&nested(64)).is_ok());
+        assert!(matches!(
+            from_slice::<IgnoredAny>(&nested(65)),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        /*
+         * Way past what the stack could take
+         */
+        assert!(matches!(
+            from_slice::<IgnoredAny>(&nested(500_000)),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        let limits = Limits {
+            max_depth: 2,
+            ..Limits::DEFAULT
+        };
+
+        assert!(matches!(
+            <Vec<Vec<Vec<u8>>>>::deserialize(&mut Deserializer::with_limits(
+                &[0x91, 0x91, 0x91, 0x01],
+                limits
+            )),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+    }
+}
@@ -0,0 +1,526 @@
+use alloc::{string::ToString, vec::Vec};
+use core::fmt::Display;
+
+use ::serde::ser::{self, Serialize};
+
+use crate::{
+    encoder::{Encoder, EncoderConfig},
+    error::{Error, Result},
+    io::Write,
+    msgpack::{array::write_array_header, map::write_map_header},
+    serde::{from_slice, value::EXTENSION},
+};
+
+impl ser::Error for Error {
+    #[cold]
+    fn custom<T: Display>(message: T) -> Self {
+        Error::Custom(message.to_string())
+    }
+}
+
+/**
+ * @name Serializer
+ * @description
+ *
+ * serde data format on top of the Encoder.
+ *
+ * Structs are written as arrays of their fields unless `with_struct_map`
+ * is used, enums are externally tagged: a unit variant is its name,
+ * anything else is a single-entry map `{ name: content }`.
+ *
+ * A newtype struct named `EXTENSION` holding `(type, bytes)` is written as ext.
+ */
+pub struct Serializer<W: Write> {
+    encoder: Encoder<W>,
+    struct_map: bool,
+}
+
+impl<W: Write> Serializer<W> {
+    #[inline(always)]
+    pub fn new(writer: W) -> Self {
+        Serializer::with_config(writer, EncoderConfig::default())
+    }
+
+    /**
+     * @name with_config
+     * @description
+     *
+     * Serializer whose scalars follow an EncoderConfig (e.g. float narrowing).
+     */
+    #[inline(always)]
+    pub fn with_config(writer: W, config: EncoderConfig) -> Self {
+        Serializer {
+            encoder: Encoder::with_config(writer, config),
+            struct_map: false,
+        }
+    }
+
+    /**
+     * @name with_struct_map
+     * @description
+     *
+     * Writes structs as maps keyed by field name instead of arrays.
+     */
+    #[inline(always)]
+    pub fn with_struct_map(mut self) -> Self {
+        self.struct_map = true;
+        self
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> W {
+        self.encoder.into_inner()
+    }
+
+    /*
+     * Serializer for the contents of a container whose length isn't known yet
+     */
+    #[inline]
+    fn buffer(&self) -> Serializer<Vec<u8>> {
+        Serializer {
+            encoder: Encoder::with_config(Vec::new(), *self.encoder.config()),
+            struct_map: self.struct_map,
+        }
+    }
+}
+
+/**
+ * @name Compound
+ * @description
+ *
+ * State of a sequence, tuple, map or struct being serialized.
+ */
+pub struct Compound<'s, W: Write> {
+    ser: &'s mut Serializer<W>,
+
+    /*
+     * Sequences and maps serialized without a length are encoded
+     * into a side buffer first, then written after their header
+     */
+    buffered: Option<(Serializer<Vec<u8>>, usize)>,
+}
+
+impl<'s, W: Write> Compound<'s, W> {
+    #[inline(always)]
+    fn sized(ser: &'s mut Serializer<W>) -> Self {
+        Compound {
+            ser,
+            buffered: None,
+        }
+    }
+
+    #[inline]
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match &mut self.buffered {
+            Some((buffer, count)) => {
+                *count += 1;
+
+                value.serialize(buffer)
+            }
+            None => value.serialize(&mut *self.ser),
+        }
+    }
+
+    #[inline]
+    fn end(self, map: bool) -> Result<()> {
+        let Some((buffer, count)) = self.buffered else {
+            return Ok(());
+        };
+
+        let mut header = Vec::with_capacity(5);
+
+        if map {
+            write_map_header(count / 2, &mut header)?;
+        } else {
+            write_array_header(count, &mut header)?;
+        }
+
+        /*
+         * The header counts as one element of the parent, the buffered
+         * contents were already tracked by the side serializer
+         */
+        self.ser.encoder.write_raw(&header)?;
+        self.ser.encoder.get_mut().write_all(&buffer.into_inner())
+    }
+}
+
+impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'s, W>;
+    type SerializeTuple = Compound<'s, W>;
+    type SerializeTupleStruct = Compound<'s, W>;
+    type SerializeTupleVariant = Compound<'s, W>;
+    type SerializeMap = Compound<'s, W>;
+    type SerializeStruct = Compound<'s, W>;
+    type SerializeStructVariant = Compound<'s, W>;
+
+    #[inline]
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.encoder.write_bool(value)
+    }
+
+    #[inline]
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.encoder.write_int(value as i64)
+    }
+
+    #[inline]
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.encoder.write_int(value as i64)
+    }
+
+    #[inline]
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.encoder.write_int(value as i64)
+    }
+
+    #[inline]
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.encoder.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        if let Ok(value) = i64::try_from(value) {
+            return self.encoder.write_int(value);
+        }
+
+        self.serialize_u128(u128::try_from(value).map_err(|_| Error::LengthOverflow("i128"))?)
+    }
+
+    #[inline]
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.encoder.write_uint(value as u64)
+    }
+
+    #[inline]
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.encoder.write_uint(value as u64)
+    }
+
+    #[inline]
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.encoder.write_uint(value as u64)
+    }
+
+    #[inline]
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.encoder.write_uint(value)
+    }
+
+    #[inline]
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.encoder
+            .write_uint(u64::try_from(value).map_err(|_| Error::LengthOverflow("u128"))?)
+    }
+
+    #[inline]
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        self.encoder.write_f32(value)
+    }
+
+    #[inline]
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        self.encoder.write_f64(value)
+    }
+
+    #[inline]
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.encoder.write_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.encoder.write_str(value)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        self.encoder.write_bin(value)
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<()> {
+        self.encoder.write_nil()
+    }
+
+    #[inline]
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<()> {
+        self.encoder.write_nil()
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.encoder.write_nil()
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.encoder.write_str(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if name == EXTENSION {
+            /*
+             * The (type, bytes) content goes through a side buffer and is
+             * read back, so any Serialize shaped like it becomes an ext
+             */
+            let mut buffer = self.buffer();
+
+            value.serialize(&mut buffer)?;
+
+            let content = buffer.into_inner();
+            let (type_, data): (u8, &[u8]) = from_slice(&content)?;
+
+            return self.encoder.write_ext(type_, data);
+        }
+
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.encoder.begin_map(1)?;
+        self.encoder.write_str(variant)?;
+
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        match len {
+            Some(len) => {
+                self.encoder.begin_array(len)?;
+
+                Ok(Compound::sized(self))
+            }
+            None => Ok(Compound {
+                buffered: Some((self.buffer(), 0)),
+                ser: self,
+            }),
+        }
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.encoder.begin_array(len)?;
+
+        Ok(Compound::sized(self))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.encoder.begin_array(len)?;
+
+        Ok(Compound::sized(self))
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.encoder.begin_map(1)?;
+        self.encoder.write_str(variant)?;
+        self.encoder.begin_array(len)?;
+
+        Ok(Compound::sized(self))
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        match len {
+            Some(len) => {
+                self.encoder.begin_map(len)?;
+
+                Ok(Compound::sized(self))
+            }
+            None => Ok(Compound {
+                buffered: Some((self.buffer(), 0)),
+                ser: self,
+            }),
+        }
+    }
+
+    #[inline]
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        if self.struct_map {
+            self.encoder.begin_map(len)?;
+        } else {
+            self.encoder.begin_array(len)?;
+        }
+
+        Ok(Compound::sized(self))
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.encoder.begin_map(1)?;
+        self.encoder.write_str(variant)?;
+
+        ser::Serializer::serialize_struct(self, name, len)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, false)
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, false)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, false)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, false)
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.element(key)
+    }
+
+    #[inline]
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, true)
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if self.ser.struct_map {
+            self.ser.encoder.write_str(key)?;
+        }
+
+        self.element(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, false)
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Compound::end(self, false)
+    }
+}
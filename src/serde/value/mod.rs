@@ -0,0 +1,452 @@
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::fmt;
+
+use ::serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+use ordered_float::OrderedFloat;
+
+use crate::{
+    msgpack::ext::Extension,
+    value::{OwnedValue, Value},
+};
+
+/**
+ * @name EXTENSION
+ * @description
+ *
+ * Name of the newtype struct that carries an ext value through serde,
+ * its content is a `(type, bytes)` tuple.
+ *
+ * Same name as rmp-serde uses, so ext values survive a trip through either crate.
+ */
+pub const EXTENSION: &str = "_ExtStruct";
+
+/*
+ * serde writes &[u8] as a sequence, this keeps ext payloads bytes
+ */
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    #[inline(always)]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/*
+ * Owned bytes from any format: bytes, or a sequence of u8 for formats
+ * (like JSON) that write bytes as arrays
+ */
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteBufVisitor;
+
+        impl<'de> Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(value.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(value))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ByteBuf, A::Error> {
+                let mut bytes = Vec::new();
+
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+
+                Ok(ByteBuf(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+impl Serialize for Extension {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(EXTENSION, &(self.type_(), Bytes(self.data())))
+    }
+}
+
+struct ExtensionVisitor;
+
+impl<'de> Visitor<'de> for ExtensionVisitor {
+    type Value = Extension;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an ext value")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Extension, D::Error> {
+        let (type_, ByteBuf(data)) = <(u8, ByteBuf)>::deserialize(deserializer)?;
+
+        Ok(Extension::new(type_, data))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Extension, A::Error> {
+        let type_ = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let ByteBuf(data) = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+        Ok(Extension::new(type_, data))
+    }
+}
+
+impl<'de> Deserialize<'de> for Extension {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(EXTENSION, ExtensionVisitor)
+    }
+}
+
+impl Serialize for Value<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::U8(value) => serializer.serialize_u8(*value),
+            Value::U16(value) => serializer.serialize_u16(*value),
+            Value::U32(value) => serializer.serialize_u32(*value),
+            Value::U64(value) => serializer.serialize_u64(*value),
+            Value::F32(value) => serializer.serialize_f32(value.0),
+            Value::F64(value) => serializer.serialize_f64(value.0),
+            Value::I8(value) => serializer.serialize_i8(*value),
+            Value::I16(value) => serializer.serialize_i16(*value),
+            Value::I32(value) => serializer.serialize_i32(*value),
+            Value::I64(value) => serializer.serialize_i64(*value),
+            Value::Str(value) => serializer.serialize_str(value),
+            Value::Bin(value) => serializer.serialize_bytes(value),
+            Value::Array(values) => serializer.collect_seq(values.iter()),
+            Value::Map(values) => {
+                serializer.collect_map(values.iter().map(|(key, value)| (key, value)))
+            }
+            Value::Extension(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for OwnedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OwnedValue::Nil => serializer.serialize_unit(),
+            OwnedValue::Bool(value) => serializer.serialize_bool(*value),
+            OwnedValue::U8(value) => serializer.serialize_u8(*value),
+            OwnedValue::U16(value) => serializer.serialize_u16(*value),
+            OwnedValue::U32(value) => serializer.serialize_u32(*value),
+            OwnedValue::U64(value) => serializer.serialize_u64(*value),
+            OwnedValue::F32(value) => serializer.serialize_f32(value.0),
+            OwnedValue::F64(value) => serializer.serialize_f64(value.0),
+            OwnedValue::I8(value) => serializer.serialize_i8(*value),
+            OwnedValue::I16(value) => serializer.serialize_i16(*value),
+            OwnedValue::I32(value) => serializer.serialize_i32(*value),
+            OwnedValue::I64(value) => serializer.serialize_i64(*value),
+            OwnedValue::Str(value) => serializer.serialize_str(value),
+            OwnedValue::Bin(value) => serializer.serialize_bytes(value),
+            OwnedValue::Array(values) => serializer.collect_seq(values),
+            OwnedValue::Map(values) => {
+                serializer.collect_map(values.iter().map(|(key, value)| (key, value)))
+            }
+            OwnedValue::Extension(value) => value.serialize(serializer),
+        }
+    }
+}
+
+/*
+ * Scalars map the same way for both visitors: every integer keeps the
+ * width the format reported it with
+ */
+macro_rules! visit_scalars {
+    ($value:ident) => {
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok($value::Nil)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok($value::Nil)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            Deserialize::deserialize(deserializer)
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+            Ok($value::Bool(value))
+        }
+
+        fn visit_u8<E: de::Error>(self, value: u8) -> Result<Self::Value, E> {
+            Ok($value::U8(value))
+        }
+
+        fn visit_u16<E: de::Error>(self, value: u16) -> Result<Self::Value, E> {
+            Ok($value::U16(value))
+        }
+
+        fn visit_u32<E: de::Error>(self, value: u32) -> Result<Self::Value, E> {
+            Ok($value::U32(value))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok($value::U64(value))
+        }
+
+        fn visit_i8<E: de::Error>(self, value: i8) -> Result<Self::Value, E> {
+            Ok($value::I8(value))
+        }
+
+        fn visit_i16<E: de::Error>(self, value: i16) -> Result<Self::Value, E> {
+            Ok($value::I16(value))
+        }
+
+        fn visit_i32<E: de::Error>(self, value: i32) -> Result<Self::Value, E> {
+            Ok($value::I32(value))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            Ok($value::I64(value))
+        }
+
+        fn visit_f32<E: de::Error>(self, value: f32) -> Result<Self::Value, E> {
+            Ok($value::F32(OrderedFloat(value)))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+            Ok($value::F64(OrderedFloat(value)))
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            Ok($value::Extension(
+                ExtensionVisitor.visit_newtype_struct(deserializer)?,
+            ))
+        }
+    };
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value whose strings and bytes borrow the input")
+    }
+
+    visit_scalars!(Value);
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Value<'de>, E> {
+        Ok(Value::Str(value))
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, value: &'de [u8]) -> Result<Value<'de>, E> {
+        Ok(Value::Bin(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value<'de>, A::Error> {
+        /*
+         * No preallocation: size hints come from the wire
+         */
+        let mut values = Vec::new();
+
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        Ok(Value::Array(values.into_boxed_slice()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value<'de>, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(entry) = map.next_entry()? {
+            values.push(entry);
+        }
+
+        Ok(Value::Map(values.into_boxed_slice()))
+    }
+}
+
+/**
+ * Value borrows its strings and binaries, so it can only be deserialized
+ * from formats that lend them (msgpack, or JSON without escapes):
+ * use OwnedValue for everything else.
+ */
+impl<'de> Deserialize<'de> for Value<'de> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct OwnedValueVisitor;
+
+impl<'de> Visitor<'de> for OwnedValueVisitor {
+    type Value = OwnedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    visit_scalars!(OwnedValue);
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::Str(value.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::Str(value))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::Bin(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::Bin(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<OwnedValue, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        Ok(OwnedValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OwnedValue, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(entry) = map.next_entry()? {
+            values.push(entry);
+        }
+
+        Ok(OwnedValue::Map(values))
+    }
+}
+
+impl<'de> Deserialize<'de> for OwnedValue {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OwnedValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec};
+
+    use crate::{
+        msgpack::WriteTo,
+        reader::Reader,
+        serde::{from_slice, to_vec},
+    };
+
+    fn packet() -> Box<[u8]> {
+        let value = Value::Array(
+            vec![
+                Value::Str("ch"),
+                Value::U16(300),
+                Value::I8(-3),
+                Value::F32(OrderedFloat(0.5)),
+                Value::Bin(&[1, 2]),
+                Value::Extension(Extension::new(5, vec![9, 9, 9, 9])),
+                Value::Map(vec![(Value::Str("nil"), Value::Nil)].into_boxed_slice()),
+            ]
+            .into_boxed_slice(),
+        );
+
+        let mut buffer = Vec::new();
+
+        value.write_to(&mut buffer).unwrap();
+
+        buffer.into_boxed_slice()
+    }
+
+    #[test]
+    fn test_value_roundtrip() {
+        let buffer = packet();
+
+        let mut reader = Reader::new(&buffer[..]);
+        let expected = reader.pull_value().unwrap();
+        let value: Value = from_slice(&buffer).unwrap();
+
+        /*
+         * Widths, bin and ext are kept, so the re-encoding is identical
+         */
+        assert_eq!(value, expected);
+        assert_eq!(to_vec(&value).unwrap(), &buffer[..]);
+
+        let owned: OwnedValue = from_slice(&buffer).unwrap();
+
+        assert_eq!(owned, OwnedValue::from(&value));
+        assert_eq!(owned.as_value(), value);
+        assert_eq!(to_vec(&owned).unwrap(), &buffer[..]);
+    }
+
+    #[test]
+    fn test_value_json() {
+        let buffer = packet();
+        let value: Value = from_slice(&buffer).unwrap();
+
+        let json = serde_json::to_string(&value).unwrap();
+
+        assert_eq!(
+            json,
+            r#"["ch",300,-3,0.5,[1,2],[5,[9,9,9,9]],{"nil":null}]"#
+        );
+
+        /*
+         * JSON has neither bin nor ext, they come back as arrays
+         */
+        let owned: OwnedValue = serde_json::from_str(&json).unwrap();
+        let OwnedValue::Array(values) = &owned else {
+            panic!("Expected an array");
+        };
+
+        assert_eq!(values[0], OwnedValue::Str("ch".to_owned()));
+        assert_eq!(values[1], OwnedValue::U64(300));
+        assert_eq!(values[2], OwnedValue::I64(-3));
+
+        let borrowed: Value = serde_json::from_str(r#"{"a":[true,null]}"#).unwrap();
+
+        assert_eq!(
+            borrowed,
+            Value::Map(
+                vec![(
+                    Value::Str("a"),
+                    Value::Array(vec![Value::Bool(true), Value::Nil].into_boxed_slice())
+                )]
+                .into_boxed_slice()
+            )
+        );
+
+        assert!(serde_json::from_str::<Value>(r#""escaped\n""#).is_err());
+    }
+}
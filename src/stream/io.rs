@@ -0,0 +1,168 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use std::io::{ErrorKind, Read};
+
+use crate::error::{Error, Result};
+use crate::stream::{Decoded, Decoder};
+use crate::value::OwnedValue;
+
+/**
+ * @name IoReader
+ * @description
+ *
+ * Reads msgpack values out of a std::io::Read (a TcpStream, a file...).
+ *
+ * The source is read in chunks of the configured capacity and fed to a
+ * Decoder, so values may be split across reads. Values are handed out
+ * owned, as the chunks they came from get reused.
+ */
+pub struct IoReader<R: Read> {
+    read: R,
+    decoder: Decoder,
+    chunk: Box<[u8]>,
+}
+
+impl<R: Read> IoReader<R> {
+    /**
+     * @name new
+     * @description
+     *
+     * Creates a reader that pulls 8 KiB from the source at a time.
+     */
+    #[inline]
+    pub fn new(read: R) -> Self {
+        IoReader::with_capacity(8 * 1024, read)
+    }
+
+    /**
+     * @name with_capacity
+     * @description
+     *
+     * Creates a reader that pulls up to `capacity` bytes from the source
+     * at a time.
+     */
+    #[inline]
+    pub fn with_capacity(capacity: usize, read: R) -> Self {
+        IoReader::with_decoder(Decoder::with_capacity(capacity), capacity, read)
+    }
+
+    /**
+     * @name with_decoder
+     * @description
+     *
+     * Creates a reader that feeds a configured Decoder (e.g. with
+     * tighter limits), pulling up to `capacity` bytes at a time.
+     */
+    #[inline]
+    pub fn with_decoder(decoder: Decoder, capacity: usize, read: R) -> Self {
+        IoReader {
+            read,
+            decoder,
+            chunk: vec![0; capacity.max(1)].into_boxed_slice(),
+        }
+    }
+
+    /**
+     * @name read_value
+     * @description
+     *
+     * Reads the next value, or None once the source ended cleanly
+     * between two values.
+     *
+     * A source that ends inside a value is an `UnexpectedEof` error.
+     */
+    pub fn read_value(&mut self) -> Result<Option<OwnedValue>> {
+        loop {
+            if let Decoded::Value(value) = self.decoder.decode()? {
+                return Ok(Some(OwnedValue::from(value)));
+            }
+
+            let read = loop {
+                match self.read.read(&mut self.chunk) {
+                    Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+
+            if read == 0 {
+                return match self.decoder.buffered() {
+                    0 => Ok(None),
+                    _ => Err(Error::UnexpectedEof("stream")),
+                };
+            }
+
+            self.decoder.feed(&self.chunk[..read]);
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_ref(&self) -> &R {
+        &self.read
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R: Read> Iterator for IoReader<R> {
+    type Item = Result<OwnedValue>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_value().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use crate::msgpack::WriteTo;
+
+    #[test]
+    fn test_io_reader() {
+        let mut buffer = Vec::new();
+
+        for index in 0..50u16 {
+            (String::from("ch"), index).write_to(&mut buffer).unwrap();
+        }
+
+        /*
+         * A tiny capacity splits nearly every value
+         */
+        let reader = IoReader::with_capacity(3, &buffer[..]);
+        let values = reader.collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(values.len(), 50);
+        assert_eq!(
+            values[42],
+            OwnedValue::Array(vec![OwnedValue::Str("ch".into()), OwnedValue::U16(42)])
+        );
+
+        let mut reader = IoReader::new(&buffer[..buffer.len() - 1]);
+
+        assert_eq!(reader.by_ref().take(49).count(), 49);
+        assert!(matches!(
+            reader.read_value(),
+            Err(Error::UnexpectedEof("stream"))
+        ));
+
+        /*
+         * Tighter limits reach the decoder: no arrays at all
+         */
+        let limits = crate::reader::Limits {
+            max_depth: 0,
+            ..Default::default()
+        };
+        let mut reader = IoReader::with_decoder(Decoder::with_limits(limits), 64, &buffer[..]);
+
+        assert!(matches!(
+            reader.read_value(),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+    }
+}
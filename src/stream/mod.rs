@@ -0,0 +1,394 @@
+use alloc::vec::Vec;
+
+use crate::error::{Error, Result};
+use crate::reader::{Limits, Reader, Scan, scan_owing};
+use crate::value::Value;
+
+#[cfg(feature = "std")]
+mod io;
+mod resync;
+
+#[cfg(feature = "std")]
+pub use io::IoReader;
+pub use resync::{Boundary, Recovered, Resync};
+
+/**
+ * @name Decoded
+ * @description
+ *
+ * Result of feeding a Decoder: either a whole value, or how many more
+ * bytes it needs at least before one can be decoded.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded<'a> {
+    Value(Value<'a>),
+    NeedMore(usize),
+}
+
+/**
+ * @name Decoder
+ * @description
+ *
+ * Incremental decoder for messages split across websocket or TCP
+ * frames.
+ *
+ * Chunks are buffered with `feed`, and `decode` hands out a value once
+ * all of its bytes arrived. Partial values are never decoded: the
+ * buffer is scanned first, and the scan isn't redone until at least
+ * as many bytes as it asked for came in.
+ *
+ * A message may take at most `max_len` bytes (16 MiB by default), so a
+ * peer can't make the buffer grow without bounds, and has to be within
+ * the decoder's limits (nesting depth included).
+ *
+ * Values borrow the decoder's buffer. Decoded bytes are only dropped by
+ * `feed`, once they make up half of the buffer, so a chunk holding many
+ * messages isn't shifted once per message.
+ */
+#[derive(Debug)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    max_len: usize,
+    limits: Limits,
+
+    /*
+     * Offset of the first byte not decoded yet
+     */
+    start: usize,
+
+    /*
+     * Bytes past `start` the last incomplete scan asked for
+     */
+    needed: usize,
+}
+
+impl Default for Decoder {
+    #[inline(always)]
+    fn default() -> Self {
+        Decoder::with_max_len(Decoder::DEFAULT_MAX_LEN)
+    }
+}
+
+impl Decoder {
+    pub const DEFAULT_MAX_LEN: usize = 16 * 1024 * 1024;
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Decoder::default()
+    }
+
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Decoder {
+            buffer: Vec::with_capacity(capacity),
+            ..Decoder::default()
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_max_len(max_len: usize) -> Self {
+        Decoder {
+            buffer: Vec::new(),
+            max_len,
+            limits: Limits::DEFAULT,
+            start: 0,
+            needed: 0,
+        }
+    }
+
+    /**
+     * @name with_limits
+     * @description
+     *
+     * Creates a decoder that scans and decodes messages with custom
+     * limits.
+     */
+    #[inline(always)]
+    pub fn with_limits(limits: Limits) -> Self {
+        Decoder {
+            limits,
+            ..Decoder::default()
+        }
+    }
+
+    #[inline(always)]
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    #[inline(always)]
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /**
+     * @name feed
+     * @description
+     *
+     * Appends the next chunk of the stream.
+     */
+    #[inline]
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.start > 0 && self.start >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /**
+     * @name decode
+     * @description
+     *
+     * Decodes the next value if all of its bytes are buffered.
+     *
+     * Malformed input is an error, and so is a message longer than
+     * `max_len`. After that the decoder should be cleared: there's no
+     * telling where the next message starts. Use a Resync instead to
+     * skip to the next plausible one.
+     */
+    pub fn decode(&mut self) -> Result<Decoded<'_>> {
+        let pending = &self.buffer[self.start..];
+
+        if pending.len() < self.needed {
+            return Ok(Decoded::NeedMore(self.needed - pending.len()));
+        }
+
+        match scan_owing(pending, &self.limits)? {
+            (Scan::Incomplete(missing), owed) => {
+                /*
+                 * Every value still owed takes a byte at least
+                 */
+                if pending.len().saturating_add(missing).saturating_add(owed) > self.max_len {
+                    return Err(Error::LimitExceeded("Message"));
+                }
+
+                self.needed = pending.len() + missing;
+
+                Ok(Decoded::NeedMore(missing))
+            }
+            (Scan::Complete(len), _) if len > self.max_len => Err(Error::LimitExceeded("Message")),
+            (Scan::Complete(len), _) => {
+                let start = self.start;
+
+                self.needed = 0;
+                self.start += len;
+
+                Reader::with_limits(&self.buffer[start..start + len], self.limits)
+                    .pull_value()
+                    .map(Decoded::Value)
+            }
+        }
+    }
+
+    /**
+     * @name buffered
+     * @description
+     *
+     * Bytes fed but not decoded yet.
+     */
+    #[inline(always)]
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /**
+     * @name clear
+     * @description
+     *
+     * Drops everything buffered, e.g. after a malformed message.
+     */
+    #[inline]
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.start = 0;
+        self.needed = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use ordered_float::OrderedFloat;
+
+    const PACKET: [u8; 21] = [
+        146, 162, 99, 104, 147, 165, 72, 101, 108, 108, 111, 1, 203, 63, 244, 204, 204, 204, 204,
+        204, 205,
+    ];
+
+    fn packet() -> Value<'static> {
+        Value::Array(
+            vec![
+                Value::Str("ch"),
+                Value::Array(
+                    vec![
+                        Value::Str("Hello"),
+                        Value::U8(1),
+                        Value::F64(OrderedFloat(1.3)),
+                    ]
+                    .into_boxed_slice(),
+                ),
+            ]
+            .into_boxed_slice(),
+        )
+    }
+
+    #[test]
+    fn test_byte_by_byte() {
+        let mut decoder = Decoder::new();
+
+        for (index, byte) in PACKET.iter().enumerate() {
+            decoder.feed(&[*byte]);
+
+            match decoder.decode().unwrap() {
+                Decoded::NeedMore(missing) => {
+                    assert!(index + 1 < PACKET.len());
+                    assert!(missing >= 1 && index + 1 + missing <= PACKET.len());
+                }
+                Decoded::Value(value) => {
+                    assert_eq!(index + 1, PACKET.len());
+                    assert_eq!(value, packet());
+                }
+            }
+        }
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(1));
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_split_frames() {
+        let mut decoder = Decoder::new();
+
+        /*
+         * One and a half packets, then the rest
+         */
+        decoder.feed(&PACKET);
+        decoder.feed(&PACKET[..10]);
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::Value(packet()));
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(1));
+
+        decoder.feed(&PACKET[10..]);
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::Value(packet()));
+    }
+
+    #[test]
+    fn test_many_per_chunk() {
+        let mut decoder = Decoder::new();
+        let chunk = PACKET.repeat(1000);
+
+        decoder.feed(&chunk);
+
+        for _ in 0..1000 {
+            assert_eq!(decoder.decode().unwrap(), Decoded::Value(packet()));
+        }
+
+        assert_eq!(decoder.buffered(), 0);
+
+        /*
+         * The decoded bytes are dropped on the next feed
+         */
+        decoder.feed(&PACKET[..3]);
+
+        assert_eq!(decoder.buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_max_len() {
+        let mut decoder = Decoder::with_max_len(PACKET.len());
+
+        decoder.feed(&PACKET);
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::Value(packet()));
+
+        /*
+         * A str 32 claiming 4 GiB is refused from its header alone
+         */
+        decoder.feed(&[0xdb, 0xff, 0xff, 0xff, 0xff]);
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(Error::LimitExceeded("Message"))
+        ));
+
+        /*
+         * So is an array whose elements don't fit, one byte each
+         */
+        let mut decoder = Decoder::with_max_len(8);
+
+        decoder.feed(&[0xdc, 0x00, 0x10, 0x01]);
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(Error::LimitExceeded("Message"))
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        /*
+         * Well under max_len, but far deeper than the stack allows
+         */
+        let mut buffer = vec![0x91; 1_000_000];
+
+        buffer.push(0xc0);
+
+        let mut decoder = Decoder::new();
+
+        decoder.feed(&buffer);
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        let mut decoder = Decoder::with_limits(Limits {
+            max_depth: 1,
+            ..Limits::DEFAULT
+        });
+
+        decoder.feed(&[0x91, 0x01, 0x91, 0x91, 0x01]);
+
+        assert_eq!(
+            decoder.decode().unwrap(),
+            Decoded::Value(Value::Array(vec![Value::U8(1)].into_boxed_slice()))
+        );
+        assert!(matches!(
+            decoder.decode(),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+
+        let mut decoder = Decoder::with_limits(Limits {
+            max_map_len: 0,
+            ..Limits::DEFAULT
+        });
+
+        decoder.feed(&[0x81, 0x01]);
+
+        assert!(matches!(decoder.decode(), Err(Error::LimitExceeded("Map"))));
+    }
+
+    #[test]
+    fn test_header_split() {
+        let mut decoder = Decoder::new();
+
+        /*
+         * str 16 whose length is cut in half
+         */
+        decoder.feed(&[0xda, 0x00]);
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(1));
+
+        decoder.feed(&[0x02]);
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::NeedMore(2));
+
+        decoder.feed(b"hi");
+
+        assert_eq!(decoder.decode().unwrap(), Decoded::Value(Value::Str("hi")));
+    }
+}
@@ -0,0 +1,568 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{
+    error::Error,
+    lazy::LazyValue,
+    reader::{Event, Limits, Reader, Scan, scan_owing},
+    value::Value,
+};
+
+/**
+ * @name Boundary
+ * @description
+ *
+ * What a message looks like, so a plausible start can be told apart
+ * from garbage.
+ */
+#[derive(Debug, Clone, Copy)]
+pub enum Boundary {
+    /*
+     * Each message is preceded by its length as a big endian u32, and
+     * holds exactly one value
+     */
+    LengthPrefixed,
+
+    /*
+     * Messages are values back to back, and every one of them passes
+     * the check (e.g. "an array of two whose first element is a str")
+     */
+    Shape(fn(LazyValue<'_>) -> bool),
+}
+
+/**
+ * @name Recovered
+ * @description
+ *
+ * Result of `Resync::next`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovered<'a> {
+    /*
+     * The next message
+     */
+    Value(Value<'a>),
+
+    /*
+     * Stream offsets of bytes that were dropped as corrupt, reported
+     * right before the message that follows them
+     */
+    Skipped(Range<u64>),
+
+    /*
+     * At least that many more bytes are needed
+     */
+    NeedMore(usize),
+}
+
+enum Candidate {
+    Valid { start: usize, len: usize },
+
+    /*
+     * Nothing can start before that many bytes
+     */
+    Invalid(usize),
+
+    Incomplete(usize),
+}
+
+enum Walked {
+    Complete(usize),
+
+    /*
+     * The bytes end inside the value
+     */
+    Truncated,
+
+    /*
+     * Malformed, or over the limits, from that offset on
+     */
+    Bad(usize),
+}
+
+/**
+ * @name Resync
+ * @description
+ *
+ * Incremental decoder that survives corrupt input.
+ *
+ * Where the plain Decoder has to be cleared after a malformed message,
+ * Resync drops bytes until a plausible message starts again,
+ * as told by the Boundary and by `max_len`. A message claiming to be
+ * longer than `max_len` is garbage too, so a corrupt length can't stall
+ * the stream. Strings have to be UTF-8 for a message to be valid, and
+ * nesting within the limits.
+ *
+ * A candidate is never walked past `max_len` or its first bad token,
+ * and the search resumes from that token rather than the next byte,
+ * so a long corrupt run isn't rescanned over and over. A message
+ * hiding inside such a broken candidate is skipped along with it.
+ *
+ * ```
+ * use msgpackp::stream::{Boundary, Recovered, Resync};
+ *
+ * let mut resync = Resync::new(Boundary::LengthPrefixed, 1024);
+ *
+ * resync.feed(&[0xde, 0xad, 0, 0, 0, 1, 0x2a]);
+ *
+ * assert_eq!(resync.next(), Recovered::Skipped(0..2));
+ * assert!(matches!(resync.next(), Recovered::Value(_)));
+ * ```
+ */
+#[derive(Debug)]
+pub struct Resync {
+    boundary: Boundary,
+    max_len: usize,
+    limits: Limits,
+    buffer: Vec<u8>,
+
+    /*
+     * Bytes at the front of the buffer that were handed out or skipped
+     */
+    consumed: usize,
+
+    /*
+     * Stream offset of the first byte of the buffer
+     */
+    offset: u64,
+
+    /*
+     * Stream offset where the skipped region being built started
+     */
+    skipped_from: Option<u64>,
+}
+
+impl Resync {
+    #[inline]
+    pub fn new(boundary: Boundary, max_len: usize) -> Self {
+        Resync::with_limits(boundary, max_len, Limits::DEFAULT)
+    }
+
+    #[inline]
+    pub fn with_limits(boundary: Boundary, max_len: usize, limits: Limits) -> Self {
+        Resync {
+            boundary,
+            max_len,
+            limits,
+            buffer: Vec::new(),
+            consumed: 0,
+            offset: 0,
+            skipped_from: None,
+        }
+    }
+
+    /**
+     * @name feed
+     * @description
+     *
+     * Appends the next chunk of the stream.
+     */
+    #[inline]
+    pub fn feed(&mut self, chunk: &[u8]) {
+        /*
+         * Dropping what was consumed once per message would shift the
+         * buffer over and over, so wait until it's half of it
+         */
+        if self.consumed > 0 && self.consumed >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.consumed);
+            self.offset += self.consumed as u64;
+            self.consumed = 0;
+        }
+
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /**
+     * @name next
+     * @description
+     *
+     * Decodes the next message, or reports the corrupt bytes in front
+     * of it. Never fails: whatever can't be decoded is skipped.
+     */
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Recovered<'_> {
+        loop {
+            let at = self.consumed;
+
+            match self.check(&self.buffer[at..]) {
+                Candidate::Incomplete(missing) => return Recovered::NeedMore(missing),
+
+                Candidate::Invalid(skip) => {
+                    self.skipped_from.get_or_insert(self.position(at));
+                    self.consumed += skip.max(1);
+                }
+
+                Candidate::Valid { start, len } => {
+                    if let Some(from) = self.skipped_from.take() {
+                        return Recovered::Skipped(from..self.position(at));
+                    }
+
+                    self.consumed += start + len;
+
+                    let value = Reader::with_limits(&self.buffer[at + start..][..len], self.limits)
+                        .pull_value();
+
+                    /*
+                     * The frame was validated strictly, strings and depth
+                     * included, so this is only a fallback
+                     */
+                    return match value {
+                        Ok(value) => Recovered::Value(value),
+                        Err(_) => {
+                            Recovered::Skipped(self.position(at)..self.position(self.consumed))
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    /**
+     * @name finish
+     * @description
+     *
+     * Ends the stream: the bytes still buffered, and any region being
+     * skipped, can't become a message anymore.
+     */
+    pub fn finish(&mut self) -> Option<Range<u64>> {
+        let from = self
+            .skipped_from
+            .take()
+            .unwrap_or(self.position(self.consumed));
+        let to = self.position(self.buffer.len());
+
+        self.offset = to;
+        self.consumed = 0;
+        self.buffer.clear();
+
+        (from < to).then_some(from..to)
+    }
+
+    /**
+     * @name buffered
+     * @description
+     *
+     * Bytes fed but not decoded or skipped yet.
+     */
+    #[inline(always)]
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.consumed
+    }
+
+    #[inline(always)]
+    fn position(&self, index: usize) -> u64 {
+        self.offset + index as u64
+    }
+
+    /*
+     * Whether a message plausibly starts at the front of `bytes`
+     */
+    fn check(&self, bytes: &[u8]) -> Candidate {
+        match self.boundary {
+            Boundary::LengthPrefixed => {
+                let Some(prefix) = bytes.get(..4) else {
+                    return Candidate::Incomplete(4 - bytes.len());
+                };
+
+                let len = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+
+                if len > self.max_len {
+                    return Candidate::Invalid(1);
+                }
+
+                match bytes.get(4..4 + len) {
+                    None => Candidate::Incomplete(4 + len - bytes.len()),
+                    Some(frame) => match self.walk(frame) {
+                        Walked::Complete(valid) if valid == len => {
+                            Candidate::Valid { start: 4, len }
+                        }
+                        Walked::Bad(bad) => Candidate::Invalid(4 + bad),
+                        _ => Candidate::Invalid(1),
+                    },
+                }
+            }
+
+            Boundary::Shape(check) => {
+                /*
+                 * A longer message is garbage anyway, so there's no need
+                 * to look further
+                 */
+                let window = &bytes[..bytes.len().min(self.max_len)];
+
+                match self.walk(window) {
+                    Walked::Bad(bad) => Candidate::Invalid(bad),
+                    Walked::Complete(len) => match check(LazyValue::new(&bytes[..len])) {
+                        true => Candidate::Valid { start: 0, len },
+                        false => Candidate::Invalid(1),
+                    },
+                    Walked::Truncated => match scan_owing(window, &self.limits) {
+                        Ok((Scan::Incomplete(missing), owed))
+                            if bytes.len().saturating_add(missing).saturating_add(owed)
+                                <= self.max_len =>
+                        {
+                            Candidate::Incomplete(missing)
+                        }
+                        _ => Candidate::Invalid(1),
+                    },
+                }
+            }
+        }
+    }
+
+    /*
+     * Walks the value at the front of `bytes` token by token. Unlike a
+     * scan, strings have to be UTF-8 and nesting within the limits, or
+     * decoding would turn them into empty strings or fail. Stopping at
+     * the first bad token also keeps broken candidates cheap
+     */
+    fn walk(&self, bytes: &[u8]) -> Walked {
+        let mut reader = Reader::with_limits(bytes, self.limits);
+
+        /*
+         * Elements each open container still owes
+         */
+        let mut owed: Vec<usize> = Vec::new();
+
+        loop {
+            let start = reader.position();
+
+            let children = match reader.next_event() {
+                Ok(None) | Err(Error::UnexpectedEof(_)) => return Walked::Truncated,
+                Err(_) => return Walked::Bad(start),
+                Ok(Some(Event::ArrayStart(len))) => Some(len),
+                Ok(Some(Event::MapStart(len))) => Some(len * 2),
+                Ok(Some(_)) => None,
+            };
+
+            if let Some(left) = owed.last_mut() {
+                *left -= 1;
+            }
+
+            if let Some(children) = children {
+                if owed.len() >= self.limits.max_depth {
+                    return Walked::Bad(start);
+                }
+
+                owed.push(children);
+            }
+
+            while owed.last() == Some(&0) {
+                owed.pop();
+            }
+
+            if owed.is_empty() {
+                return Walked::Complete(reader.position());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::String, vec};
+
+    use crate::msgpack::WriteTo;
+    use crate::reader::Kind;
+
+    /*
+     * [code: str, args: array]
+     */
+    fn packet_shape(value: LazyValue<'_>) -> bool {
+        value.peek_kind().ok() == Some(Kind::Array)
+            && value.len().ok() == Some(2)
+            && value
+                .array_get(0)
+                .ok()
+                .flatten()
+                .is_some_and(|code| code.peek_kind().ok() == Some(Kind::Str))
+            && value
+                .array_get(1)
+                .ok()
+                .flatten()
+                .is_some_and(|args| args.peek_kind().ok() == Some(Kind::Array))
+    }
+
+    fn packet(index: u16) -> Vec<u8> {
+        let mut buffer = Vec::new();
+
+        ("ch", (String::from("hello"), index))
+            .write_to(&mut buffer)
+            .unwrap();
+
+        buffer
+    }
+
+    fn drain(resync: &mut Resync, values: &mut Vec<u16>, skipped: &mut Vec<Range<u64>>) {
+        loop {
+            match resync.next() {
+                Recovered::Value(value) => match value {
+                    Value::Array(items) => match &items[1] {
+                        Value::Array(args) => match args[1] {
+                            Value::U8(index) => values.push(index as u16),
+                            Value::U16(index) => values.push(index),
+                            _ => panic!("unexpected args"),
+                        },
+                        _ => panic!("unexpected packet"),
+                    },
+                    _ => panic!("unexpected packet"),
+                },
+                Recovered::Skipped(range) => skipped.push(range),
+                Recovered::NeedMore(_) => break,
+            }
+        }
+    }
+
+    #[test]
+    fn test_shape() {
+        let mut stream = Vec::new();
+        let mut garbage = Vec::new();
+
+        for index in 0..10u16 {
+            /*
+             * A claimed 4 GB array, a stray byte, and the start of a
+             * packet
+             */
+            let junk: &[u8] = match index {
+                3 => &[0xdd, 0xff, 0xff, 0xff, 0xff],
+                5 => &[0xc1, 0x07],
+                7 => &packet(99)[..3],
+                _ => &[],
+            };
+
+            if !junk.is_empty() {
+                garbage.push(stream.len() as u64..(stream.len() + junk.len()) as u64);
+                stream.extend_from_slice(junk);
+            }
+
+            stream.extend_from_slice(&packet(index));
+        }
+
+        let mut resync = Resync::new(Boundary::Shape(packet_shape), 256);
+        let mut values = Vec::new();
+        let mut skipped = Vec::new();
+
+        for chunk in stream.chunks(7) {
+            resync.feed(chunk);
+            drain(&mut resync, &mut values, &mut skipped);
+        }
+
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+        assert_eq!(skipped, garbage);
+        assert_eq!(resync.finish(), None);
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let mut stream = Vec::new();
+
+        for index in 0..3u16 {
+            let body = packet(index);
+
+            if index == 1 {
+                /*
+                 * A frame whose body ends before its value does
+                 */
+                stream.extend_from_slice(&[0, 0, 0, 3, 0x93, 0x01, 0x02]);
+            }
+
+            stream.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            stream.extend_from_slice(&body);
+        }
+
+        stream.extend_from_slice(&[0, 0, 0, 9, 0x01]);
+
+        let mut resync = Resync::new(Boundary::LengthPrefixed, 64);
+        let mut values = Vec::new();
+        let mut skipped = Vec::new();
+
+        resync.feed(&stream);
+        drain(&mut resync, &mut values, &mut skipped);
+
+        let first = 4 + packet(0).len() as u64;
+
+        assert_eq!(values, vec![0, 1, 2]);
+        assert_eq!(skipped, vec![first..first + 7]);
+
+        /*
+         * A frame cut off by the end of the stream
+         */
+        assert_eq!(resync.buffered(), 5);
+        assert_eq!(
+            resync.finish(),
+            Some(stream.len() as u64 - 5..stream.len() as u64)
+        );
+        assert_eq!(resync.buffered(), 0);
+    }
+
+    #[test]
+    fn test_deep_and_long_runs() {
+        /*
+         * ["ch", [[[...]]]]: the right shape, far too deep to decode
+         */
+        let mut deep = vec![0x92, 0xa2, b'c', b'h'];
+
+        deep.extend_from_slice(&[0x91; 100_000]);
+        deep.push(0xc0);
+
+        let mut resync = Resync::new(Boundary::Shape(packet_shape), 1 << 20);
+        let mut values = Vec::new();
+        let mut skipped = Vec::new();
+
+        resync.feed(&deep);
+        resync.feed(&packet(1));
+        drain(&mut resync, &mut values, &mut skipped);
+
+        assert_eq!(values, vec![1]);
+        assert_eq!(skipped, vec![0..deep.len() as u64]);
+
+        /*
+         * A megabyte of array headers, each claiming more than max_len
+         * could hold
+         */
+        let junk = [0xdc, 0x00, 0xff].repeat(350_000);
+
+        let mut resync = Resync::new(Boundary::Shape(packet_shape), 256);
+        let mut values = Vec::new();
+        let mut skipped = Vec::new();
+
+        resync.feed(&junk);
+        resync.feed(&packet(2));
+        drain(&mut resync, &mut values, &mut skipped);
+
+        assert_eq!(values, vec![2]);
+        assert_eq!(skipped, vec![0..junk.len() as u64]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        /*
+         * Has the right shape, but its str isn't UTF-8
+         */
+        let bad = [0x92, 0xa2, 0xff, 0xfe, 0x90];
+
+        let mut resync = Resync::new(Boundary::Shape(packet_shape), 256);
+        let mut values = Vec::new();
+        let mut skipped = Vec::new();
+
+        resync.feed(&bad);
+        resync.feed(&packet(1));
+        drain(&mut resync, &mut values, &mut skipped);
+
+        assert_eq!(values, vec![1]);
+        assert_eq!(skipped, vec![0..5]);
+
+        let mut resync = Resync::new(Boundary::LengthPrefixed, 64);
+        let mut values = Vec::new();
+        let mut skipped = Vec::new();
+
+        resync.feed(&[0, 0, 0, 5]);
+        resync.feed(&bad);
+        resync.feed(&[0, 0, 0, packet(2).len() as u8]);
+        resync.feed(&packet(2));
+        drain(&mut resync, &mut values, &mut skipped);
+
+        assert_eq!(values, vec![2]);
+        assert_eq!(skipped, vec![0..9]);
+    }
+}
@@ -0,0 +1,390 @@
+use alloc::vec::Vec;
+
+use crate::error::Result;
+
+use crate::{
+    constants::Families,
+    msgpack::{
+        array::{Array, read_array_len},
+        bin::read_bin_len,
+        ext::read_ext_header,
+        float::{read_f32, read_f64},
+        integer::{read_i8, read_i16, read_i32, read_i64, read_u8, read_u16, read_u32, read_u64},
+        map::{Map, read_map_len},
+        string::read_str_len,
+    },
+    reader::Reader,
+};
+
+mod value_ref;
+
+pub use value_ref::{Elements, Entries, ValueRef};
+
+/*
+ * One value on the tape. Strings, bins and ext data are offsets into the
+ * input, containers are followed by their children and know where their
+ * subtree ends so it can be stepped over.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Node {
+    Nil,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Str { start: usize, len: usize },
+    Bin { start: usize, len: usize },
+    Extension { type_: u8, start: usize, len: usize },
+    Array { len: usize, end: usize },
+    Map { len: usize, end: usize },
+}
+
+/**
+ * @name Tape
+ * @description
+ *
+ * Flat, reusable decoding target.
+ *
+ * A value is decoded in one pass into a list of nodes, children right
+ * after their container, instead of a tree of boxed Values. Keeping one
+ * Tape around and parsing every packet into it costs no allocation once
+ * it grew to fit the biggest packet.
+ *
+ * ```
+ * use msgpackp::tape::Tape;
+ *
+ * let packet = [0x92, 0xa2, b'c', b'h', 0x93, 0xa5, b'H', b'e', b'l', b'l', b'o', 0x01, 0xc3];
+ * let mut tape = Tape::new();
+ *
+ * let root = tape.parse(&packet).unwrap();
+ *
+ * assert_eq!(root.get(0).and_then(|code| code.as_str()), Some("ch"));
+ * assert_eq!(root.get(1).and_then(|args| args.get(1)?.as_u64()), Some(1));
+ * ```
+ */
+#[derive(Debug, Default, Clone)]
+pub struct Tape {
+    nodes: Vec<Node>,
+
+    /*
+     * Containers being filled: their node and how many children they
+     * still owe
+     */
+    open: Vec<(usize, usize)>,
+}
+
+impl Tape {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Tape::default()
+    }
+
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Tape {
+            nodes: Vec::with_capacity(capacity),
+            open: Vec::new(),
+        }
+    }
+
+    /**
+     * @name parse
+     * @description
+     *
+     * Decodes the value at the front of `bytes`, replacing whatever the
+     * tape held. Trailing bytes are left alone.
+     */
+    #[inline]
+    pub fn parse<'a>(&'a mut self, bytes: &'a [u8]) -> Result<ValueRef<'a>> {
+        self.read(&mut Reader::new(bytes))
+    }
+
+    /**
+     * @name read
+     * @description
+     *
+     * Decodes the next value of the reader, honoring its limits.
+     */
+    pub fn read<'a>(&'a mut self, reader: &mut Reader<'a>) -> Result<ValueRef<'a>> {
+        self.nodes.clear();
+        self.open.clear();
+
+        /*
+         * No recursion, so nesting is only bounded by memory: containers
+         * are pushed first and their end is patched in once they got all
+         * of their children
+         */
+        loop {
+            let node = Tape::next_node(reader)?;
+
+            if let Some((_, left)) = self.open.last_mut() {
+                *left -= 1;
+            }
+
+            let at = self.nodes.len();
+
+            self.nodes.push(node);
+
+            match node {
+                Node::Array { len, .. } => self.open.push((at, len)),
+                Node::Map { len, .. } => self.open.push((at, len * 2)),
+                _ => {}
+            }
+
+            while let Some(&(at, 0)) = self.open.last() {
+                let len = self.nodes.len();
+
+                if let Node::Array { end, .. } | Node::Map { end, .. } = &mut self.nodes[at] {
+                    *end = len;
+                }
+
+                self.open.pop();
+            }
+
+            if self.open.is_empty() {
+                break;
+            }
+        }
+
+        Ok(ValueRef::new(&self.nodes, reader.buffer(), 0))
+    }
+
+    /**
+     * @name len
+     * @description
+     *
+     * Number of nodes the last parsed value took.
+     */
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /*
+     * Reads one node, containers with their end left to patch
+     */
+    fn next_node(reader: &mut Reader<'_>) -> Result<Node> {
+        let marker = reader.pull_marker()?;
+
+        Ok(match marker {
+            0x00..=0x7f => Node::U8(marker),
+            0xe0..=0xff => Node::I8(marker as i8),
+
+            Families::NIL | Families::RESERVED => Node::Nil,
+            Families::FALSE | Families::TRUE => Node::Bool(marker == Families::TRUE),
+
+            Families::UINT8 => Node::U8(read_u8(marker, reader)?),
+            Families::UINT16 => Node::U16(read_u16(marker, reader)?),
+            Families::UINT32 => Node::U32(read_u32(marker, reader)?),
+            Families::UINT64 => Node::U64(read_u64(marker, reader)?),
+            Families::INT8 => Node::I8(read_i8(marker, reader)?),
+            Families::INT16 => Node::I16(read_i16(marker, reader)?),
+            Families::INT32 => Node::I32(read_i32(marker, reader)?),
+            Families::INT64 => Node::I64(read_i64(marker, reader)?),
+            Families::FLOAT32 => Node::F32(read_f32(marker, reader)?),
+            Families::FLOAT64 => Node::F64(read_f64(marker, reader)?),
+
+            0xa0..=0xbf | Families::STR8 | Families::STR16 | Families::STR32 => {
+                let len = read_str_len(marker, reader)?;
+                let start = reader.position();
+
+                reader.pull_borrowed(len)?;
+
+                Node::Str { start, len }
+            }
+
+            Families::BIN8 | Families::BIN16 | Families::BIN32 => {
+                let len = read_bin_len(marker, reader)?;
+                let start = reader.position();
+
+                reader.pull_borrowed(len)?;
+
+                Node::Bin { start, len }
+            }
+
+            Families::FIXEXT1
+            | Families::FIXEXT2
+            | Families::FIXEXT4
+            | Families::FIXEXT8
+            | Families::FIXEXT16
+            | Families::EXT8
+            | Families::EXT16
+            | Families::EXT32 => {
+                let (type_, len) = read_ext_header(marker, reader)?;
+                let start = reader.position();
+
+                reader.pull_borrowed(len)?;
+
+                Node::Extension { type_, start, len }
+            }
+
+            0x90..=0x9f | Array::ARRAY_16_TYPE | Array::ARRAY_32_TYPE => {
+                let len = read_array_len(marker, reader)?;
+
+                Node::Array {
+                    len: reader.limits().check_array(len)?,
+                    end: 0,
+                }
+            }
+
+            0x80..=0x8f | Map::MAP_16_TYPE | Map::MAP_32_TYPE => {
+                let len = read_map_len(marker, reader)?;
+
+                Node::Map {
+                    len: reader.limits().check_map(len)?,
+                    end: 0,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::String, vec, vec::Vec};
+
+    use crate::error::Error;
+    use crate::msgpack::WriteTo;
+    use crate::reader::Limits;
+
+    fn packet() -> Vec<u8> {
+        let mut buffer = Vec::new();
+
+        (
+            "update",
+            vec![(1u16, -5i32, 1.5f64), (300, 70000, -0.25)],
+            [("hp", 100u8), ("xp", 7)]
+                .into_iter()
+                .collect::<alloc::collections::BTreeMap<_, _>>(),
+            (Option::<u8>::None, &b"raw"[..], String::from("tail")),
+        )
+            .write_to(&mut buffer)
+            .unwrap();
+
+        buffer
+    }
+
+    #[test]
+    fn test_matches_pull_value() {
+        let buffer = packet();
+        let mut tape = Tape::new();
+
+        let root = tape.parse(&buffer).unwrap();
+
+        assert_eq!(root.to_value(), Reader::new(&buffer).pull_value().unwrap());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let buffer = packet();
+        let mut tape = Tape::with_capacity(32);
+
+        let root = tape.parse(&buffer).unwrap();
+
+        assert_eq!(root.len(), Some(4));
+        assert_eq!(root.get(0).unwrap().as_str(), Some("update"));
+        assert!(root.get(4).is_none());
+
+        let entities = root.get(1).unwrap();
+        let ids = entities
+            .iter()
+            .map(|entity| entity.get(0).unwrap().as_u64().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, [1, 300]);
+        assert_eq!(
+            entities.get(1).unwrap().get(1).unwrap().as_i64(),
+            Some(70000)
+        );
+        assert_eq!(entities.get(0).unwrap().get(2).unwrap().as_f64(), Some(1.5));
+
+        let stats = root.get(2).unwrap();
+
+        assert_eq!(stats.get_key("xp").and_then(|xp| xp.as_u64()), Some(7));
+        assert!(stats.get_key("mp").is_none());
+        assert_eq!(stats.entries().count(), 2);
+
+        let tail = root.get(3).unwrap();
+
+        assert!(tail.get(0).unwrap().is_nil());
+        assert_eq!(tail.get(1).unwrap().as_bin(), Some(&b"raw"[..]));
+        assert_eq!(tail.get(2).unwrap().as_str(), Some("tail"));
+
+        /*
+         * Scalars don't pretend to be containers
+         */
+        assert_eq!(tail.get(2).unwrap().len(), None);
+        assert_eq!(tail.get(2).unwrap().iter().count(), 0);
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        /*
+         * Far deeper than the stack would allow a recursive decoder
+         */
+        let mut buffer = vec![0x91; 100_000];
+
+        buffer.push(0x2a);
+
+        let mut tape = Tape::new();
+        let mut value = tape.parse(&buffer).unwrap();
+
+        for _ in 0..100_000 {
+            assert_eq!(value.len(), Some(1));
+            value = value.get(0).unwrap();
+        }
+
+        assert_eq!(value.as_u64(), Some(42));
+        assert_eq!(tape.len(), 100_001);
+
+        buffer.pop();
+
+        assert!(matches!(
+            Tape::new().parse(&buffer),
+            Err(Error::UnexpectedEof(_))
+        ));
+    }
+
+    #[test]
+    fn test_reuse_and_errors() {
+        let buffer = packet();
+        let mut tape = Tape::new();
+
+        let nodes = {
+            tape.parse(&buffer).unwrap();
+            tape.len()
+        };
+
+        assert_eq!(tape.parse(&[0x2a]).unwrap().as_u64(), Some(42));
+        assert_eq!(tape.len(), 1);
+        assert!(nodes > 1);
+
+        assert!(matches!(
+            tape.parse(&buffer[..buffer.len() - 1]),
+            Err(Error::UnexpectedEof(_))
+        ));
+
+        let limits = Limits {
+            max_array_len: 3,
+            ..Limits::DEFAULT
+        };
+
+        assert!(matches!(
+            tape.read(&mut Reader::with_limits(&buffer, limits)),
+            Err(Error::LimitExceeded("Array"))
+        ));
+    }
+}
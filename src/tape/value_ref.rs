@@ -0,0 +1,357 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use ordered_float::OrderedFloat;
+
+use crate::msgpack::ext::Extension;
+use crate::tape::Node;
+use crate::value::Value;
+
+/**
+ * @name ValueRef
+ * @description
+ *
+ * A value on a Tape.
+ *
+ * It's a cursor, copying or navigating it costs nothing: containers are
+ * indexed and iterated in place, and strings and binaries borrow the
+ * input buffer.
+ */
+#[derive(Clone, Copy)]
+pub struct ValueRef<'a> {
+    nodes: &'a [Node],
+    input: &'a [u8],
+    index: usize,
+}
+
+impl<'a> ValueRef<'a> {
+    #[inline(always)]
+    pub(crate) fn new(nodes: &'a [Node], input: &'a [u8], index: usize) -> Self {
+        ValueRef {
+            nodes,
+            input,
+            index,
+        }
+    }
+
+    #[inline(always)]
+    fn node(&self) -> Node {
+        self.nodes[self.index]
+    }
+
+    #[inline(always)]
+    fn at(&self, index: usize) -> ValueRef<'a> {
+        ValueRef::new(self.nodes, self.input, index)
+    }
+
+    /*
+     * Index of the node right after this value's subtree
+     */
+    #[inline(always)]
+    fn end(&self) -> usize {
+        match self.node() {
+            Node::Array { end, .. } | Node::Map { end, .. } => end,
+            _ => self.index + 1,
+        }
+    }
+
+    #[inline(always)]
+    fn slice(&self, start: usize, len: usize) -> &'a [u8] {
+        &self.input[start..start + len]
+    }
+
+    #[inline(always)]
+    pub fn is_nil(&self) -> bool {
+        matches!(self.node(), Node::Nil)
+    }
+
+    #[inline(always)]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node() {
+            Node::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /**
+     * @name as_u64
+     * @description
+     *
+     * Any integer that isn't negative, whatever width it was sent as.
+     */
+    #[inline]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.node() {
+            Node::U8(value) => Some(value as u64),
+            Node::U16(value) => Some(value as u64),
+            Node::U32(value) => Some(value as u64),
+            Node::U64(value) => Some(value),
+            _ => self.as_i64().and_then(|value| u64::try_from(value).ok()),
+        }
+    }
+
+    /**
+     * @name as_i64
+     * @description
+     *
+     * Any integer that fits into an i64, whatever width it was sent as.
+     */
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.node() {
+            Node::I8(value) => Some(value as i64),
+            Node::I16(value) => Some(value as i64),
+            Node::I32(value) => Some(value as i64),
+            Node::I64(value) => Some(value),
+            Node::U8(value) => Some(value as i64),
+            Node::U16(value) => Some(value as i64),
+            Node::U32(value) => Some(value as i64),
+            Node::U64(value) => i64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.node() {
+            Node::F32(value) => Some(value as f64),
+            Node::F64(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /**
+     * @name as_str
+     * @description
+     *
+     * The string, or None if it isn't one or isn't valid UTF-8.
+     */
+    #[inline]
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.node() {
+            Node::Str { start, len } => core::str::from_utf8(self.slice(start, len)).ok(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_bin(&self) -> Option<&'a [u8]> {
+        match self.node() {
+            Node::Bin { start, len } => Some(self.slice(start, len)),
+            _ => None,
+        }
+    }
+
+    /**
+     * @name as_extension
+     * @description
+     *
+     * The ext type and its borrowed data.
+     */
+    #[inline]
+    pub fn as_extension(&self) -> Option<(u8, &'a [u8])> {
+        match self.node() {
+            Node::Extension { type_, start, len } => Some((type_, self.slice(start, len))),
+            _ => None,
+        }
+    }
+
+    /**
+     * @name len
+     * @description
+     *
+     * Element count of an array or pair count of a map, None otherwise.
+     */
+    #[inline]
+    pub fn len(&self) -> Option<usize> {
+        match self.node() {
+            Node::Array { len, .. } | Node::Map { len, .. } => Some(len),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /**
+     * @name iter
+     * @description
+     *
+     * Elements of an array, nothing for anything else.
+     */
+    #[inline]
+    pub fn iter(&self) -> Elements<'a> {
+        let left = match self.node() {
+            Node::Array { len, .. } => len,
+            _ => 0,
+        };
+
+        Elements {
+            next: self.at(self.index + 1),
+            left,
+        }
+    }
+
+    /**
+     * @name entries
+     * @description
+     *
+     * Key-value pairs of a map, nothing for anything else.
+     */
+    #[inline]
+    pub fn entries(&self) -> Entries<'a> {
+        let left = match self.node() {
+            Node::Map { len, .. } => len * 2,
+            _ => 0,
+        };
+
+        Entries {
+            elements: Elements {
+                next: self.at(self.index + 1),
+                left,
+            },
+        }
+    }
+
+    /**
+     * @name get
+     * @description
+     *
+     * Element `index` of an array.
+     *
+     * Siblings are stepped over by their subtree's end, so it's linear in
+     * the array's length, not in its size.
+     */
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<ValueRef<'a>> {
+        self.iter().nth(index)
+    }
+
+    /**
+     * @name get_key
+     * @description
+     *
+     * Value of the first map entry whose key is the string `key`.
+     */
+    #[inline]
+    pub fn get_key(&self, key: &str) -> Option<ValueRef<'a>> {
+        self.entries()
+            .find(|(entry, _)| entry.as_str() == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    /**
+     * @name to_value
+     * @description
+     *
+     * Builds the equivalent Value, allocating its containers.
+     *
+     * Invalid UTF-8 reads as an empty string, like `Reader::pull_value`.
+     */
+    pub fn to_value(&self) -> Value<'a> {
+        match self.node() {
+            Node::Nil => Value::Nil,
+            Node::Bool(value) => Value::Bool(value),
+            Node::U8(value) => Value::U8(value),
+            Node::U16(value) => Value::U16(value),
+            Node::U32(value) => Value::U32(value),
+            Node::U64(value) => Value::U64(value),
+            Node::F32(value) => Value::F32(OrderedFloat(value)),
+            Node::F64(value) => Value::F64(OrderedFloat(value)),
+            Node::I8(value) => Value::I8(value),
+            Node::I16(value) => Value::I16(value),
+            Node::I32(value) => Value::I32(value),
+            Node::I64(value) => Value::I64(value),
+            Node::Str { .. } => Value::Str(self.as_str().unwrap_or("")),
+            Node::Bin { start, len } => Value::Bin(self.slice(start, len)),
+            Node::Extension { type_, start, len } => {
+                Value::Extension(Extension::new(type_, self.slice(start, len).to_vec()))
+            }
+            Node::Array { .. } => Value::Array(
+                self.iter()
+                    .map(|element| element.to_value())
+                    .collect::<Vec<_>>()
+                    .into_boxed_slice(),
+            ),
+            Node::Map { .. } => Value::Map(
+                self.entries()
+                    .map(|(key, value)| (key.to_value(), value.to_value()))
+                    .collect::<Box<[_]>>(),
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for ValueRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_value(), f)
+    }
+}
+
+/**
+ * @name Elements
+ * @description
+ *
+ * Iterator returned by `ValueRef::iter`.
+ */
+#[derive(Clone)]
+pub struct Elements<'a> {
+    next: ValueRef<'a>,
+    left: usize,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = ValueRef<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+
+        let element = self.next;
+
+        self.left -= 1;
+        self.next = element.at(element.end());
+
+        Some(element)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl ExactSizeIterator for Elements<'_> {}
+
+/**
+ * @name Entries
+ * @description
+ *
+ * Iterator returned by `ValueRef::entries`.
+ */
+#[derive(Clone)]
+pub struct Entries<'a> {
+    elements: Elements<'a>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (ValueRef<'a>, ValueRef<'a>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.elements.next()?, self.elements.next()?))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.elements.left / 2;
+
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Entries<'_> {}
@@ -0,0 +1,333 @@
+use alloc::vec::Vec;
+
+use ::bytes::BytesMut;
+use ::tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use ::tokio_util::codec::Decoder;
+
+use crate::{
+    bytes::BytesValue,
+    error::{Error, Result},
+    msgpack::WriteTo,
+    tokio::MsgpackCodec,
+};
+
+/**
+ * @name AsyncReader
+ * @description
+ *
+ * Reads msgpack values out of a tokio AsyncRead (a TcpStream, one half
+ * of a duplex...), the async counterpart of `stream::IoReader`.
+ *
+ * For a Stream of values, wrap the source in a `FramedRead` with a
+ * MsgpackCodec instead.
+ */
+pub struct AsyncReader<R: AsyncRead + Unpin> {
+    read: R,
+    codec: MsgpackCodec,
+    buffer: BytesMut,
+    capacity: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncReader<R> {
+    /**
+     * @name new
+     * @description
+     *
+     * Creates a reader of values back to back, pulling 8 KiB at a time.
+     */
+    #[inline]
+    pub fn new(read: R) -> Self {
+        AsyncReader::with_codec(MsgpackCodec::default(), 8 * 1024, read)
+    }
+
+    /**
+     * @name with_codec
+     * @description
+     *
+     * Creates a reader with custom framing and limits, pulling up to
+     * `capacity` bytes at a time.
+     */
+    #[inline]
+    pub fn with_codec(codec: MsgpackCodec, capacity: usize, read: R) -> Self {
+        AsyncReader {
+            read,
+            codec,
+            buffer: BytesMut::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /**
+     * @name read_value
+     * @description
+     *
+     * Reads the next value, or None once the source ended cleanly
+     * between two values.
+     *
+     * A source that ends inside a value is an `UnexpectedEof` error.
+     */
+    pub async fn read_value(&mut self) -> Result<Option<BytesValue>> {
+        loop {
+            if let Some(value) = self.codec.decode(&mut self.buffer)? {
+                return Ok(Some(value));
+            }
+
+            self.buffer.reserve(self.capacity);
+
+            if self.read.read_buf(&mut self.buffer).await? == 0 {
+                return match self.buffer.is_empty() {
+                    true => Ok(None),
+                    false => Err(Error::UnexpectedEof("stream")),
+                };
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_ref(&self) -> &R {
+        &self.read
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+/**
+ * @name write_value
+ * @description
+ *
+ * Encodes a value and writes it to a tokio AsyncWrite in one go.
+ *
+ * The value is encoded into a buffer first, so nothing is written if
+ * encoding fails.
+ */
+pub async fn write_value<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: WriteTo + ?Sized,
+{
+    let mut buffer = Vec::new();
+
+    value.write_to(&mut buffer)?;
+    writer.write_all(&buffer).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    use ::futures::{SinkExt, StreamExt};
+    use ::tokio::io::duplex;
+    use ::tokio_util::codec::{FramedRead, FramedWrite};
+
+    use crate::{bytes::Utf8Bytes, tokio::Framing};
+
+    fn chat(index: u16) -> (&'static str, (String, u16)) {
+        ("ch", (String::from("hello"), index))
+    }
+
+    #[tokio::test]
+    async fn test_async_reader() {
+        /*
+         * A tiny duplex buffer splits nearly every value
+         */
+        let (mut client, server) = duplex(5);
+
+        let writer = tokio::spawn(async move {
+            for index in 0..20 {
+                write_value(&mut client, &chat(index)).await.unwrap();
+            }
+        });
+
+        let mut reader = AsyncReader::with_codec(MsgpackCodec::default(), 3, server);
+        let mut values = Vec::new();
+
+        while let Some(value) = reader.read_value().await.unwrap() {
+            values.push(value);
+        }
+
+        writer.await.unwrap();
+
+        assert_eq!(values.len(), 20);
+        assert_eq!(
+            values[7],
+            BytesValue::Array(vec![
+                BytesValue::Str(Utf8Bytes::from_static("ch")),
+                BytesValue::Array(vec![
+                    BytesValue::Str(Utf8Bytes::from_static("hello")),
+                    BytesValue::U16(7),
+                ]),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_framed() {
+        for framing in [
+            Framing::Delimited { max_len: 64 },
+            Framing::LengthPrefixed { max_len: 64 },
+        ] {
+            let (client, server) = duplex(7);
+
+            let writer = tokio::spawn(async move {
+                let mut sink = FramedWrite::new(client, MsgpackCodec::new(framing));
+
+                for index in 0..20 {
+                    sink.send(chat(index)).await.unwrap();
+                }
+            });
+
+            let values = FramedRead::new(server, MsgpackCodec::new(framing))
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await;
+
+            writer.await.unwrap();
+
+            assert_eq!(values.len(), 20);
+            assert_eq!(values[19], {
+                let mut buffer = Vec::new();
+
+                chat(19).write_to(&mut buffer).unwrap();
+                BytesValue::decode(&buffer.into()).unwrap()
+            });
+        }
+    }
+
+    fn codec_of(max_len: usize) -> MsgpackCodec {
+        MsgpackCodec::new(Framing::LengthPrefixed { max_len })
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (mut client, server) = duplex(64);
+
+        /*
+         * The source ends inside a value
+         */
+        client.write_all(&[0x92, 0x01]).await.unwrap();
+        drop(client);
+
+        let mut reader = AsyncReader::new(server);
+
+        assert!(matches!(
+            reader.read_value().await,
+            Err(Error::UnexpectedEof("stream"))
+        ));
+
+        let (mut client, server) = duplex(64);
+
+        client.write_all(&[0, 0, 1, 0]).await.unwrap();
+
+        let mut reader = AsyncReader::with_codec(codec_of(64), 64, server);
+
+        assert!(matches!(
+            reader.read_value().await,
+            Err(Error::LimitExceeded("Frame"))
+        ));
+
+        /*
+         * Two values in one frame
+         */
+        let (mut client, server) = duplex(64);
+
+        client.write_all(&[0, 0, 0, 2, 0x01, 0x02]).await.unwrap();
+
+        let mut reader = AsyncReader::with_codec(codec_of(64), 64, server);
+
+        assert!(reader.read_value().await.is_err());
+
+        let mut buffer = BytesMut::new();
+
+        assert!(matches!(
+            ::tokio_util::codec::Encoder::encode(&mut codec_of(2), "too long", &mut buffer),
+            Err(Error::LimitExceeded("Frame"))
+        ));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_delimited_max_len() {
+        let mut codec = MsgpackCodec::new(Framing::Delimited { max_len: 64 });
+
+        /*
+         * A claimed 4 GiB string is refused before anything is reserved
+         */
+        let mut buffer = BytesMut::from(&[0xdb, 0xff, 0xff, 0xff, 0xff][..]);
+
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(Error::LimitExceeded("Frame"))
+        ));
+        assert!(buffer.capacity() < 64);
+
+        /*
+         * So is a huge array, even though each element may be small
+         */
+        let mut buffer = BytesMut::from(&[0xdd, 0x00, 0x01, 0x00, 0x00][..]);
+
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(Error::LimitExceeded("Frame"))
+        ));
+
+        let mut packet = Vec::new();
+
+        chat(1).write_to(&mut packet).unwrap();
+
+        let mut buffer = BytesMut::from(&packet[..]);
+
+        assert!(codec.decode(&mut buffer).unwrap().is_some());
+        assert!(matches!(
+            ::tokio_util::codec::Encoder::encode(
+                &mut MsgpackCodec::new(Framing::Delimited { max_len: 2 }),
+                "too long",
+                &mut buffer
+            ),
+            Err(Error::LimitExceeded("Frame"))
+        ));
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut deep = vec![0x91; 100_000];
+
+        deep.push(0xc0);
+
+        for framing in [
+            Framing::default(),
+            Framing::LengthPrefixed { max_len: 1 << 20 },
+        ] {
+            let mut buffer = BytesMut::new();
+
+            if framing != Framing::default() {
+                buffer.extend_from_slice(&(deep.len() as u32).to_be_bytes());
+            }
+
+            buffer.extend_from_slice(&deep);
+
+            assert!(matches!(
+                MsgpackCodec::new(framing).decode(&mut buffer),
+                Err(Error::LimitExceeded("Depth"))
+            ));
+        }
+
+        let limits = crate::reader::Limits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let mut codec = MsgpackCodec::with_limits(Framing::default(), limits);
+        let mut buffer = BytesMut::from(&[0x91, 0x01, 0x91, 0x91, 0x01][..]);
+
+        assert!(codec.decode(&mut buffer).unwrap().is_some());
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(Error::LimitExceeded("Depth"))
+        ));
+    }
+}
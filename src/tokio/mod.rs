@@ -0,0 +1,236 @@
+use ::bytes::{Buf, BufMut, BytesMut};
+use ::tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    bytes::{BytesValue, write_to_buf},
+    error::{Error, Result},
+    msgpack::WriteTo,
+    reader::{Limits, Scan, scan_owing, validate_with},
+    stream,
+};
+
+mod io;
+
+pub use io::{AsyncReader, write_value};
+
+/**
+ * @name Framing
+ * @description
+ *
+ * How messages are delimited on the stream.
+ *
+ * Either way a message may take at most `max_len` bytes, so a peer
+ * can't make the buffer grow without bounds. The default is delimited
+ * with the stream Decoder's limit.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /*
+     * Values back to back: msgpack values are self-delimiting
+     */
+    Delimited { max_len: usize },
+
+    /*
+     * Each value is preceded by its length as a big endian u32, and
+     * frames longer than `max_len` are refused
+     */
+    LengthPrefixed { max_len: usize },
+}
+
+impl Default for Framing {
+    #[inline(always)]
+    fn default() -> Self {
+        Framing::Delimited {
+            max_len: stream::Decoder::DEFAULT_MAX_LEN,
+        }
+    }
+}
+
+/**
+ * @name MsgpackCodec
+ * @description
+ *
+ * tokio_util codec turning a byte stream into BytesValues and anything
+ * WriteTo into bytes, for FramedRead, FramedWrite and Framed.
+ *
+ * Decoded values point into the frame they came from instead of copying
+ * their strings and binaries.
+ *
+ * ```
+ * use bytes::BytesMut;
+ * use msgpackp::tokio::{Framing, MsgpackCodec};
+ * use tokio_util::codec::{Decoder, Encoder};
+ *
+ * let mut codec = MsgpackCodec::new(Framing::LengthPrefixed { max_len: 1024 });
+ * let mut buffer = BytesMut::new();
+ *
+ * codec.encode(("ch", 1u8), &mut buffer).unwrap();
+ *
+ * assert_eq!(&buffer[..4], &[0, 0, 0, 5]);
+ * assert!(codec.decode(&mut buffer).unwrap().is_some());
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MsgpackCodec {
+    framing: Framing,
+    limits: Limits,
+
+    /*
+     * Buffer length the last incomplete scan asked for
+     */
+    needed: usize,
+}
+
+impl MsgpackCodec {
+    #[inline(always)]
+    pub fn new(framing: Framing) -> Self {
+        MsgpackCodec::with_limits(framing, Limits::DEFAULT)
+    }
+
+    /**
+     * @name with_limits
+     * @description
+     *
+     * Creates a codec whose frames are scanned and decoded with custom
+     * limits, nesting depth included.
+     */
+    #[inline(always)]
+    pub fn with_limits(framing: Framing, limits: Limits) -> Self {
+        MsgpackCodec {
+            framing,
+            limits,
+            needed: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    #[inline(always)]
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    fn decode_delimited(
+        &mut self,
+        src: &mut BytesMut,
+        max_len: usize,
+    ) -> Result<Option<BytesValue>> {
+        if src.len() < self.needed {
+            return Ok(None);
+        }
+
+        match scan_owing(src, &self.limits)? {
+            (Scan::Incomplete(missing), owed) => {
+                /*
+                 * Every value still owed takes a byte at least, and the
+                 * declared lengths are only trusted up to `max_len`
+                 */
+                if src.len().saturating_add(missing).saturating_add(owed) > max_len {
+                    return Err(Error::LimitExceeded("Frame"));
+                }
+
+                self.needed = src.len() + missing;
+                src.reserve(missing);
+
+                Ok(None)
+            }
+            (Scan::Complete(len), _) if len > max_len => Err(Error::LimitExceeded("Frame")),
+            (Scan::Complete(len), _) => {
+                self.needed = 0;
+
+                BytesValue::decode_with(&src.split_to(len).freeze(), self.limits).map(Some)
+            }
+        }
+    }
+
+    fn decode_prefixed(
+        &mut self,
+        src: &mut BytesMut,
+        max_len: usize,
+    ) -> Result<Option<BytesValue>> {
+        let Some(prefix) = src.get(..4) else {
+            return Ok(None);
+        };
+
+        let len = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+
+        if len > max_len {
+            return Err(Error::LimitExceeded("Frame"));
+        }
+
+        if src.len() < 4 + len {
+            src.reserve(4 + len - src.len());
+
+            return Ok(None);
+        }
+
+        src.advance(4);
+
+        let frame = src.split_to(len).freeze();
+
+        /*
+         * The frame has to hold exactly one value
+         */
+        if validate_with(&frame, &self.limits)? != len {
+            return Err(Error::Message("Trailing bytes after the value"));
+        }
+
+        BytesValue::decode_with(&frame, self.limits).map(Some)
+    }
+}
+
+impl Decoder for MsgpackCodec {
+    type Item = BytesValue;
+    type Error = Error;
+
+    #[inline]
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        match self.framing {
+            Framing::Delimited { max_len } => self.decode_delimited(src, max_len),
+            Framing::LengthPrefixed { max_len } => self.decode_prefixed(src, max_len),
+        }
+    }
+}
+
+impl<T: WriteTo> Encoder<T> for MsgpackCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<()> {
+        let start = dst.len();
+
+        let (header, max_len) = match self.framing {
+            Framing::Delimited { max_len } => (0, max_len),
+            Framing::LengthPrefixed { max_len } => {
+                dst.put_u32(0);
+
+                (4, max_len.min(u32::MAX as usize))
+            }
+        };
+
+        let written = write_to_buf(&item, dst).and_then(|_| match dst.len() - start - header {
+            len if len > max_len => Err(Error::LimitExceeded("Frame")),
+            len => Ok(len),
+        });
+
+        /*
+         * Never leave half a frame behind
+         */
+        let len = match written {
+            Ok(len) => len,
+            Err(error) => {
+                dst.truncate(start);
+
+                return Err(error);
+            }
+        };
+
+        if header == 4 {
+            dst[start..start + 4].copy_from_slice(&(len as u32).to_be_bytes());
+        }
+
+        Ok(())
+    }
+}
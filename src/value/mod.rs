@@ -1,13 +1,22 @@
-use std::{fmt, io::Write};
+use alloc::{borrow::ToOwned, boxed::Box};
+use core::fmt;
 
 use crate::{
     constants::Families,
-    msgpack::{WriteTo, ext::Extension},
+    encoder::{Encoder, EncoderConfig},
+    helpers::fnv::Fnv1a,
+    io::Write,
+    msgpack::{ReadFrom, WriteTo, bin::write_bin_header, ext::Extension},
+    reader::Reader,
 };
 
-use anyhow::Result;
+use crate::error::Result;
 use ordered_float::OrderedFloat;
 
+mod owned;
+
+pub use owned::OwnedValue;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Value<'a> {
     Nil,
@@ -23,6 +32,7 @@ pub enum Value<'a> {
     I32(i32),
     I64(i64),
     Str(&'a str),
+    Bin(&'a [u8]),
     Array(Box<[Value<'a>]>),
     Map(Box<[(Value<'a>, Value<'a>)]>),
     Extension(Extension),
@@ -36,14 +46,13 @@ impl<'a> Value<'a> {
 
     /**
      * @name bin
-     * @description According to msgpack spec, Extension is built
-     * from a Vec<u8>
+     * @description
      *
-     * I assume that moomoo.io's server doesn't use any extensions whatsoever.
+     * Raw bytes, borrowed from the buffer just like `str`.
      */
     #[inline(always)]
-    pub fn bin(value: Vec<u8>) -> Self {
-        Value::Extension(Extension::new(Families::BIN8, value))
+    pub fn bin(value: &'a [u8]) -> Self {
+        Value::Bin(value)
     }
 
     #[inline(always)]
@@ -128,6 +137,24 @@ impl<'a> Value<'a> {
     pub fn extension(value: Extension) -> Self {
         Value::Extension(value)
     }
+
+    /**
+     * @name canonical_hash
+     * @description
+     *
+     * Stable 64-bit hash (FNV-1a) of the canonical encoding.
+     *
+     * Values that only differ in integer widths, map order or NaN
+     * payloads hash the same on every peer. Fails like canonical
+     * encoding does, e.g. on duplicate map keys.
+     */
+    pub fn canonical_hash(&self) -> Result<u64> {
+        let mut encoder = Encoder::with_config(Fnv1a::new(), EncoderConfig::canonical());
+
+        encoder.write_value(self)?;
+
+        Ok(encoder.finish()?.finish())
+    }
 }
 
 impl fmt::Display for Value<'_> {
@@ -150,6 +177,7 @@ impl fmt::Display for Value<'_> {
             Value::I32(value) => write!(f, "{}", value),
             Value::I64(value) => write!(f, "{}", value),
             Value::Str(value) => write!(f, "{}", value),
+            Value::Bin(value) => write!(f, "{:?}", value),
             Value::Array(value) => write!(f, "{:?}", value),
             Value::Map(value) => write!(f, "{:?}", value),
             Value::Extension(value) => write!(f, "{:?}", value),
@@ -174,6 +202,10 @@ impl WriteTo for Value<'_> {
             Value::I32(value) => value.write_to(buffer)?,
             Value::I64(value) => value.write_to(buffer)?,
             Value::Str(value) => value.to_owned().to_owned().write_to(buffer)?,
+            Value::Bin(value) => {
+                write_bin_header(value.len(), buffer)?;
+                buffer.write_all(value)?
+            }
             Value::Array(value) => value.write_to(buffer)?,
             Value::Map(value) => value.write_to(buffer)?,
             Value::Nil => buffer.write_all(&Families::NIL.to_be_bytes())?,
@@ -184,3 +216,10 @@ impl WriteTo for Value<'_> {
         Ok(())
     }
 }
+
+impl<'buf> ReadFrom<'buf> for Value<'buf> {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'buf>) -> Result<Self> {
+        reader.pull_value()
+    }
+}
@@ -0,0 +1,168 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use ordered_float::OrderedFloat;
+
+use crate::{
+    constants::Families,
+    error::Result,
+    io::Write,
+    msgpack::{
+        ReadFrom, WriteTo, array::write_array_header, bin::write_bin_header, ext::Extension,
+        map::write_map_header, string::write_str_header,
+    },
+    reader::Reader,
+    value::Value,
+};
+
+/**
+ * @name OwnedValue
+ * @description
+ *
+ * A Value that owns its strings and binaries.
+ *
+ * Use it when the value has to outlive the buffer it was decoded
+ * from, or when it's built from a source that can't lend `&str`s.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OwnedValue {
+    Nil,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(OrderedFloat<f32>),
+    F64(OrderedFloat<f64>),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Str(String),
+    Bin(Vec<u8>),
+    Array(Vec<OwnedValue>),
+    Map(Vec<(OwnedValue, OwnedValue)>),
+    Extension(Extension),
+}
+
+impl OwnedValue {
+    /**
+     * @name as_value
+     * @description
+     *
+     * Borrows the strings and binaries back as a Value.
+     */
+    pub fn as_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::Nil => Value::Nil,
+            OwnedValue::Bool(value) => Value::Bool(*value),
+            OwnedValue::U8(value) => Value::U8(*value),
+            OwnedValue::U16(value) => Value::U16(*value),
+            OwnedValue::U32(value) => Value::U32(*value),
+            OwnedValue::U64(value) => Value::U64(*value),
+            OwnedValue::F32(value) => Value::F32(*value),
+            OwnedValue::F64(value) => Value::F64(*value),
+            OwnedValue::I8(value) => Value::I8(*value),
+            OwnedValue::I16(value) => Value::I16(*value),
+            OwnedValue::I32(value) => Value::I32(*value),
+            OwnedValue::I64(value) => Value::I64(*value),
+            OwnedValue::Str(value) => Value::Str(value),
+            OwnedValue::Bin(value) => Value::Bin(value),
+            OwnedValue::Array(values) => Value::Array(values.iter().map(Self::as_value).collect()),
+            OwnedValue::Map(values) => Value::Map(
+                values
+                    .iter()
+                    .map(|(key, value)| (key.as_value(), value.as_value()))
+                    .collect::<Box<[_]>>(),
+            ),
+            OwnedValue::Extension(value) => Value::Extension(value.clone()),
+        }
+    }
+}
+
+impl From<&Value<'_>> for OwnedValue {
+    fn from(value: &Value<'_>) -> Self {
+        match value {
+            Value::Nil => OwnedValue::Nil,
+            Value::Bool(value) => OwnedValue::Bool(*value),
+            Value::U8(value) => OwnedValue::U8(*value),
+            Value::U16(value) => OwnedValue::U16(*value),
+            Value::U32(value) => OwnedValue::U32(*value),
+            Value::U64(value) => OwnedValue::U64(*value),
+            Value::F32(value) => OwnedValue::F32(*value),
+            Value::F64(value) => OwnedValue::F64(*value),
+            Value::I8(value) => OwnedValue::I8(*value),
+            Value::I16(value) => OwnedValue::I16(*value),
+            Value::I32(value) => OwnedValue::I32(*value),
+            Value::I64(value) => OwnedValue::I64(*value),
+            Value::Str(value) => OwnedValue::Str(String::from(*value)),
+            Value::Bin(value) => OwnedValue::Bin(value.to_vec()),
+            Value::Array(values) => OwnedValue::Array(values.iter().map(Self::from).collect()),
+            Value::Map(values) => OwnedValue::Map(
+                values
+                    .iter()
+                    .map(|(key, value)| (Self::from(key), Self::from(value)))
+                    .collect(),
+            ),
+            Value::Extension(value) => OwnedValue::Extension(value.clone()),
+        }
+    }
+}
+
+impl From<Value<'_>> for OwnedValue {
+    #[inline(always)]
+    fn from(value: Value<'_>) -> Self {
+        OwnedValue::from(&value)
+    }
+}
+
+impl WriteTo for OwnedValue {
+    fn write_to<U: Write>(&self, buffer: &mut U) -> Result<()> {
+        match self {
+            OwnedValue::Nil => buffer.write_all(&[Families::NIL])?,
+            OwnedValue::Bool(value) => value.write_to(buffer)?,
+            OwnedValue::U8(value) => value.write_to(buffer)?,
+            OwnedValue::U16(value) => value.write_to(buffer)?,
+            OwnedValue::U32(value) => value.write_to(buffer)?,
+            OwnedValue::U64(value) => value.write_to(buffer)?,
+            OwnedValue::F32(value) => value.write_to(buffer)?,
+            OwnedValue::F64(value) => value.write_to(buffer)?,
+            OwnedValue::I8(value) => value.write_to(buffer)?,
+            OwnedValue::I16(value) => value.write_to(buffer)?,
+            OwnedValue::I32(value) => value.write_to(buffer)?,
+            OwnedValue::I64(value) => value.write_to(buffer)?,
+            OwnedValue::Str(value) => {
+                write_str_header(value.len(), buffer)?;
+                buffer.write_all(value.as_bytes())?
+            }
+            OwnedValue::Bin(value) => {
+                write_bin_header(value.len(), buffer)?;
+                buffer.write_all(value)?
+            }
+            OwnedValue::Array(values) => {
+                write_array_header(values.len(), buffer)?;
+
+                for value in values {
+                    value.write_to(buffer)?;
+                }
+            }
+            OwnedValue::Map(values) => {
+                write_map_header(values.len(), buffer)?;
+
+                for (key, value) in values {
+                    key.write_to(buffer)?;
+                    value.write_to(buffer)?;
+                }
+            }
+            OwnedValue::Extension(value) => value.write_to(buffer)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl<'buf> ReadFrom<'buf> for OwnedValue {
+    #[inline(always)]
+    fn read_from(reader: &mut Reader<'buf>) -> Result<Self> {
+        reader.pull_value().map(OwnedValue::from)
+    }
+}